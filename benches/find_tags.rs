@@ -0,0 +1,59 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use nostro2::NostrNote;
+use std::hint::black_box;
+
+const NOTES: usize = 10_000;
+
+// Notes with ten tags each: four `p`, four `e` and two `t`.
+fn notes() -> Vec<NostrNote> {
+    (0..NOTES)
+        .map(|i| {
+            let hex = |tag: usize| format!("{:064x}", i * 10 + tag);
+            let tags = serde_json::json!([
+                ["p", hex(0), "wss://relay.example.com"],
+                ["p", hex(1)],
+                ["p", hex(2)],
+                ["p", hex(3)],
+                ["e", hex(4), "wss://relay.example.com", "root"],
+                ["e", hex(5), "", "reply"],
+                ["e", hex(6)],
+                ["e", hex(7)],
+                ["t", "nostr"],
+                ["t", format!("topic{}", i)],
+            ]);
+            serde_json::from_value(serde_json::json!({
+                "pubkey": hex(8),
+                "created_at": 1_700_000_000 + i,
+                "kind": 1,
+                "tags": tags,
+                "content": "",
+            }))
+            .expect("Failed to build note")
+        })
+        .collect()
+}
+
+fn find_tags(c: &mut Criterion) {
+    let notes = notes();
+    let mut group = c.benchmark_group("find_tags");
+    group.bench_function("ref", |b| {
+        b.iter(|| {
+            notes
+                .iter()
+                .map(|note| note.tags.find_tags_ref(black_box("p")).count())
+                .sum::<usize>()
+        })
+    });
+    group.bench_function("owned", |b| {
+        b.iter(|| {
+            notes
+                .iter()
+                .map(|note| note.tags.find_tags_owned(black_box("p")).len())
+                .sum::<usize>()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, find_tags);
+criterion_main!(benches);
@@ -1,7 +1,9 @@
 use bech32::{Bech32, Hrp};
 use bip39::Language;
 
-use secp256k1::{rand::rngs::OsRng, Keypair, Secp256k1};
+use secp256k1::{rand::rngs::OsRng, schnorr, Keypair, Secp256k1, SecretKey, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 use crate::{
     nips::{Nip04, Nip44},
@@ -13,46 +15,187 @@ pub struct NostrKeypair {
     keypair: Keypair,
     extractable: bool,
 }
+// Overwrites the secret key in place, leaving a fixed dummy key behind.
+impl zeroize::Zeroize for NostrKeypair {
+    fn zeroize(&mut self) {
+        self.keypair.non_secure_erase();
+        self.extractable = false;
+        // `non_secure_erase` uses plain writes; observing the result through
+        // `black_box` keeps them from being optimized away before a drop.
+        core::hint::black_box(&self.keypair);
+    }
+}
+impl Drop for NostrKeypair {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+impl zeroize::ZeroizeOnDrop for NostrKeypair {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignerError {
+    InvalidHex(String),
+    InvalidNsec(String),
+    InvalidSecretKey,
+    InvalidPubkey(String),
+    Serialization(String),
+}
+impl std::fmt::Display for SignerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SignerError::InvalidHex(reason) => write!(f, "Invalid hex private key: {}", reason),
+            SignerError::InvalidNsec(reason) => write!(f, "Invalid nsec: {}", reason),
+            SignerError::InvalidSecretKey => write!(f, "Invalid private key"),
+            SignerError::InvalidPubkey(reason) => write!(f, "Invalid public key: {}", reason),
+            SignerError::Serialization(reason) => write!(f, "Failed to serialize note: {}", reason),
+        }
+    }
+}
+impl std::error::Error for SignerError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DerivationError {
+    InvalidMnemonic(String),
+    InvalidSeed(usize),
+    InvalidPath(String),
+    InvalidChildKey,
+}
+impl std::fmt::Display for DerivationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DerivationError::InvalidMnemonic(reason) => write!(f, "Invalid mnemonic: {}", reason),
+            DerivationError::InvalidSeed(len) => {
+                write!(f, "Seeds must be 16 to 64 bytes, got {}", len)
+            }
+            DerivationError::InvalidPath(path) => write!(f, "Invalid derivation path: {}", path),
+            DerivationError::InvalidChildKey => write!(f, "Derived key is out of range"),
+        }
+    }
+}
+impl std::error::Error for DerivationError {}
+
+/// The NIP-06 path of the first account.
+pub const NOSTR_DERIVATION_PATH: &str = "m/44'/1237'/0'/0/0";
+const HARDENED: u32 = 1 << 31;
+
+/// A NIP-26 delegation from `pubkey` to `delegatee`, valid under `conditions`.
+/// `token` is the delegator's Schnorr signature over the delegation string.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DelegationToken {
+    pub pubkey: String,
+    pub delegatee: String,
+    pub conditions: String,
+    pub token: String,
+}
+impl DelegationToken {
+    fn digest(delegatee: &str, conditions: &str) -> [u8; 32] {
+        let delegation = format!("nostr:delegation:{}:{}", delegatee, conditions);
+        Sha256::digest(delegation.as_bytes()).into()
+    }
+}
+
+// Only extractable keys serialize their nsec, so non-extractable keys can be
+// persisted for display but not restored.
+impl Serialize for NostrKeypair {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("NostrKeypair", 3)?;
+        state.serialize_field("pubkey", &self.public_key())?;
+        let nsec = self.extractable.then(|| self.get_nsec());
+        state.serialize_field("nsec", &nsec)?;
+        state.serialize_field("extractable", &self.extractable)?;
+        state.end()
+    }
+}
+impl<'de> Deserialize<'de> for NostrKeypair {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct KeypairFields {
+            pubkey: Option<String>,
+            nsec: Option<String>,
+            seckey: Option<String>,
+            #[serde(default)]
+            extractable: bool,
+        }
+        let fields = KeypairFields::deserialize(deserializer)?;
+        let keypair = match (fields.nsec, fields.seckey) {
+            (Some(nsec), _) if nsec.starts_with("nsec") => {
+                Self::parse_private_key(&nsec, fields.extractable)
+            }
+            (Some(_), _) => Err(SignerError::InvalidNsec("Invalid nsec prefix".to_string())),
+            (None, Some(seckey)) => Self::from_hex(&seckey, fields.extractable),
+            (None, None) => return Err(serde::de::Error::missing_field("nsec")),
+        }
+        .map_err(serde::de::Error::custom)?;
+        if fields
+            .pubkey
+            .is_some_and(|pubkey| pubkey != keypair.public_key())
+        {
+            return Err(serde::de::Error::custom(
+                "pubkey does not match the secret key",
+            ));
+        }
+        Ok(keypair)
+    }
+}
 
 impl NostrKeypair {
     pub fn new(private_key: &str) -> anyhow::Result<Self> {
-        let secp = Secp256k1::signing_only();
-        let keypair = match private_key.starts_with("nsec") {
-            true => {
-                let (hrp, data) = bech32::decode(&private_key)?;
-                if hrp.to_string() != "nsec" {
-                    anyhow::bail!("Invalid nsec prefix");
-                }
-                Keypair::from_seckey_slice(&secp, &data)
-                    .map_err(|_| anyhow::anyhow!("Invalid private key"))?
-            }
-            false => Keypair::from_seckey_str(&secp, &private_key)
-                .map_err(|_| anyhow::anyhow!("Invalid private key"))?,
-        };
-        Ok(Self {
-            keypair,
-            extractable: false,
-        })
+        Ok(Self::parse_private_key(private_key, false)?)
     }
     pub fn new_extractable(private_key: &str) -> anyhow::Result<Self> {
-        let secp = Secp256k1::signing_only();
-        let keypair = match private_key.starts_with("nsec") {
-            true => {
-                let (hrp, data) = bech32::decode(&private_key)?;
-                if hrp.to_string() != "nsec" {
-                    anyhow::bail!("Invalid nsec prefix");
-                }
-                Keypair::from_seckey_slice(&secp, &data)
-                    .map_err(|_| anyhow::anyhow!("Invalid private key"))?
-            }
-            false => Keypair::from_seckey_str(&secp, &private_key)
-                .map_err(|_| anyhow::anyhow!("Invalid private key"))?,
-        };
+        Ok(Self::parse_private_key(private_key, true)?)
+    }
+    pub fn from_bytes(private_key: &[u8; 32], extractable: bool) -> Result<Self, SignerError> {
+        let secret_key =
+            SecretKey::from_byte_array(private_key).map_err(|_| SignerError::InvalidSecretKey)?;
         Ok(Self {
-            keypair,
-            extractable: true,
+            keypair: Keypair::from_secret_key(&Secp256k1::signing_only(), &secret_key),
+            extractable,
         })
     }
+    /// Same as `from_bytes`, for raw 32-byte secrets such as those from other libraries.
+    pub fn from_entropy(bytes: &[u8; 32], extractable: bool) -> Result<Self, SignerError> {
+        Self::from_bytes(bytes, extractable)
+    }
+    /// Like `from_entropy`, failing with `InvalidSecretKey` unless `bytes` is 32 long.
+    pub fn from_entropy_slice(bytes: &[u8], extractable: bool) -> Result<Self, SignerError> {
+        let bytes: &[u8; 32] = bytes
+            .try_into()
+            .map_err(|_| SignerError::InvalidSecretKey)?;
+        Self::from_entropy(bytes, extractable)
+    }
+    pub fn from_hex(private_key: &str, extractable: bool) -> Result<Self, SignerError> {
+        if private_key.len() != 64 || !private_key.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(SignerError::InvalidHex(
+                "expected 64 hex characters".to_string(),
+            ));
+        }
+        let mut key_bytes = [0u8; 32];
+        key_bytes.copy_from_slice(&Self::hex_decode(private_key));
+        Self::from_bytes(&key_bytes, extractable)
+    }
+    fn parse_private_key(private_key: &str, extractable: bool) -> Result<Self, SignerError> {
+        if !private_key.starts_with("nsec") {
+            return Self::from_hex(private_key, extractable);
+        }
+        let (hrp, data) =
+            bech32::decode(private_key).map_err(|e| SignerError::InvalidNsec(e.to_string()))?;
+        if hrp.to_string() != "nsec" {
+            return Err(SignerError::InvalidNsec("Invalid nsec prefix".to_string()));
+        }
+        let key_bytes: [u8; 32] = data
+            .as_slice()
+            .try_into()
+            .map_err(|_| SignerError::InvalidSecretKey)?;
+        Self::from_bytes(&key_bytes, extractable)
+    }
     pub fn generate(extractable: bool) -> Self {
         let keypair = Keypair::new(&Secp256k1::signing_only(), &mut OsRng);
         Self {
@@ -61,28 +204,84 @@ impl NostrKeypair {
         }
     }
     pub fn public_key(&self) -> String {
-        return self.keypair.public_key().x_only_public_key().0.to_string();
+        self.keypair.public_key().x_only_public_key().0.to_string()
     }
     pub fn public_key_slice(&self) -> [u8; 32] {
-        return self.keypair.public_key().x_only_public_key().0.serialize();
+        self.keypair.public_key().x_only_public_key().0.serialize()
     }
     pub fn npub(&self) -> String {
         let hrp = Hrp::parse("npub").expect("valid hrp");
         let pk_data = self.keypair.public_key().x_only_public_key().0.serialize();
-        let string = bech32::encode::<Bech32>(hrp, &pk_data).expect("failed to encode string");
-        string
+        bech32::encode::<Bech32>(hrp, &pk_data).expect("failed to encode string")
     }
 
+    /// Computes the note id and signs it. The note's pubkey has to be set to 64 hex
+    /// characters first, it is not compared against this keypair.
+    pub fn sign_note(&self, note: &mut NostrNote) -> Result<(), SignerError> {
+        if !NostrNote::is_hex(&note.pubkey, 64) {
+            return Err(SignerError::InvalidPubkey(note.pubkey.clone()));
+        }
+        note.serialize_id()
+            .map_err(|e| SignerError::Serialization(e.to_string()))?;
+        let id = note
+            .id_bytes()
+            .ok_or_else(|| SignerError::Serialization("Missing note id".to_string()))?;
+        let secp = Secp256k1::signing_only();
+        note.sig = Some(
+            secp.sign_schnorr_no_aux_rand(&id, &self.keypair)
+                .to_string(),
+        );
+        Ok(())
+    }
+    /// Panics when the note cannot be signed.
+    #[deprecated(note = "use `sign_note`, which returns signing errors")]
     pub fn sign_nostr_event(&self, note: &mut NostrNote) {
-        if note.serialize_id().is_ok() {
-            let secp = Secp256k1::signing_only();
-            let sig = secp
-                .sign_schnorr_no_aux_rand(note.id_bytes().as_ref().unwrap(), &self.keypair)
-                .to_string();
-            note.sig = Some(sig);
+        self.sign_note(note).expect("Failed to sign note");
+    }
+    pub fn sign_delegation(
+        &self,
+        delegatee_pubkey: &str,
+        conditions: &str,
+    ) -> Result<DelegationToken, SignerError> {
+        if delegatee_pubkey.len() != 64
+            || XOnlyPublicKey::from_slice(&Self::hex_decode(delegatee_pubkey)).is_err()
+        {
+            return Err(SignerError::InvalidPubkey(delegatee_pubkey.to_string()));
         }
+        let digest = DelegationToken::digest(delegatee_pubkey, conditions);
+        let token = Secp256k1::signing_only().sign_schnorr_no_aux_rand(&digest, &self.keypair);
+        Ok(DelegationToken {
+            pubkey: self.public_key(),
+            delegatee: delegatee_pubkey.to_string(),
+            conditions: conditions.to_string(),
+            token: token.to_string(),
+        })
     }
-    pub fn get_shared_point(&self, public_key_string: &String) -> anyhow::Result<[u8; 32]> {
+    pub fn verify_delegation(token: &DelegationToken) -> bool {
+        let Ok(public_key) = XOnlyPublicKey::from_slice(&Self::hex_decode(&token.pubkey)) else {
+            return false;
+        };
+        let Ok(signature) = <[u8; 64]>::try_from(Self::hex_decode(&token.token)) else {
+            return false;
+        };
+        let digest = DelegationToken::digest(&token.delegatee, &token.conditions);
+        Secp256k1::verification_only()
+            .verify_schnorr(
+                &schnorr::Signature::from_byte_array(signature),
+                &digest,
+                &public_key,
+            )
+            .is_ok()
+    }
+    /// Checks that `note` is validly signed and was signed by this keypair.
+    pub fn verify_note(&self, note: &NostrNote) -> bool {
+        note.pubkey == self.public_key() && Self::verify_note_from_anyone(note)
+    }
+    /// Checks the signature and content hash of `note`, whoever signed it.
+    pub fn verify_note_from_anyone(note: &NostrNote) -> bool {
+        note.verify()
+    }
+    pub fn get_shared_point(&self, public_key_string: &str) -> anyhow::Result<[u8; 32]> {
         let hex_pk = Self::hex_decode(public_key_string);
         let x_only_public_key = secp256k1::XOnlyPublicKey::from_slice(hex_pk.as_slice())?;
         let public_key = secp256k1::PublicKey::from_x_only_public_key(
@@ -115,7 +314,7 @@ impl NostrKeypair {
 
     pub fn encrypt_nip_44_plaintext(
         &self,
-        plaintext: String,
+        plaintext: impl AsRef<str>,
         pubkey: String,
     ) -> anyhow::Result<String> {
         let nip_44 = Nip44::new(self.clone(), pubkey);
@@ -123,21 +322,19 @@ impl NostrKeypair {
     }
     pub fn decrypt_nip_44_plaintext(
         &self,
-        cyphertext: String,
+        cyphertext: impl AsRef<str>,
         pubkey: String,
     ) -> anyhow::Result<String> {
         let nip_44 = Nip44::new(self.clone(), pubkey);
-        nip_44.nip_44_decrypt(cyphertext)
+        Ok(nip_44.nip_44_decrypt(cyphertext)?)
     }
     pub fn sign_nip_04_encrypted(
         &self,
         note: &mut NostrNote,
         pubkey: String,
     ) -> anyhow::Result<()> {
-        note.tags.add_pubkey_tag(&pubkey);
-        let encrypted_content = self.encrypt_nip_04_plaintext(note.content.to_string(), pubkey)?;
-        note.content = encrypted_content;
-        self.sign_nostr_event(note);
+        Nip04::new(self.clone(), pubkey.clone()).nip04_encrypt_note_for(note, &pubkey, None)?;
+        self.sign_note(note)?;
         Ok(())
     }
     pub fn decrypt_nip_04_content(&self, signed_note: &NostrNote) -> anyhow::Result<String> {
@@ -152,10 +349,8 @@ impl NostrKeypair {
         note: &mut NostrNote,
         pubkey: String,
     ) -> anyhow::Result<()> {
-        note.tags.add_pubkey_tag(&pubkey);
-        let encrypted_content = self.encrypt_nip_44_plaintext(note.content.to_string(), pubkey)?;
-        note.content = encrypted_content;
-        self.sign_nostr_event(note);
+        Nip44::new(self.clone(), pubkey.clone()).nip44_encrypt_note_for(note, &pubkey, None)?;
+        self.sign_note(note)?;
         Ok(())
     }
     pub fn decrypt_nip_44_content(&self, signed_note: &NostrNote) -> anyhow::Result<String> {
@@ -176,8 +371,7 @@ impl NostrKeypair {
         }
         let secret_key = self.keypair.secret_key().secret_bytes();
         let hrp = Hrp::parse("nsec").expect("valid hrp");
-        let string = bech32::encode::<Bech32>(hrp, &secret_key).expect("failed to encode string");
-        string
+        bech32::encode::<Bech32>(hrp, &secret_key).expect("failed to encode string")
     }
     pub fn get_mnemonic_phrase(&self) -> String {
         if !self.extractable {
@@ -198,13 +392,9 @@ impl NostrKeypair {
     pub fn parse_mnemonic(mnemonic: &str, extractable: bool) -> anyhow::Result<Self> {
         let english_parse = bip39::Mnemonic::parse_in(Language::English, mnemonic);
         let spanish_parse = bip39::Mnemonic::parse_in(Language::Spanish, mnemonic);
-        if english_parse.is_err() && spanish_parse.is_err() {
-            anyhow::bail!("Invalid mnemonic phrase");
-        }
-        let mnemonic = if english_parse.is_ok() {
-            english_parse.unwrap()
-        } else {
-            spanish_parse.unwrap()
+        let mnemonic = match (english_parse, spanish_parse) {
+            (Ok(mnemonic), _) | (_, Ok(mnemonic)) => mnemonic,
+            _ => anyhow::bail!("Invalid mnemonic phrase"),
         };
         let secret_key = mnemonic
             .to_entropy()
@@ -216,6 +406,77 @@ impl NostrKeypair {
             false => Ok(Self::new(&secret_key)?),
         }
     }
+    /// NIP-06 key for `path` from an English BIP-39 mnemonic, without a passphrase.
+    /// Unlike `parse_mnemonic`, this goes through the BIP-32 seed, like other clients do.
+    pub fn derive(mnemonic: &str, path: &str) -> Result<Self, DerivationError> {
+        let mnemonic = bip39::Mnemonic::parse_in(Language::English, mnemonic)
+            .map_err(|e| DerivationError::InvalidMnemonic(e.to_string()))?;
+        Self::derive_from_entropy(&mnemonic.to_seed(""), path)
+    }
+    /// BIP-32 child key for `path`, such as `m/44'/1237'/0'/0/0`, from a 16 to 64
+    /// byte seed. Hardened steps may be written with `'` or `h`.
+    pub fn derive_from_entropy(entropy: &[u8], path: &str) -> Result<Self, DerivationError> {
+        if !(16..=64).contains(&entropy.len()) {
+            return Err(DerivationError::InvalidSeed(entropy.len()));
+        }
+        let indexes = Self::parse_derivation_path(path)?;
+        let (mut key, mut chain_code) = Self::hmac_split(b"Bitcoin seed", entropy);
+        let mut secret_key =
+            SecretKey::from_byte_array(&key).map_err(|_| DerivationError::InvalidChildKey)?;
+        let secp = Secp256k1::new();
+        for index in indexes {
+            let mut data = Vec::with_capacity(37);
+            if index >= HARDENED {
+                data.push(0);
+                data.extend_from_slice(&secret_key.secret_bytes());
+            } else {
+                data.extend_from_slice(&secret_key.public_key(&secp).serialize());
+            }
+            data.extend_from_slice(&index.to_be_bytes());
+            (key, chain_code) = Self::hmac_split(&chain_code, &data);
+            let tweak = secp256k1::Scalar::from_be_bytes(key)
+                .map_err(|_| DerivationError::InvalidChildKey)?;
+            secret_key = secret_key
+                .add_tweak(&tweak)
+                .map_err(|_| DerivationError::InvalidChildKey)?;
+        }
+        Ok(Self {
+            keypair: Keypair::from_secret_key(&secp, &secret_key),
+            extractable: false,
+        })
+    }
+    fn parse_derivation_path(path: &str) -> Result<Vec<u32>, DerivationError> {
+        let invalid = || DerivationError::InvalidPath(path.to_string());
+        let mut steps = path.split('/');
+        if steps.next() != Some("m") {
+            return Err(invalid());
+        }
+        steps
+            .map(|step| {
+                let (index, hardened) = match step.strip_suffix(['\'', 'h']) {
+                    Some(index) => (index, HARDENED),
+                    None => (step, 0),
+                };
+                match index.parse::<u32>() {
+                    Ok(index) if index < HARDENED => Ok(index | hardened),
+                    _ => Err(invalid()),
+                }
+            })
+            .collect()
+    }
+    // HMAC-SHA512 halves: the key material and the chain code.
+    fn hmac_split(key: &[u8], data: &[u8]) -> ([u8; 32], [u8; 32]) {
+        use hmac::{Hmac, Mac};
+        let mut mac =
+            Hmac::<sha2::Sha512>::new_from_slice(key).expect("HMAC accepts keys of any length");
+        mac.update(data);
+        let output = mac.finalize().into_bytes();
+        let mut left = [0u8; 32];
+        let mut right = [0u8; 32];
+        left.copy_from_slice(&output[..32]);
+        right.copy_from_slice(&output[32..]);
+        (left, right)
+    }
     fn hex_decode(hex_string: &str) -> Vec<u8> {
         hex_string
             .as_bytes()
@@ -225,6 +486,21 @@ impl NostrKeypair {
     }
 }
 
+/// General purpose entry point, accepts either a hex or an `nsec` private key.
+/// The resulting keypair is not extractable.
+impl TryFrom<&str> for NostrKeypair {
+    type Error = SignerError;
+    fn try_from(private_key: &str) -> Result<Self, Self::Error> {
+        Self::parse_private_key(private_key, false)
+    }
+}
+impl TryFrom<&[u8]> for NostrKeypair {
+    type Error = SignerError;
+    fn try_from(private_key: &[u8]) -> Result<Self, Self::Error> {
+        Self::from_entropy_slice(private_key, false)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -253,6 +529,201 @@ mod tests {
         assert_eq!(nsec_npub, npub);
     }
 
+    #[test]
+    fn test_nip19_reference_vectors() {
+        let nsec = "nsec1vl029mgpspedva04g90vltkh6fvh240zqtv9k0t9af8935ke9laqsnlfe5";
+        let seckey = "67dea2ed018072d675f5415ecfaed7d2597555e202d85b3d65ea4e58d2d92ffa";
+        let from_nsec = NostrKeypair::new_extractable(nsec).unwrap();
+        assert_eq!(from_nsec, NostrKeypair::new_extractable(seckey).unwrap());
+        assert_eq!(
+            from_nsec.get_secret_key().to_vec(),
+            NostrKeypair::hex_decode(seckey)
+        );
+        assert_eq!(from_nsec.get_nsec(), nsec);
+
+        // npub and nsec must carry the raw 32 key bytes, not their hex text.
+        let npub = "npub10elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qzvjptg";
+        let pubkey = "7e7e9c42a91bfef19fa929e5fda1b72e0ebc1a4c1141673e2794234d86addf4e";
+        let (hrp, data) = bech32::decode(npub).unwrap();
+        assert_eq!(hrp.to_string(), "npub");
+        assert_eq!(data, NostrKeypair::hex_decode(pubkey));
+        let (_, data) = bech32::decode(&from_nsec.npub()).unwrap();
+        assert_eq!(data, from_nsec.public_key_slice());
+    }
+
+    #[test]
+    fn test_delegation() {
+        let delegator = NostrKeypair::generate(false);
+        let delegatee = "477318cfb5427b9cfc66a9fa376150c1ddbc62115ae27cef72417eb959691396";
+        let conditions = "kind=1&created_at>1674834236&created_at<1677426236";
+        let token = delegator.sign_delegation(delegatee, conditions).unwrap();
+        assert_eq!(token.pubkey, delegator.public_key());
+        assert!(NostrKeypair::verify_delegation(&token));
+        let widened = DelegationToken {
+            conditions: "kind=1".to_string(),
+            ..token.clone()
+        };
+        assert!(!NostrKeypair::verify_delegation(&widened));
+        let stolen = DelegationToken {
+            delegatee: delegator.public_key(),
+            ..token.clone()
+        };
+        assert!(!NostrKeypair::verify_delegation(&stolen));
+        assert!(matches!(
+            delegator.sign_delegation("not a pubkey", conditions),
+            Err(SignerError::InvalidPubkey(_))
+        ));
+
+        let delegatee_keys = NostrKeypair::generate(false);
+        let mut note = NostrNote {
+            pubkey: delegatee_keys.public_key(),
+            content: "delegated".to_string(),
+            ..Default::default()
+        };
+        note.add_delegation_tag(&token.pubkey, &token.conditions, &token.token);
+        delegatee_keys
+            .sign_note(&mut note)
+            .expect("Failed to sign note");
+        let value: serde_json::Value = serde_json::from_str(&note.to_string()).unwrap();
+        assert_eq!(
+            value["tags"],
+            serde_json::json!([["delegation", token.pubkey, conditions, token.token]])
+        );
+        assert!(note.verify());
+    }
+
+    #[test]
+    fn test_verify_note() {
+        let author = NostrKeypair::generate(false);
+        let peer = NostrKeypair::generate(false);
+        let mut note = NostrNote {
+            pubkey: author.public_key(),
+            content: "verified".to_string(),
+            ..Default::default()
+        };
+        author.sign_note(&mut note).expect("Failed to sign note");
+        assert!(author.verify_note(&note));
+        assert!(!peer.verify_note(&note));
+        assert!(NostrKeypair::verify_note_from_anyone(&note));
+
+        note.content = "tampered".to_string();
+        assert!(!author.verify_note(&note));
+        assert!(!NostrKeypair::verify_note_from_anyone(&note));
+    }
+
+    #[test]
+    fn test_sign_note_errors() {
+        let keys = NostrKeypair::generate(false);
+        for pubkey in ["", "npub1notahexkey", &"zz".repeat(32)] {
+            let mut note = NostrNote {
+                pubkey: pubkey.to_string(),
+                ..Default::default()
+            };
+            assert_eq!(
+                keys.sign_note(&mut note),
+                Err(SignerError::InvalidPubkey(pubkey.to_string()))
+            );
+            assert_eq!(note.sig, None);
+            assert_eq!(note.id, None);
+        }
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    #[should_panic(expected = "Failed to sign note")]
+    fn test_sign_nostr_event_panics() {
+        let keys = NostrKeypair::generate(false);
+        keys.sign_nostr_event(&mut NostrNote::default());
+    }
+
+    #[test]
+    fn test_keypair_serde() {
+        let hex_key = "a992011980303ea8c43f66087634283026e7796e7fcea8b61710239e19ee28c8";
+        let pubkey = "689403d3808274889e371cfe53c2d78eb05743a964cc60d3b2e55824e8fe740a";
+        let nsec = "nsec14xfqzxvqxql233plvcy8vdpgxqnww7tw0l823dshzq3eux0w9ryqulcv53";
+        let extractable = NostrKeypair::new_extractable(hex_key).unwrap();
+        let value = serde_json::to_value(&extractable).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"pubkey": pubkey, "nsec": nsec, "extractable": true})
+        );
+        let restored: NostrKeypair = serde_json::from_value(value).unwrap();
+        assert_eq!(restored, extractable);
+
+        let locked = NostrKeypair::new(hex_key).unwrap();
+        let value = serde_json::to_value(&locked).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"pubkey": pubkey, "nsec": null, "extractable": false})
+        );
+        assert!(serde_json::from_value::<NostrKeypair>(value).is_err());
+
+        let from_seckey: NostrKeypair =
+            serde_json::from_value(serde_json::json!({"seckey": hex_key})).unwrap();
+        assert_eq!(from_seckey, locked);
+        let wrong_pubkey = serde_json::json!({"pubkey": "a".repeat(64), "seckey": hex_key});
+        assert!(serde_json::from_value::<NostrKeypair>(wrong_pubkey).is_err());
+        let hex_as_nsec = serde_json::json!({"nsec": hex_key});
+        assert!(serde_json::from_value::<NostrKeypair>(hex_as_nsec).is_err());
+    }
+
+    #[test]
+    fn test_key_entry_points() {
+        let hex_key = "a992011980303ea8c43f66087634283026e7796e7fcea8b61710239e19ee28c8";
+        let mut key_bytes = [0u8; 32];
+        key_bytes.copy_from_slice(&NostrKeypair::hex_decode(hex_key));
+        let from_bytes = NostrKeypair::from_bytes(&key_bytes, false).unwrap();
+        let from_hex = NostrKeypair::from_hex(hex_key, false).unwrap();
+        let from_str = NostrKeypair::try_from(hex_key).unwrap();
+        let from_nsec = NostrKeypair::try_from(
+            "nsec14xfqzxvqxql233plvcy8vdpgxqnww7tw0l823dshzq3eux0w9ryqulcv53",
+        )
+        .unwrap();
+        assert_eq!(from_bytes, from_hex);
+        assert_eq!(from_hex, from_str);
+        assert_eq!(from_str, from_nsec);
+        assert!(matches!(
+            NostrKeypair::from_hex("not hex", false),
+            Err(SignerError::InvalidHex(_))
+        ));
+        assert!(matches!(
+            NostrKeypair::from_hex(&"z".repeat(64), false),
+            Err(SignerError::InvalidHex(_))
+        ));
+        assert_eq!(
+            NostrKeypair::from_bytes(&[0u8; 32], false),
+            Err(SignerError::InvalidSecretKey)
+        );
+        assert!(matches!(
+            NostrKeypair::try_from("nsec1invalid"),
+            Err(SignerError::InvalidNsec(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_entropy() {
+        let generated = NostrKeypair::generate(true);
+        let secret = generated.get_secret_key();
+        let from_entropy = NostrKeypair::from_entropy(&secret, false).unwrap();
+        assert_eq!(from_entropy.public_key(), generated.public_key());
+        let from_slice = NostrKeypair::from_entropy_slice(&secret[..], true).unwrap();
+        assert_eq!(from_slice.public_key(), generated.public_key());
+        assert_eq!(from_slice.get_secret_key(), secret);
+        let from_try = NostrKeypair::try_from(&secret[..]).unwrap();
+        assert_eq!(from_try.public_key(), generated.public_key());
+
+        for bytes in [&secret[..31], &[secret.as_slice(), &[0]].concat(), &[]] {
+            assert_eq!(
+                NostrKeypair::from_entropy_slice(bytes, false),
+                Err(SignerError::InvalidSecretKey)
+            );
+        }
+        assert_eq!(
+            NostrKeypair::from_entropy(&[0xff; 32], false),
+            Err(SignerError::InvalidSecretKey)
+        );
+    }
+
     #[test]
     fn test_mnemonic() {
         let user_keys = NostrKeypair::generate(true);
@@ -272,6 +743,109 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_derive() {
+        let expected_key = |hex: &str| NostrKeypair::from_hex(hex, false).unwrap().public_key();
+        // NIP-06 test vectors.
+        let keys = NostrKeypair::derive(
+            "leader monkey parrot ring guide accident before fence cannon height naive bean",
+            NOSTR_DERIVATION_PATH,
+        )
+        .unwrap();
+        assert_eq!(
+            keys.public_key(),
+            expected_key("7f7ff03d123792d6ac594bfa67bf6d0c0ab55b6b1fdb6249303fe861f1ccba9a")
+        );
+        assert_eq!(keys.get_secret_key(), [0u8; 32]);
+        let keys = NostrKeypair::derive(
+            "what bleak badge arrange retreat wolf trade produce cricket blur garlic valid proud rude strong choose busy staff weather area salt hollow arm fade",
+            "m/44h/1237h/0h/0/0",
+        )
+        .unwrap();
+        assert_eq!(
+            keys.public_key(),
+            expected_key("c15d739894c81a2fcfd3a2df85a0d2c0dbc47a280d092799f144d73d7ae78add")
+        );
+
+        // BIP-32 test vector 1, with hardened and normal steps.
+        let seed = NostrKeypair::hex_decode("000102030405060708090a0b0c0d0e0f");
+        for (path, hex) in [
+            (
+                "m",
+                "e8f32e723decf4051aefac8e2c93c9c5b214313817cdb01a1494b917c8436b35",
+            ),
+            (
+                "m/0'",
+                "edb2e14f9ee77d26dd93b4ecede8d16ed408ce149b6cd80b0715a2d911a0afea",
+            ),
+            (
+                "m/0'/1",
+                "3c6cb8d0f6a264c91ea8b5030fadaa8e538b020f0a387421a12de9319dc93368",
+            ),
+            (
+                "m/0'/1/2'/2/1000000000",
+                "471b76e389e528d6de6d816857e012c5455051cad6660850e58372a6c3e6e7c8",
+            ),
+        ] {
+            assert_eq!(
+                NostrKeypair::derive_from_entropy(&seed, path)
+                    .unwrap()
+                    .public_key(),
+                expected_key(hex)
+            );
+        }
+    }
+
+    #[test]
+    fn test_derive_errors() {
+        let seed = [7u8; 32];
+        for path in ["", "44'/0'", "m/", "m/x", "m/-1", "m/2147483648", "m/0''"] {
+            assert_eq!(
+                NostrKeypair::derive_from_entropy(&seed, path).map(|_| ()),
+                Err(DerivationError::InvalidPath(path.to_string()))
+            );
+        }
+        assert_eq!(
+            NostrKeypair::derive_from_entropy(&[0u8; 8], "m").map(|_| ()),
+            Err(DerivationError::InvalidSeed(8))
+        );
+        assert!(matches!(
+            NostrKeypair::derive("not a mnemonic", NOSTR_DERIVATION_PATH),
+            Err(DerivationError::InvalidMnemonic(_))
+        ));
+    }
+
+    #[test]
+    fn test_zeroize() {
+        use zeroize::Zeroize;
+        let secret = NostrKeypair::generate(true).get_secret_key();
+        let contains_secret = |bytes: &[u8]| bytes.windows(32).any(|window| window == secret);
+
+        let mut keys = NostrKeypair::from_bytes(&secret, true).unwrap();
+        let public_key = keys.public_key();
+        keys.zeroize();
+        assert_eq!(keys.get_secret_key(), [0u8; 32]);
+        assert!(!contains_secret(&keys.keypair.secret_bytes()));
+        assert_ne!(keys.public_key(), public_key);
+
+        let mut slot = std::mem::MaybeUninit::new(NostrKeypair::from_bytes(&secret, true).unwrap());
+        let memory = |slot: &std::mem::MaybeUninit<NostrKeypair>| {
+            // SAFETY: the slot stays allocated, a keypair has no padding bytes and
+            // dropping one only overwrites it in place.
+            unsafe {
+                std::slice::from_raw_parts(
+                    slot.as_ptr().cast::<u8>(),
+                    std::mem::size_of::<NostrKeypair>(),
+                )
+                .to_vec()
+            }
+        };
+        assert!(contains_secret(&memory(&slot)));
+        // SAFETY: the slot holds an initialized keypair, dropped exactly once.
+        unsafe { slot.assume_init_drop() };
+        assert!(!contains_secret(&memory(&slot)));
+    }
+
     #[test]
     fn test_extractable() {
         let user_keys = NostrKeypair::generate(true);
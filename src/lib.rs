@@ -1,4 +1,20 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+extern crate alloc;
+
+#[cfg(not(feature = "alloc"))]
+compile_error!("nostro2 requires either the `std` or the `alloc` feature");
+
+#[cfg(feature = "std")]
 pub mod nips;
 pub mod notes;
+#[cfg(feature = "std")]
 pub mod relays;
+#[cfg(feature = "std")]
 pub mod keypair;
+#[cfg(all(
+    test,
+    any(feature = "nip05", feature = "nip11"),
+    not(target_arch = "wasm32")
+))]
+mod test_util;
+pub use notes::NostrNote;
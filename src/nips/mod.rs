@@ -1,5 +1,50 @@
 mod nip_04;
+#[cfg(feature = "nip05")]
+mod nip_05;
+mod nip_09;
+mod nip_13;
+mod nip_19;
+mod nip_21;
+mod nip_23;
+mod nip_25;
+mod nip_26;
+mod nip_28;
+mod nip_32;
+mod nip_42;
 mod nip_44;
 pub mod nip_46;
-pub use nip_04::Nip04;
-pub use nip_44::Nip44;
+mod nip_47;
+mod nip_51;
+mod nip_57;
+mod nip_65;
+mod nip_78;
+mod nip_82;
+mod nip_89;
+mod nip_94;
+pub use nip_04::{Nip04, Nip04Error};
+#[cfg(feature = "nip05")]
+pub use nip_05::{Nip05, Nip05Error};
+pub use nip_09::{DeletionRequest, Nip09Error};
+pub use nip_13::{Nip13, Nip13Error};
+pub use nip_19::{AddressPointer, EventPointer, Nip19, Nip19Error, NostrAddress, ProfilePointer};
+pub use nip_21::{Nip21Error, NostrUri};
+pub use nip_23::{Article, ArticleBuilder, Nip23Error};
+pub use nip_25::{Nip25Error, Reaction};
+pub use nip_26::{DelegationConditions, Nip26, Nip26Error};
+pub use nip_28::{
+    ChannelCreate, ChannelHide, ChannelMessage, ChannelMetadata, ChannelMute, Nip28Error,
+};
+pub use nip_32::{LabelBuilder, LabelEntry, LabelNote, LabelTarget, Nip32Error};
+pub use nip_42::Nip42;
+pub use nip_44::{Nip44, Nip44Error};
+pub use nip_47::{
+    Nip47Error, WalletConnectUri, WalletRequest, WalletResponse, WalletResponsePayload,
+    WalletTransaction,
+};
+pub use nip_51::{Nip51Error, NoteList, NoteListBuilder};
+pub use nip_57::{Nip57Error, ZapReceipt, ZapRequest};
+pub use nip_65::{Nip65Error, RelayEntry, RelayListMetadata, RelayMarker};
+pub use nip_78::{AppData, Nip78Error};
+pub use nip_82::Nip82;
+pub use nip_89::{AppHandler, Nip89Error};
+pub use nip_94::{FileMetadata, FileMetadataBuilder, Nip94Error};
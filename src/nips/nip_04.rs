@@ -2,7 +2,22 @@ use base64::{engine::general_purpose, Engine as _};
 use libaes::Cipher;
 use secp256k1::rand::{thread_rng, Rng};
 
-use crate::keypair::NostrKeypair;
+use crate::{keypair::NostrKeypair, notes::NostrNote};
+
+#[derive(Debug)]
+pub enum Nip04Error {
+    EncryptionError(String),
+    DecryptionError(String),
+}
+impl std::fmt::Display for Nip04Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Nip04Error::EncryptionError(e) => write!(f, "Encryption error: {}", e),
+            Nip04Error::DecryptionError(e) => write!(f, "Decryption error: {}", e),
+        }
+    }
+}
+impl std::error::Error for Nip04Error {}
 
 pub struct Nip04 {
     private_key: NostrKeypair,
@@ -23,17 +38,39 @@ impl Nip04 {
         cipher.set_auto_padding(true);
         let cyphertext = cipher.cbc_encrypt(&iv, plaintext.as_bytes());
         let base_64_cyphertext = general_purpose::STANDARD.encode(&cyphertext);
-        let base_64_iv = general_purpose::STANDARD.encode(&iv);
+        let base_64_iv = general_purpose::STANDARD.encode(iv);
         Ok(format!("{}?iv={}", base_64_cyphertext, base_64_iv))
     }
 
+    pub fn nip04_encrypt_note_for(
+        &self,
+        note: &mut NostrNote,
+        recipient_pubkey: &str,
+        relay_hint: Option<&str>,
+    ) -> Result<(), Nip04Error> {
+        let recipient = Nip04::new(self.private_key.clone(), recipient_pubkey.to_string());
+        let encrypted_content = recipient
+            .encrypt(note.content.to_string())
+            .map_err(|e| Nip04Error::EncryptionError(e.to_string()))?;
+        note.tags
+            .add_pubkey_tag_with_relay(recipient_pubkey, relay_hint);
+        note.content = encrypted_content;
+        Ok(())
+    }
+
     pub fn decrypt(&self, cyphertext: String) -> anyhow::Result<String> {
         let shared_secret = self.private_key.get_shared_point(&self.peer_pubkey)?;
         let mut parts = cyphertext.split('?');
         let base_64_cyphertext = parts.next().ok_or(anyhow::anyhow!("No cyphertext"))?;
-        let base_64_iv = &parts.next().ok_or(anyhow::anyhow!("No IV"))?[3..]; // skip "iv="
+        let base_64_iv = parts
+            .next()
+            .and_then(|iv| iv.strip_prefix("iv="))
+            .ok_or(anyhow::anyhow!("No IV"))?;
         let cyphertext = general_purpose::STANDARD.decode(base_64_cyphertext.as_bytes())?;
         let iv = general_purpose::STANDARD.decode(base_64_iv.as_bytes())?;
+        if iv.len() != 16 {
+            anyhow::bail!("Invalid IV length");
+        }
         let mut cipher = Cipher::new_256(&shared_secret);
         cipher.set_auto_padding(true);
         let plaintext = cipher.cbc_decrypt(&iv, &cyphertext);
@@ -43,7 +80,7 @@ impl Nip04 {
 
 #[cfg(test)]
 mod tests {
-    use crate::{nips::nip_46::Nip46Request, notes::NostrNote, keypair::NostrKeypair};
+    use crate::{keypair::NostrKeypair, nips::nip_46::Nip46Request, notes::NostrNote};
 
     use super::*;
 
@@ -65,6 +102,40 @@ mod tests {
         assert_eq!(plaintext, "{\"id\":\"2fm12v\",\"method\":\"connect\",\"params\":[\"62dfdb53ea2282ef478f7cdbf77938ec1add74b2bcbc8d862cfe1df24ac72cba\",\"\",\"sign_event:1985,sign_event:3,sign_event:30000\"]}");
     }
 
+    #[test]
+    fn test_encrypt_note_for() {
+        let sender = NostrKeypair::generate(false);
+        let recipient = NostrKeypair::generate(false);
+        let mut note = NostrNote {
+            pubkey: sender.public_key(),
+            content: "secret".to_string(),
+            kind: 4,
+            ..Default::default()
+        };
+        let relay_hint = Some("wss://relay.example.com");
+        Nip04::new(sender.clone(), recipient.public_key())
+            .nip04_encrypt_note_for(&mut note, &recipient.public_key(), relay_hint)
+            .expect("Failed to encrypt note");
+        assert_ne!(note.content, "secret");
+        assert_eq!(
+            serde_json::to_value(&note.tags).unwrap(),
+            serde_json::json!([["p", recipient.public_key(), "wss://relay.example.com"]])
+        );
+        sender.sign_note(&mut note).expect("Failed to sign note");
+        assert_eq!(recipient.decrypt_nip_04_content(&note).unwrap(), "secret");
+    }
+
+    #[test]
+    fn test_decrypt_malformed() {
+        let nip04 = Nip04::new(
+            NostrKeypair::generate(false),
+            NostrKeypair::generate(false).public_key(),
+        );
+        for cyphertext in ["", "abc", "abc?x", "abc?iv=", "PXvfOGMy?iv=AAAA"] {
+            assert!(nip04.decrypt(cyphertext.to_string()).is_err());
+        }
+    }
+
     #[test]
     fn third_test() {
         let note_str = r#"
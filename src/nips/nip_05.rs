@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip05Error {
+    InvalidIdentifier(String),
+    Network(String),
+    Parse(String),
+    PubkeyMismatch { expected: String, found: String },
+}
+impl std::fmt::Display for Nip05Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Nip05Error::InvalidIdentifier(identifier) => {
+                write!(f, "Invalid NIP-05 identifier: {}", identifier)
+            }
+            Nip05Error::Network(e) => write!(f, "Failed to fetch nostr.json: {}", e),
+            Nip05Error::Parse(e) => write!(f, "Failed to parse nostr.json: {}", e),
+            Nip05Error::PubkeyMismatch { expected, found } => {
+                write!(
+                    f,
+                    "Expected pubkey {}, nostr.json lists {}",
+                    expected, found
+                )
+            }
+        }
+    }
+}
+impl std::error::Error for Nip05Error {}
+
+#[derive(Debug, Deserialize)]
+struct Nip05Document {
+    #[serde(default)]
+    names: HashMap<String, String>,
+    #[serde(default)]
+    relays: HashMap<String, Vec<String>>,
+}
+
+/// Resolves `name@domain` identifiers through `https://<domain>/.well-known/nostr.json`.
+pub struct Nip05;
+
+impl Nip05 {
+    /// `Ok(true)` when the identifier maps to `expected_pubkey`, `Ok(false)` when the
+    /// domain does not list the name, and `PubkeyMismatch` when it lists another key.
+    pub async fn verify(identifier: &str, expected_pubkey: &str) -> Result<bool, Nip05Error> {
+        let (name, domain) = Self::parse_identifier(identifier)?;
+        Self::verify_at(&format!("https://{}", domain), &name, expected_pubkey).await
+    }
+    /// Relays the domain recommends for the identifier, empty when it lists none.
+    pub async fn lookup_relays(identifier: &str) -> Result<Vec<String>, Nip05Error> {
+        let (name, domain) = Self::parse_identifier(identifier)?;
+        Self::lookup_relays_at(&format!("https://{}", domain), &name).await
+    }
+    // A bare domain is the `_@domain` root identifier.
+    fn parse_identifier(identifier: &str) -> Result<(String, String), Nip05Error> {
+        let (name, domain) = identifier.split_once('@').unwrap_or(("_", identifier));
+        let name = name.to_lowercase();
+        let valid_name = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        let valid_domain = !domain.is_empty() && !domain.contains(['/', '@', '?', '#']);
+        if !valid_name || !valid_domain {
+            return Err(Nip05Error::InvalidIdentifier(identifier.to_string()));
+        }
+        Ok((name, domain.to_string()))
+    }
+    async fn verify_at(
+        base_url: &str,
+        name: &str,
+        expected_pubkey: &str,
+    ) -> Result<bool, Nip05Error> {
+        let document = Self::fetch(base_url, name).await?;
+        match document.names.get(name) {
+            None => Ok(false),
+            Some(pubkey) if pubkey.eq_ignore_ascii_case(expected_pubkey) => Ok(true),
+            Some(pubkey) => Err(Nip05Error::PubkeyMismatch {
+                expected: expected_pubkey.to_string(),
+                found: pubkey.to_string(),
+            }),
+        }
+    }
+    async fn lookup_relays_at(base_url: &str, name: &str) -> Result<Vec<String>, Nip05Error> {
+        let mut document = Self::fetch(base_url, name).await?;
+        Ok(document
+            .names
+            .get(name)
+            .and_then(|pubkey| document.relays.remove(pubkey))
+            .unwrap_or_default())
+    }
+    async fn fetch(base_url: &str, name: &str) -> Result<Nip05Document, Nip05Error> {
+        let url = url::Url::parse_with_params(
+            &format!("{}/.well-known/nostr.json", base_url),
+            &[("name", name)],
+        )
+        .map_err(|e| Nip05Error::InvalidIdentifier(e.to_string()))?;
+        // NIP-05 fetchers must not follow redirects.
+        #[cfg(not(target_arch = "wasm32"))]
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| Nip05Error::Network(e.to_string()))?;
+        #[cfg(target_arch = "wasm32")]
+        let client = reqwest::Client::new();
+        let response = client
+            .get(url)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| Nip05Error::Network(e.to_string()))?;
+        // Unfollowed redirects are not errors to reqwest.
+        if !response.status().is_success() {
+            return Err(Nip05Error::Network(format!(
+                "Unexpected status {}",
+                response.status()
+            )));
+        }
+        let body = response
+            .text()
+            .await
+            .map_err(|e| Nip05Error::Network(e.to_string()))?;
+        serde_json::from_str(&body).map_err(|e| Nip05Error::Parse(e.to_string()))
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::test_util::mock_http_server;
+
+    const BOB: &str = "b0635d6a9851d3aed0cd6c495b282167acf761729078d975fc341b22650b07b9";
+
+    async fn mock_server(
+        status: &'static str,
+        body: &'static str,
+    ) -> (String, tokio::sync::mpsc::UnboundedReceiver<String>) {
+        let (address, requests) = mock_http_server(status, "application/json", body).await;
+        (format!("http://{}", address), requests)
+    }
+
+    #[test]
+    fn test_parse_identifier() {
+        assert_eq!(
+            Nip05::parse_identifier("Bob@example.com"),
+            Ok(("bob".to_string(), "example.com".to_string()))
+        );
+        assert_eq!(
+            Nip05::parse_identifier("example.com"),
+            Ok(("_".to_string(), "example.com".to_string()))
+        );
+        for invalid in [
+            "bob@",
+            "@example.com",
+            "b o b@example.com",
+            "bob@example.com/path",
+        ] {
+            assert!(matches!(
+                Nip05::parse_identifier(invalid),
+                Err(Nip05Error::InvalidIdentifier(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify() {
+        let (url, mut requests) = mock_server(
+            "200 OK",
+            r#"{"names":{"bob":"b0635d6a9851d3aed0cd6c495b282167acf761729078d975fc341b22650b07b9"},"relays":{"b0635d6a9851d3aed0cd6c495b282167acf761729078d975fc341b22650b07b9":["wss://relay.example.com"]}}"#,
+        )
+        .await;
+        assert_eq!(Nip05::verify_at(&url, "bob", BOB).await, Ok(true));
+        assert_eq!(
+            requests.recv().await.unwrap().lines().next(),
+            Some("GET /.well-known/nostr.json?name=bob HTTP/1.1")
+        );
+        assert_eq!(Nip05::verify_at(&url, "alice", BOB).await, Ok(false));
+        assert!(matches!(
+            Nip05::verify_at(&url, "bob", &"a".repeat(64)).await,
+            Err(Nip05Error::PubkeyMismatch { ref found, .. }) if found == BOB
+        ));
+        assert_eq!(
+            Nip05::lookup_relays_at(&url, "bob").await,
+            Ok(vec!["wss://relay.example.com".to_string()])
+        );
+        assert_eq!(Nip05::lookup_relays_at(&url, "alice").await, Ok(vec![]));
+    }
+
+    #[tokio::test]
+    async fn test_verify_errors() {
+        let (url, _) = mock_server("200 OK", "not json").await;
+        assert!(matches!(
+            Nip05::verify_at(&url, "bob", BOB).await,
+            Err(Nip05Error::Parse(_))
+        ));
+        let (url, _) = mock_server("404 Not Found", "{}").await;
+        assert!(matches!(
+            Nip05::verify_at(&url, "bob", BOB).await,
+            Err(Nip05Error::Network(_))
+        ));
+        let refused_url = {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+        assert!(matches!(
+            Nip05::verify_at(&refused_url, "bob", BOB).await,
+            Err(Nip05Error::Network(_))
+        ));
+    }
+}
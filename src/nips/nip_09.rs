@@ -0,0 +1,110 @@
+use crate::notes::{NostrNote, NostrTag, TagList};
+
+const DELETION_KIND: u32 = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip09Error {
+    WrongKind(u32),
+    MissingEventTag,
+}
+impl std::fmt::Display for Nip09Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Nip09Error::WrongKind(kind) => {
+                write!(f, "Expected a kind {} note, found {}", DELETION_KIND, kind)
+            }
+            Nip09Error::MissingEventTag => write!(f, "Deletion request has no e tag"),
+        }
+    }
+}
+impl std::error::Error for Nip09Error {}
+
+/// A kind 5 request asking relays and clients to drop the author's earlier notes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeletionRequest {
+    targets: Vec<String>,
+    reason: Option<String>,
+}
+
+impl DeletionRequest {
+    /// Unsigned kind 5 note with one `e` tag per id and `reason` as its content.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(event_ids: &[&str], reason: Option<&str>) -> NostrNote {
+        let mut note = NostrNote {
+            kind: DELETION_KIND,
+            content: reason.unwrap_or_default().to_string(),
+            ..Default::default()
+        };
+        // Pushed directly, `add_event_tag` would group the ids into one tag.
+        note.tags.0.extend(event_ids.iter().map(|id| TagList {
+            tag_type: NostrTag::Event,
+            tags: vec![id.to_string()],
+        }));
+        note
+    }
+    /// Empty content parses as no reason.
+    pub fn from_note(note: &NostrNote) -> Result<Self, Nip09Error> {
+        if note.kind != DELETION_KIND {
+            return Err(Nip09Error::WrongKind(note.kind));
+        }
+        let targets = note.tags.find_all_tagged_events();
+        if targets.is_empty() {
+            return Err(Nip09Error::MissingEventTag);
+        }
+        Ok(DeletionRequest {
+            targets,
+            reason: Some(note.content.clone()).filter(|reason| !reason.is_empty()),
+        })
+    }
+    pub fn targets(&self) -> Vec<String> {
+        self.targets.clone()
+    }
+    pub fn reason(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::NostrKeypair;
+
+    const FIRST: &str = "b3e392b11f5d4f28321cedd09303a748acfd0487aea5a7450b3481c60b6e4f87";
+    const SECOND: &str = "d5b6dd6b92c3d637bcdbc4f9ad5ac6f9a4d1e0e6d18dfa6a2c4689d0bb1187a3";
+
+    #[test]
+    fn test_deletion_round_trip() {
+        let keys = NostrKeypair::generate(false);
+        let mut note = DeletionRequest::new(&[FIRST, SECOND], Some("posted by mistake"));
+        assert_eq!(note.kind, 5);
+        assert_eq!(note.content, "posted by mistake");
+        assert_eq!(
+            serde_json::to_value(&note.tags).unwrap(),
+            serde_json::json!([["e", FIRST], ["e", SECOND]])
+        );
+        note.pubkey = keys.public_key();
+        keys.sign_note(&mut note).expect("Failed to sign note");
+        assert!(note.verify());
+
+        let request = DeletionRequest::from_note(&note).expect("Failed to parse deletion");
+        assert_eq!(request.targets(), vec![FIRST, SECOND]);
+        assert_eq!(request.reason(), Some("posted by mistake"));
+
+        let without_reason = DeletionRequest::from_note(&DeletionRequest::new(&[FIRST], None))
+            .expect("Failed to parse deletion");
+        assert_eq!(without_reason.targets(), vec![FIRST]);
+        assert_eq!(without_reason.reason(), None);
+    }
+
+    #[test]
+    fn test_deletion_errors() {
+        assert_eq!(
+            DeletionRequest::from_note(&NostrNote::default()),
+            Err(Nip09Error::WrongKind(1))
+        );
+        assert_eq!(
+            DeletionRequest::from_note(&DeletionRequest::new(&[], Some("nothing"))),
+            Err(Nip09Error::MissingEventTag)
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{keypair::NostrKeypair, notes::NostrNote};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip13Error {
+    Serialization(String),
+    NonceExhausted,
+}
+impl std::fmt::Display for Nip13Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Nip13Error::Serialization(e) => write!(f, "Failed to serialize note id: {}", e),
+            Nip13Error::NonceExhausted => write!(f, "No nonce reached the target difficulty"),
+        }
+    }
+}
+impl std::error::Error for Nip13Error {}
+
+pub struct Nip13;
+
+impl Nip13 {
+    /// Signs `note` with `signer` once its id has at least `target_bits` leading
+    /// zero bits, replacing any previous `nonce` tag.
+    pub fn mine_note(
+        note: &mut NostrNote,
+        target_bits: u8,
+        signer: &NostrKeypair,
+    ) -> Result<NostrNote, Nip13Error> {
+        // The pubkey is part of the id, so it has to be final before mining.
+        note.pubkey = signer.public_key();
+        note.tags.add_nonce_tag(0, target_bits);
+        let nonce = note.tags.0.len() - 1;
+        for counter in 0..=u64::MAX {
+            note.tags.0[nonce].tags[0] = counter.to_string();
+            note.serialize_id()
+                .map_err(|e| Nip13Error::Serialization(e.to_string()))?;
+            if note.meets_pow_target(target_bits) {
+                signer
+                    .sign_note(note)
+                    .map_err(|e| Nip13Error::Serialization(e.to_string()))?;
+                return Ok(note.clone());
+            }
+        }
+        Err(Nip13Error::NonceExhausted)
+    }
+    /// Leading zero bits of a hex id, stopping at the first non-hex character.
+    /// An all-zero id saturates at 255.
+    pub fn leading_zero_bits(id: &str) -> u8 {
+        crate::notes::leading_zero_bits(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::{NostrTag, TagList};
+
+    #[test]
+    fn test_leading_zero_bits() {
+        assert_eq!(Nip13::leading_zero_bits("ffff"), 0);
+        assert_eq!(Nip13::leading_zero_bits("7fff"), 1);
+        assert_eq!(Nip13::leading_zero_bits("0f00"), 4);
+        assert_eq!(Nip13::leading_zero_bits("0010"), 11);
+        // The example id from NIP-13.
+        assert_eq!(
+            Nip13::leading_zero_bits(
+                "000000000e9d97a1ab09fc381030b346cdd7a142ad57e6df0b46dc9bef6c7e2d"
+            ),
+            36
+        );
+        assert_eq!(Nip13::leading_zero_bits(&"0".repeat(64)), 255);
+        assert_eq!(Nip13::leading_zero_bits(""), 0);
+    }
+
+    #[test]
+    fn test_mine_note() {
+        let keys = NostrKeypair::generate(false);
+        let mut note = NostrNote {
+            content: "It's just me mining my own business".to_string(),
+            ..Default::default()
+        };
+        note.tags.add_custom_tag(NostrTag::Custom("nonce"), "stale");
+        let mined = Nip13::mine_note(&mut note, 12, &keys).expect("Failed to mine note");
+        assert_eq!(mined, note);
+        assert!(mined.verify());
+        assert!(keys.verify_note(&mined));
+        assert!(Nip13::leading_zero_bits(mined.id.as_deref().unwrap()) >= 12);
+
+        let nonce: Vec<&TagList> = mined
+            .tags
+            .0
+            .iter()
+            .filter(|tag_list| tag_list.tag_type == NostrTag::Custom("nonce"))
+            .collect();
+        assert_eq!(nonce.len(), 1);
+        assert_eq!(nonce[0].tags.len(), 2);
+        assert!(nonce[0].tags[0].parse::<u64>().is_ok());
+        assert_eq!(nonce[0].tags[1], "12");
+        assert_eq!(mined.tags.nonce().map(|(_, target)| target), Some(12));
+    }
+}
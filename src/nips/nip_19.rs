@@ -0,0 +1,361 @@
+use bech32::{Bech32, Hrp};
+
+const TLV_SPECIAL: u8 = 0;
+const TLV_RELAY: u8 = 1;
+const TLV_AUTHOR: u8 = 2;
+const TLV_KIND: u8 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip19Error {
+    InvalidBech32(String),
+    WrongPrefix { expected: String, found: String },
+    InvalidHex(String),
+    MalformedTlv(String),
+    MissingField(String),
+}
+impl std::fmt::Display for Nip19Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Nip19Error::InvalidBech32(e) => write!(f, "Invalid bech32: {}", e),
+            Nip19Error::WrongPrefix { expected, found } => {
+                write!(f, "Expected {} prefix, found {}", expected, found)
+            }
+            Nip19Error::InvalidHex(value) => write!(f, "Expected 64 hex characters: {}", value),
+            Nip19Error::MalformedTlv(e) => write!(f, "Malformed TLV entry: {}", e),
+            Nip19Error::MissingField(field) => write!(f, "Missing field: {}", field),
+        }
+    }
+}
+impl std::error::Error for Nip19Error {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventPointer {
+    pub id: String,
+    pub relays: Vec<String>,
+    pub author: Option<String>,
+    pub kind: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressPointer {
+    pub identifier: String,
+    pub pubkey: String,
+    pub kind: u32,
+    pub relays: Vec<String>,
+}
+
+/// The NIP-33 address of an addressable note, see `NostrNote::to_address`.
+pub type NostrAddress = AddressPointer;
+
+impl AddressPointer {
+    /// The `<kind>:<pubkey>:<d tag>` coordinate used in `a` tags.
+    pub fn coordinate(&self) -> String {
+        format!("{}:{}:{}", self.kind, self.pubkey, self.identifier)
+    }
+    pub fn to_naddr(&self) -> Result<String, Nip19Error> {
+        let relays: Vec<&str> = self.relays.iter().map(String::as_str).collect();
+        Nip19::encode_naddr(&self.identifier, &self.pubkey, self.kind, &relays)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfilePointer {
+    pub pubkey: String,
+    pub relays: Vec<String>,
+}
+
+/// TLV-encoded NIP-19 entities: `nevent`, `naddr` and `nprofile`.
+/// `npub` and `nsec` live on `NostrKeypair`.
+pub struct Nip19;
+
+impl Nip19 {
+    pub fn encode_nevent(
+        id: &str,
+        relays: &[&str],
+        author: Option<&str>,
+    ) -> Result<String, Nip19Error> {
+        let mut tlv = Vec::new();
+        push_tlv(&mut tlv, TLV_SPECIAL, &hex_decode_32(id)?)?;
+        push_relays(&mut tlv, relays)?;
+        if let Some(author) = author {
+            push_tlv(&mut tlv, TLV_AUTHOR, &hex_decode_32(author)?)?;
+        }
+        encode("nevent", &tlv)
+    }
+    pub fn encode_naddr(
+        identifier: &str,
+        pubkey: &str,
+        kind: u32,
+        relays: &[&str],
+    ) -> Result<String, Nip19Error> {
+        let mut tlv = Vec::new();
+        push_tlv(&mut tlv, TLV_SPECIAL, identifier.as_bytes())?;
+        push_relays(&mut tlv, relays)?;
+        push_tlv(&mut tlv, TLV_AUTHOR, &hex_decode_32(pubkey)?)?;
+        push_tlv(&mut tlv, TLV_KIND, &kind.to_be_bytes())?;
+        encode("naddr", &tlv)
+    }
+    pub fn encode_nprofile(pubkey: &str, relays: &[&str]) -> Result<String, Nip19Error> {
+        let mut tlv = Vec::new();
+        push_tlv(&mut tlv, TLV_SPECIAL, &hex_decode_32(pubkey)?)?;
+        push_relays(&mut tlv, relays)?;
+        encode("nprofile", &tlv)
+    }
+    pub fn decode_nevent(nevent: &str) -> Result<EventPointer, Nip19Error> {
+        let entries = decode("nevent", nevent)?;
+        let mut pointer = EventPointer {
+            id: String::new(),
+            relays: vec![],
+            author: None,
+            kind: None,
+        };
+        let mut id = None;
+        for (tlv_type, value) in entries {
+            match tlv_type {
+                TLV_SPECIAL => id = Some(hex_encode_32(&value, "id")?),
+                TLV_RELAY => pointer.relays.push(utf8(value)?),
+                TLV_AUTHOR => pointer.author = Some(hex_encode_32(&value, "author")?),
+                TLV_KIND => pointer.kind = Some(kind(&value)?),
+                _ => {}
+            }
+        }
+        pointer.id = id.ok_or_else(|| Nip19Error::MissingField("id".to_string()))?;
+        Ok(pointer)
+    }
+    pub fn decode_naddr(naddr: &str) -> Result<AddressPointer, Nip19Error> {
+        let entries = decode("naddr", naddr)?;
+        let (mut identifier, mut pubkey, mut event_kind) = (None, None, None);
+        let mut relays = vec![];
+        for (tlv_type, value) in entries {
+            match tlv_type {
+                TLV_SPECIAL => identifier = Some(utf8(value)?),
+                TLV_RELAY => relays.push(utf8(value)?),
+                TLV_AUTHOR => pubkey = Some(hex_encode_32(&value, "author")?),
+                TLV_KIND => event_kind = Some(kind(&value)?),
+                _ => {}
+            }
+        }
+        let missing = |field: &str| Nip19Error::MissingField(field.to_string());
+        Ok(AddressPointer {
+            identifier: identifier.ok_or_else(|| missing("identifier"))?,
+            pubkey: pubkey.ok_or_else(|| missing("author"))?,
+            kind: event_kind.ok_or_else(|| missing("kind"))?,
+            relays,
+        })
+    }
+    pub fn decode_nprofile(nprofile: &str) -> Result<ProfilePointer, Nip19Error> {
+        let entries = decode("nprofile", nprofile)?;
+        let mut pubkey = None;
+        let mut relays = vec![];
+        for (tlv_type, value) in entries {
+            match tlv_type {
+                TLV_SPECIAL => pubkey = Some(hex_encode_32(&value, "pubkey")?),
+                TLV_RELAY => relays.push(utf8(value)?),
+                _ => {}
+            }
+        }
+        Ok(ProfilePointer {
+            pubkey: pubkey.ok_or_else(|| Nip19Error::MissingField("pubkey".to_string()))?,
+            relays,
+        })
+    }
+}
+
+/// Decodes a bare `npub`, `nsec` or `note` entity to the hex of its 32 bytes.
+pub(super) fn decode_hex(prefix: &str, encoded: &str) -> Result<String, Nip19Error> {
+    let (hrp, data) =
+        bech32::decode(encoded).map_err(|e| Nip19Error::InvalidBech32(e.to_string()))?;
+    if hrp.as_str() != prefix {
+        return Err(Nip19Error::WrongPrefix {
+            expected: prefix.to_string(),
+            found: hrp.to_string(),
+        });
+    }
+    hex_encode_32(&data, prefix)
+}
+fn push_tlv(tlv: &mut Vec<u8>, tlv_type: u8, value: &[u8]) -> Result<(), Nip19Error> {
+    let length = u8::try_from(value.len())
+        .map_err(|_| Nip19Error::MalformedTlv(format!("value of {} bytes", value.len())))?;
+    tlv.push(tlv_type);
+    tlv.push(length);
+    tlv.extend_from_slice(value);
+    Ok(())
+}
+fn push_relays(tlv: &mut Vec<u8>, relays: &[&str]) -> Result<(), Nip19Error> {
+    relays
+        .iter()
+        .try_for_each(|relay| push_tlv(tlv, TLV_RELAY, relay.as_bytes()))
+}
+fn encode(prefix: &str, tlv: &[u8]) -> Result<String, Nip19Error> {
+    let hrp = Hrp::parse(prefix).expect("valid hrp");
+    bech32::encode::<Bech32>(hrp, tlv).map_err(|e| Nip19Error::InvalidBech32(e.to_string()))
+}
+fn decode(prefix: &str, encoded: &str) -> Result<Vec<(u8, Vec<u8>)>, Nip19Error> {
+    let (hrp, data) =
+        bech32::decode(encoded).map_err(|e| Nip19Error::InvalidBech32(e.to_string()))?;
+    if hrp.as_str() != prefix {
+        return Err(Nip19Error::WrongPrefix {
+            expected: prefix.to_string(),
+            found: hrp.to_string(),
+        });
+    }
+    let mut entries = vec![];
+    let mut rest = data.as_slice();
+    while !rest.is_empty() {
+        let [tlv_type, length, value @ ..] = rest else {
+            return Err(Nip19Error::MalformedTlv("truncated header".to_string()));
+        };
+        let length = *length as usize;
+        if value.len() < length {
+            return Err(Nip19Error::MalformedTlv("truncated value".to_string()));
+        }
+        entries.push((*tlv_type, value[..length].to_vec()));
+        rest = &value[length..];
+    }
+    Ok(entries)
+}
+fn hex_decode_32(hex: &str) -> Result<[u8; 32], Nip19Error> {
+    let mut bytes = [0u8; 32];
+    if hex.len() != 64 {
+        return Err(Nip19Error::InvalidHex(hex.to_string()));
+    }
+    for (byte, chunk) in bytes.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = std::str::from_utf8(chunk)
+            .ok()
+            .and_then(|chunk| u8::from_str_radix(chunk, 16).ok())
+            .ok_or_else(|| Nip19Error::InvalidHex(hex.to_string()))?;
+    }
+    Ok(bytes)
+}
+fn hex_encode_32(value: &[u8], field: &str) -> Result<String, Nip19Error> {
+    if value.len() != 32 {
+        return Err(Nip19Error::MalformedTlv(format!(
+            "{} must be 32 bytes",
+            field
+        )));
+    }
+    Ok(value.iter().map(|b| format!("{:02x}", b)).collect())
+}
+fn utf8(value: Vec<u8>) -> Result<String, Nip19Error> {
+    String::from_utf8(value).map_err(|e| Nip19Error::MalformedTlv(e.to_string()))
+}
+fn kind(value: &[u8]) -> Result<u32, Nip19Error> {
+    let bytes = <[u8; 4]>::try_from(value)
+        .map_err(|_| Nip19Error::MalformedTlv("kind must be 4 bytes".to_string()))?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PUBKEY: &str = "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d";
+
+    #[test]
+    fn test_nprofile_reference_vector() {
+        // Example from the NIP-19 specification.
+        let nprofile = "nprofile1qqsrhuxx8l9ex335q7he0f09aej04zpazpl0ne2cgukyawd24mayt8gpp4mhxue69uhhytnc9e3k7mgpz4mhxue69uhkg6nzv9ejuumpv34kytnrdaksjlyr9p";
+        let relays = ["wss://r.x.com", "wss://djbas.sadkb.com"];
+        assert_eq!(Nip19::encode_nprofile(PUBKEY, &relays).unwrap(), nprofile);
+        assert_eq!(
+            Nip19::decode_nprofile(nprofile).unwrap(),
+            ProfilePointer {
+                pubkey: PUBKEY.to_string(),
+                relays: relays.iter().map(|relay| relay.to_string()).collect(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_address_naddr() {
+        let address = NostrAddress {
+            identifier: "banana".to_string(),
+            pubkey: PUBKEY.to_string(),
+            kind: 30023,
+            relays: vec!["wss://relay.example.com".to_string()],
+        };
+        assert_eq!(address.coordinate(), format!("30023:{}:banana", PUBKEY));
+        let naddr = address.to_naddr().unwrap();
+        assert!(naddr.starts_with("naddr1"));
+        assert_eq!(Nip19::decode_naddr(&naddr), Ok(address.clone()));
+        let invalid = NostrAddress {
+            pubkey: "abc".to_string(),
+            ..address
+        };
+        assert_eq!(
+            invalid.to_naddr(),
+            Err(Nip19Error::InvalidHex("abc".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_nip19_errors() {
+        assert_eq!(
+            Nip19::encode_nevent("abc", &[], None),
+            Err(Nip19Error::InvalidHex("abc".to_string()))
+        );
+        let nprofile = Nip19::encode_nprofile(PUBKEY, &[]).unwrap();
+        assert!(matches!(
+            Nip19::decode_nevent(&nprofile),
+            Err(Nip19Error::WrongPrefix { ref found, .. }) if found == "nprofile"
+        ));
+        let truncated = encode("nevent", &[TLV_SPECIAL, 32, 0xab]).unwrap();
+        assert!(matches!(
+            Nip19::decode_nevent(&truncated),
+            Err(Nip19Error::MalformedTlv(_))
+        ));
+        let without_kind = encode("naddr", &[TLV_SPECIAL, 1, b'd']).unwrap();
+        assert_eq!(
+            Nip19::decode_naddr(&without_kind),
+            Err(Nip19Error::MissingField("author".to_string()))
+        );
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    mod round_trip {
+        use super::super::*;
+        use proptest::prelude::*;
+
+        fn hex_key() -> impl Strategy<Value = String> {
+            "[0-9a-f]{64}"
+        }
+        fn relays() -> impl Strategy<Value = Vec<String>> {
+            prop::collection::vec("wss://[a-z0-9.]{1,40}", 0..4)
+        }
+
+        proptest! {
+            #[test]
+            fn nevent_round_trip(
+                id in hex_key(),
+                relays in relays(),
+                author in prop::option::of(hex_key()),
+            ) {
+                let relay_refs: Vec<&str> = relays.iter().map(String::as_str).collect();
+                let nevent = Nip19::encode_nevent(&id, &relay_refs, author.as_deref()).unwrap();
+                let decoded = Nip19::decode_nevent(&nevent).unwrap();
+                prop_assert_eq!(decoded, EventPointer { id, relays, author, kind: None });
+            }
+
+            #[test]
+            fn naddr_round_trip(
+                identifier in "\\PC{0,40}",
+                pubkey in hex_key(),
+                kind in any::<u32>(),
+                relays in relays(),
+            ) {
+                let relay_refs: Vec<&str> = relays.iter().map(String::as_str).collect();
+                let naddr = Nip19::encode_naddr(&identifier, &pubkey, kind, &relay_refs).unwrap();
+                let decoded = Nip19::decode_naddr(&naddr).unwrap();
+                prop_assert_eq!(decoded, AddressPointer { identifier, pubkey, kind, relays });
+            }
+
+            #[test]
+            fn nprofile_round_trip(pubkey in hex_key(), relays in relays()) {
+                let relay_refs: Vec<&str> = relays.iter().map(String::as_str).collect();
+                let nprofile = Nip19::encode_nprofile(&pubkey, &relay_refs).unwrap();
+                let decoded = Nip19::decode_nprofile(&nprofile).unwrap();
+                prop_assert_eq!(decoded, ProfilePointer { pubkey, relays });
+            }
+        }
+    }
+}
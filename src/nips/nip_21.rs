@@ -0,0 +1,209 @@
+use std::str::FromStr;
+
+use super::nip_19::{decode_hex, Nip19, Nip19Error};
+
+const SCHEME: &str = "nostr:";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip21Error {
+    MissingScheme,
+    UnknownEntity(String),
+    Nip19(Nip19Error),
+}
+impl std::fmt::Display for Nip21Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Nip21Error::MissingScheme => write!(f, "URI does not start with nostr:"),
+            Nip21Error::UnknownEntity(entity) => write!(f, "Unknown NIP-19 entity: {}", entity),
+            Nip21Error::Nip19(e) => write!(f, "{}", e),
+        }
+    }
+}
+impl std::error::Error for Nip21Error {}
+impl From<Nip19Error> for Nip21Error {
+    fn from(e: Nip19Error) -> Self {
+        Nip21Error::Nip19(e)
+    }
+}
+
+/// A decoded `nostr:` URI. Keys and ids are hex.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NostrUri {
+    Npub(String),
+    /// NIP-21 forbids sharing these, they are only decoded to be recognized.
+    Nsec(String),
+    Note(String),
+    Nevent {
+        id: String,
+        relays: Vec<String>,
+        author: Option<String>,
+    },
+    Naddr {
+        identifier: String,
+        pubkey: String,
+        kind: u32,
+        relays: Vec<String>,
+    },
+    Nprofile {
+        pubkey: String,
+        relays: Vec<String>,
+    },
+}
+
+impl FromStr for NostrUri {
+    type Err = Nip21Error;
+    fn from_str(uri: &str) -> Result<Self, Self::Err> {
+        let entity = uri.strip_prefix(SCHEME).ok_or(Nip21Error::MissingScheme)?;
+        NostrUri::from_entity(entity)
+    }
+}
+
+impl NostrUri {
+    // Decodes a bech32 entity without its `nostr:` prefix.
+    fn from_entity(entity: &str) -> Result<Self, Nip21Error> {
+        let prefix = entity.split_once('1').map_or(entity, |(prefix, _)| prefix);
+        Ok(match prefix {
+            "npub" => NostrUri::Npub(decode_hex("npub", entity)?),
+            "nsec" => NostrUri::Nsec(decode_hex("nsec", entity)?),
+            "note" => NostrUri::Note(decode_hex("note", entity)?),
+            "nevent" => {
+                let pointer = Nip19::decode_nevent(entity)?;
+                NostrUri::Nevent {
+                    id: pointer.id,
+                    relays: pointer.relays,
+                    author: pointer.author,
+                }
+            }
+            "naddr" => {
+                let pointer = Nip19::decode_naddr(entity)?;
+                NostrUri::Naddr {
+                    identifier: pointer.identifier,
+                    pubkey: pointer.pubkey,
+                    kind: pointer.kind,
+                    relays: pointer.relays,
+                }
+            }
+            "nprofile" => {
+                let pointer = Nip19::decode_nprofile(entity)?;
+                NostrUri::Nprofile {
+                    pubkey: pointer.pubkey,
+                    relays: pointer.relays,
+                }
+            }
+            _ => return Err(Nip21Error::UnknownEntity(entity.to_string())),
+        })
+    }
+    /// Every `nostr:` URI in `content` that decodes, in order. A URI ends at the
+    /// first character outside the bech32 alphabet, so trailing punctuation is fine.
+    pub fn extract_uris(content: &str) -> Vec<NostrUri> {
+        crate::notes::nostr_uri_entities(content)
+            .filter_map(|entity| NostrUri::from_entity(entity).ok())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::NostrKeypair;
+
+    const PUBKEY: &str = "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d";
+    const NOTE: &str = "4376c65d2f232afbe9b882a35baa4f6fe8667c4e684749af565f981833ed6a65";
+
+    #[test]
+    fn test_uri_types() {
+        let keys = NostrKeypair::generate(true);
+        assert_eq!(
+            format!("nostr:{}", keys.npub()).parse(),
+            Ok(NostrUri::Npub(keys.public_key()))
+        );
+        assert!(matches!(
+            format!("nostr:{}", keys.get_nsec()).parse(),
+            Ok(NostrUri::Nsec(secret)) if secret.len() == 64
+        ));
+
+        let note = crate::NostrNote {
+            id: Some(NOTE.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            format!("nostr:{}", note.get_note_hrp().unwrap()).parse(),
+            Ok(NostrUri::Note(NOTE.to_string()))
+        );
+
+        let nevent =
+            Nip19::encode_nevent(NOTE, &["wss://relay.example.com"], Some(PUBKEY)).unwrap();
+        assert_eq!(
+            format!("nostr:{}", nevent).parse(),
+            Ok(NostrUri::Nevent {
+                id: NOTE.to_string(),
+                relays: vec!["wss://relay.example.com".to_string()],
+                author: Some(PUBKEY.to_string()),
+            })
+        );
+
+        let naddr = Nip19::encode_naddr("banana", PUBKEY, 30023, &[]).unwrap();
+        assert_eq!(
+            format!("nostr:{}", naddr).parse(),
+            Ok(NostrUri::Naddr {
+                identifier: "banana".to_string(),
+                pubkey: PUBKEY.to_string(),
+                kind: 30023,
+                relays: vec![],
+            })
+        );
+
+        // Example from the NIP-21 specification.
+        let nprofile = "nostr:nprofile1qqsrhuxx8l9ex335q7he0f09aej04zpazpl0ne2cgukyawd24mayt8gpp4mhxue69uhhytnc9e3k7mgpz4mhxue69uhkg6nzv9ejuumpv34kytnrdaksjlyr9p";
+        assert_eq!(
+            nprofile.parse(),
+            Ok(NostrUri::Nprofile {
+                pubkey: PUBKEY.to_string(),
+                relays: vec![
+                    "wss://r.x.com".to_string(),
+                    "wss://djbas.sadkb.com".to_string()
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_uri_errors() {
+        let keys = NostrKeypair::generate(false);
+        assert_eq!(
+            keys.npub().parse::<NostrUri>(),
+            Err(Nip21Error::MissingScheme)
+        );
+        assert_eq!(
+            "nostr:lnurl1dp68gurn8ghj7".parse::<NostrUri>(),
+            Err(Nip21Error::UnknownEntity("lnurl1dp68gurn8ghj7".to_string()))
+        );
+        assert!(matches!(
+            "nostr:npub1invalid".parse::<NostrUri>(),
+            Err(Nip21Error::Nip19(Nip19Error::InvalidBech32(_)))
+        ));
+    }
+
+    #[test]
+    fn test_extract_uris() {
+        let keys = NostrKeypair::generate(false);
+        let nevent = Nip19::encode_nevent(NOTE, &[], None).unwrap();
+        let content = format!(
+            "gm nostr:{}, have you seen nostr:{}? Not this one: nostr:npub1broken or nostr:",
+            keys.npub(),
+            nevent
+        );
+        assert_eq!(
+            NostrUri::extract_uris(&content),
+            vec![
+                NostrUri::Npub(keys.public_key()),
+                NostrUri::Nevent {
+                    id: NOTE.to_string(),
+                    relays: vec![],
+                    author: None,
+                },
+            ]
+        );
+        assert!(NostrUri::extract_uris("no references here").is_empty());
+    }
+}
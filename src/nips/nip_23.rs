@@ -0,0 +1,241 @@
+use crate::{
+    keypair::{NostrKeypair, SignerError},
+    notes::{NostrNote, NostrTag, TagList},
+};
+
+const ARTICLE_KIND: u32 = 30023;
+const DRAFT_KIND: u32 = 30024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip23Error {
+    WrongKind(u32),
+    MissingTitle,
+    MissingIdentifier,
+    InvalidPublishedAt(String),
+    Signing(SignerError),
+}
+impl std::fmt::Display for Nip23Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Nip23Error::WrongKind(kind) => {
+                write!(f, "Expected a kind {} note, found {}", ARTICLE_KIND, kind)
+            }
+            Nip23Error::MissingTitle => write!(f, "Article has no title"),
+            Nip23Error::MissingIdentifier => write!(f, "Article has no d tag"),
+            Nip23Error::InvalidPublishedAt(value) => {
+                write!(f, "Invalid published_at timestamp: {}", value)
+            }
+            Nip23Error::Signing(e) => write!(f, "{}", e),
+        }
+    }
+}
+impl std::error::Error for Nip23Error {}
+
+/// A kind 30023 long-form article, or a kind 30024 draft of one.
+/// The content is Markdown and is kept as is.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Article {
+    /// The `d` tag the article is addressed by.
+    pub identifier: String,
+    pub title: String,
+    pub image: Option<String>,
+    pub summary: Option<String>,
+    /// When the article was first published; `created_at` changes with every edit.
+    pub published_at: Option<i64>,
+    pub hashtags: Vec<String>,
+    pub content: String,
+}
+
+impl Article {
+    pub fn from_note(note: &NostrNote) -> Result<Self, Nip23Error> {
+        if note.kind != ARTICLE_KIND && note.kind != DRAFT_KIND {
+            return Err(Nip23Error::WrongKind(note.kind));
+        }
+        let first = |tag_type: &str| {
+            note.tags
+                .0
+                .iter()
+                .find(|tag_list| tag_list.tag_type.as_ref() == tag_type)
+                .and_then(|tag_list| tag_list.tags.first().cloned())
+        };
+        let published_at = first("published_at")
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| Nip23Error::InvalidPublishedAt(value))
+            })
+            .transpose()?;
+        Ok(Article {
+            identifier: first("d").ok_or(Nip23Error::MissingIdentifier)?,
+            title: first("title").unwrap_or_default(),
+            image: first("image"),
+            summary: first("summary"),
+            published_at,
+            hashtags: note.tags.find_tags_owned("t"),
+            content: note.content.clone(),
+        })
+    }
+    /// Unsigned kind 30024 draft with the same tags and content.
+    pub fn to_draft(&self) -> NostrNote {
+        self.to_note(DRAFT_KIND)
+    }
+    fn to_note(&self, kind: u32) -> NostrNote {
+        let mut note = NostrNote {
+            kind,
+            content: self.content.clone(),
+            ..Default::default()
+        };
+        let mut push = |tag_type: &'static str, value: &str| {
+            note.tags.0.push(TagList {
+                tag_type: NostrTag::Custom(tag_type),
+                tags: vec![value.to_string()],
+            });
+        };
+        push("d", &self.identifier);
+        push("title", &self.title);
+        if let Some(image) = &self.image {
+            push("image", image);
+        }
+        if let Some(summary) = &self.summary {
+            push("summary", summary);
+        }
+        if let Some(published_at) = self.published_at {
+            push("published_at", &published_at.to_string());
+        }
+        for hashtag in &self.hashtags {
+            push("t", hashtag);
+        }
+        note
+    }
+}
+
+/// Collects the fields of an `Article` and signs it as a kind 30023 note.
+#[derive(Debug, Clone, Default)]
+pub struct ArticleBuilder {
+    article: Article,
+}
+
+impl ArticleBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn identifier(mut self, identifier: &str) -> Self {
+        self.article.identifier = identifier.to_string();
+        self
+    }
+    pub fn title(mut self, title: &str) -> Self {
+        self.article.title = title.to_string();
+        self
+    }
+    pub fn image(mut self, image_url: &str) -> Self {
+        self.article.image = Some(image_url.to_string());
+        self
+    }
+    pub fn summary(mut self, summary: &str) -> Self {
+        self.article.summary = Some(summary.to_string());
+        self
+    }
+    pub fn published_at(mut self, published_at: i64) -> Self {
+        self.article.published_at = Some(published_at);
+        self
+    }
+    pub fn add_hashtag(mut self, hashtag: &str) -> Self {
+        self.article.hashtags.push(hashtag.to_string());
+        self
+    }
+    pub fn content(mut self, content: &str) -> Self {
+        self.article.content = content.to_string();
+        self
+    }
+    /// Fails without a title or identifier.
+    pub fn publish(self, signer: &NostrKeypair) -> Result<NostrNote, Nip23Error> {
+        if self.article.title.is_empty() {
+            return Err(Nip23Error::MissingTitle);
+        }
+        if self.article.identifier.is_empty() {
+            return Err(Nip23Error::MissingIdentifier);
+        }
+        let mut note = self.article.to_note(ARTICLE_KIND);
+        note.pubkey = signer.public_key();
+        signer.sign_note(&mut note).map_err(Nip23Error::Signing)?;
+        Ok(note)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder() -> ArticleBuilder {
+        ArticleBuilder::new()
+            .identifier("lorem-ipsum")
+            .title("Lorem Ipsum")
+            .summary("A placeholder article")
+            .published_at(1296962229)
+            .add_hashtag("placeholder")
+            .add_hashtag("latin")
+            .content("Lorem [ipsum](https://example.com) dolor sit amet.")
+    }
+
+    #[test]
+    fn test_article_round_trip() {
+        let keys = NostrKeypair::generate(false);
+        let note = builder().publish(&keys).expect("Failed to publish article");
+        assert_eq!(note.kind, 30023);
+        assert!(note.verify());
+        assert!(note.content.starts_with("Lorem [ipsum]"));
+        assert_eq!(
+            serde_json::to_value(&note.tags).unwrap(),
+            serde_json::json!([
+                ["d", "lorem-ipsum"],
+                ["title", "Lorem Ipsum"],
+                ["summary", "A placeholder article"],
+                ["published_at", "1296962229"],
+                ["t", "placeholder"],
+                ["t", "latin"]
+            ])
+        );
+
+        let article = Article::from_note(&note).expect("Failed to parse article");
+        assert_eq!(article.identifier, "lorem-ipsum");
+        assert_eq!(article.title, "Lorem Ipsum");
+        assert_eq!(article.image, None);
+        assert_eq!(article.published_at, Some(1296962229));
+        assert_eq!(article.hashtags, vec!["placeholder", "latin"]);
+        assert_eq!(article.content, note.content);
+
+        let draft = article.to_draft();
+        assert_eq!(draft.kind, 30024);
+        assert_eq!(draft.tags, note.tags);
+        assert_eq!(Article::from_note(&draft), Ok(article));
+    }
+
+    #[test]
+    fn test_article_errors() {
+        let keys = NostrKeypair::generate(false);
+        assert_eq!(
+            builder().title("").publish(&keys),
+            Err(Nip23Error::MissingTitle)
+        );
+        assert_eq!(
+            builder().identifier("").publish(&keys),
+            Err(Nip23Error::MissingIdentifier)
+        );
+        assert_eq!(
+            Article::from_note(&NostrNote::default()),
+            Err(Nip23Error::WrongKind(1))
+        );
+        let mut note = builder().publish(&keys).unwrap();
+        note.tags.replace_tag("published_at", "yesterday");
+        assert_eq!(
+            Article::from_note(&note),
+            Err(Nip23Error::InvalidPublishedAt("yesterday".to_string()))
+        );
+        let mut note = builder().publish(&keys).unwrap();
+        note.tags.remove_all_of_type("d");
+        assert_eq!(
+            Article::from_note(&note),
+            Err(Nip23Error::MissingIdentifier)
+        );
+    }
+}
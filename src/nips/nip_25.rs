@@ -0,0 +1,157 @@
+use crate::notes::{NostrNote, NostrTag};
+
+const REACTION_KIND: u32 = 7;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip25Error {
+    EmptyContent,
+    MissingTargetId,
+    WrongKind(u32),
+    MissingEventTag,
+}
+impl std::fmt::Display for Nip25Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Nip25Error::EmptyContent => write!(f, "Reaction content is empty"),
+            Nip25Error::MissingTargetId => write!(f, "Reacted note has no id"),
+            Nip25Error::WrongKind(kind) => {
+                write!(f, "Expected a kind {} note, found {}", REACTION_KIND, kind)
+            }
+            Nip25Error::MissingEventTag => write!(f, "Reaction has no e tag"),
+        }
+    }
+}
+impl std::error::Error for Nip25Error {}
+
+/// A kind 7 reaction: `+` or empty likes, `-` dislikes, anything else is an emoji.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reaction {
+    pub content: String,
+    pub event_id: String,
+    pub author: Option<String>,
+}
+
+impl Reaction {
+    /// Unsigned kind 7 note reacting to `target_event`, with its `e`, `p` and `k` tags.
+    /// Any emoji or `:shortcode:` is accepted, only blank content is rejected.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(content: &str, target_event: &NostrNote) -> Result<NostrNote, Nip25Error> {
+        if content.trim().is_empty() {
+            return Err(Nip25Error::EmptyContent);
+        }
+        let target_id = target_event
+            .id
+            .as_deref()
+            .ok_or(Nip25Error::MissingTargetId)?;
+        let mut reaction = NostrNote {
+            kind: REACTION_KIND,
+            content: content.to_string(),
+            ..Default::default()
+        };
+        reaction.tags.add_event_tag(target_id);
+        if !target_event.pubkey.is_empty() {
+            reaction.tags.add_pubkey_tag(&target_event.pubkey);
+        }
+        reaction
+            .tags
+            .add_custom_tag(NostrTag::Custom("k"), &target_event.kind.to_string());
+        Ok(reaction)
+    }
+    /// The last `e` and `p` tags name the reacted note and its author. Empty content
+    /// is kept, other clients publish it as a like.
+    pub fn from_note(note: &NostrNote) -> Result<Reaction, Nip25Error> {
+        if note.kind != REACTION_KIND {
+            return Err(Nip25Error::WrongKind(note.kind));
+        }
+        let event_id = note
+            .tags
+            .find_tags_ref("e")
+            .last()
+            .ok_or(Nip25Error::MissingEventTag)?;
+        Ok(Reaction {
+            content: note.content.clone(),
+            event_id: event_id.to_string(),
+            author: note.tags.find_tags_ref("p").last().map(str::to_string),
+        })
+    }
+    pub fn is_positive(&self) -> bool {
+        self.content == "+" || self.content.is_empty()
+    }
+    pub fn is_negative(&self) -> bool {
+        self.content == "-"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::NostrKeypair;
+
+    fn target() -> NostrNote {
+        let author = NostrKeypair::generate(false);
+        let mut note = NostrNote {
+            pubkey: author.public_key(),
+            content: "GM".to_string(),
+            ..Default::default()
+        };
+        author.sign_note(&mut note).expect("Failed to sign note");
+        note
+    }
+
+    #[test]
+    fn test_reactions() {
+        let target = target();
+        let reactor = NostrKeypair::generate(false);
+        for (content, positive, negative) in
+            [("+", true, false), ("-", false, true), ("🤙", false, false)]
+        {
+            let mut note = Reaction::new(content, &target).expect("Failed to react");
+            assert_eq!(note.kind, 7);
+            assert_eq!(note.content, content);
+            assert_eq!(
+                serde_json::to_value(&note.tags).unwrap(),
+                serde_json::json!([
+                    ["e", target.id.as_deref().unwrap()],
+                    ["p", target.pubkey],
+                    ["k", "1"]
+                ])
+            );
+            note.pubkey = reactor.public_key();
+            reactor.sign_note(&mut note).expect("Failed to sign note");
+            assert!(note.verify());
+
+            let reaction = Reaction::from_note(&note).expect("Failed to parse reaction");
+            assert_eq!(reaction.content, content);
+            assert_eq!(reaction.event_id, target.id.clone().unwrap());
+            assert_eq!(reaction.author.as_deref(), Some(target.pubkey.as_str()));
+            assert_eq!(reaction.is_positive(), positive);
+            assert_eq!(reaction.is_negative(), negative);
+        }
+    }
+
+    #[test]
+    fn test_reaction_errors() {
+        let target = target();
+        assert_eq!(Reaction::new("", &target), Err(Nip25Error::EmptyContent));
+        assert_eq!(Reaction::new("  ", &target), Err(Nip25Error::EmptyContent));
+        assert_eq!(
+            Reaction::new("+", &NostrNote::default()),
+            Err(Nip25Error::MissingTargetId)
+        );
+        assert_eq!(Reaction::from_note(&target), Err(Nip25Error::WrongKind(1)));
+        let untagged = NostrNote {
+            kind: 7,
+            content: "+".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            Reaction::from_note(&untagged),
+            Err(Nip25Error::MissingEventTag)
+        );
+
+        let mut empty = untagged;
+        empty.content = String::new();
+        empty.tags.add_event_tag(target.id.as_deref().unwrap());
+        assert!(Reaction::from_note(&empty).unwrap().is_positive());
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{
+    keypair::{DelegationToken, NostrKeypair, SignerError},
+    notes::{NostrNote, NostrTag},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip26Error {
+    Signing(SignerError),
+    MissingDelegationTag,
+    InvalidConditions(String),
+    InvalidToken,
+    ConditionsNotMet,
+}
+impl std::fmt::Display for Nip26Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Nip26Error::Signing(e) => write!(f, "{}", e),
+            Nip26Error::MissingDelegationTag => write!(f, "Note has no delegation tag"),
+            Nip26Error::InvalidConditions(conditions) => {
+                write!(f, "Invalid delegation conditions: {}", conditions)
+            }
+            Nip26Error::InvalidToken => write!(f, "Delegation token signature is invalid"),
+            Nip26Error::ConditionsNotMet => {
+                write!(f, "Note is outside the delegation conditions")
+            }
+        }
+    }
+}
+impl std::error::Error for Nip26Error {}
+
+/// What a delegatee may sign: any of `kinds`, created strictly between `since` and `until`.
+/// `None` leaves that condition out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DelegationConditions {
+    pub kinds: Option<Vec<u32>>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+}
+
+impl DelegationConditions {
+    /// Whether `note` falls within these conditions.
+    pub fn allows(&self, note: &NostrNote) -> bool {
+        self.kinds
+            .as_ref()
+            .is_none_or(|kinds| kinds.contains(&note.kind))
+            && self.since.is_none_or(|since| note.created_at > since)
+            && self.until.is_none_or(|until| note.created_at < until)
+    }
+}
+
+/// The NIP-26 query string, such as `kind=1&created_at>1674834236&created_at<1677426236`.
+impl std::fmt::Display for DelegationConditions {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut conditions: Vec<String> = self
+            .kinds
+            .iter()
+            .flatten()
+            .map(|kind| format!("kind={}", kind))
+            .collect();
+        conditions.extend(self.since.map(|since| format!("created_at>{}", since)));
+        conditions.extend(self.until.map(|until| format!("created_at<{}", until)));
+        write!(f, "{}", conditions.join("&"))
+    }
+}
+
+impl std::str::FromStr for DelegationConditions {
+    type Err = Nip26Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Nip26Error::InvalidConditions(s.to_string());
+        let mut conditions = DelegationConditions::default();
+        for condition in s.split('&').filter(|condition| !condition.is_empty()) {
+            if let Some(kind) = condition.strip_prefix("kind=") {
+                let kind = kind.parse().map_err(|_| invalid())?;
+                conditions.kinds.get_or_insert_with(Vec::new).push(kind);
+            } else if let Some(since) = condition.strip_prefix("created_at>") {
+                conditions.since = Some(since.parse().map_err(|_| invalid())?);
+            } else if let Some(until) = condition.strip_prefix("created_at<") {
+                conditions.until = Some(until.parse().map_err(|_| invalid())?);
+            } else {
+                return Err(invalid());
+            }
+        }
+        Ok(conditions)
+    }
+}
+
+impl DelegationToken {
+    /// Signs a delegation from `delegator` to `delegatee_pubkey` under `conditions`.
+    pub fn create(
+        delegator: &NostrKeypair,
+        delegatee_pubkey: &str,
+        conditions: &DelegationConditions,
+    ) -> Result<DelegationToken, Nip26Error> {
+        delegator
+            .sign_delegation(delegatee_pubkey, &conditions.to_string())
+            .map_err(Nip26Error::Signing)
+    }
+}
+
+pub struct Nip26;
+
+impl Nip26 {
+    /// Adds the `delegation` tag; sign `note` with the delegatee's keys afterwards.
+    pub fn attach_to_note(note: &mut NostrNote, token: &DelegationToken) {
+        note.add_delegation_tag(&token.pubkey, &token.conditions, &token.token);
+    }
+    /// Checks the `delegation` tag of `note` against its pubkey, kind and
+    /// `created_at`, returning the delegator pubkey. The note's own signature is
+    /// not checked here, see `NostrNote::verify`.
+    pub fn verify_delegation(note: &NostrNote) -> Result<String, Nip26Error> {
+        let tag = note
+            .tags
+            .0
+            .iter()
+            .find(|tag_list| tag_list.tag_type == NostrTag::Custom("delegation"))
+            .ok_or(Nip26Error::MissingDelegationTag)?;
+        let [pubkey, conditions, token] = tag.tags.as_slice() else {
+            return Err(Nip26Error::InvalidToken);
+        };
+        let token = DelegationToken {
+            pubkey: pubkey.clone(),
+            delegatee: note.pubkey.clone(),
+            conditions: conditions.clone(),
+            token: token.clone(),
+        };
+        if !NostrKeypair::verify_delegation(&token) {
+            return Err(Nip26Error::InvalidToken);
+        }
+        if !conditions.parse::<DelegationConditions>()?.allows(note) {
+            return Err(Nip26Error::ConditionsNotMet);
+        }
+        Ok(token.pubkey)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delegated_note(delegatee: &NostrKeypair, kind: u32, created_at: i64) -> NostrNote {
+        NostrNote {
+            pubkey: delegatee.public_key(),
+            kind,
+            created_at,
+            content: "delegated".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_conditions_string() {
+        let conditions = DelegationConditions {
+            kinds: Some(vec![1, 7]),
+            since: Some(1674834236),
+            until: Some(1677426236),
+        };
+        let string = "kind=1&kind=7&created_at>1674834236&created_at<1677426236";
+        assert_eq!(conditions.to_string(), string);
+        assert_eq!(string.parse(), Ok(conditions));
+        assert_eq!(DelegationConditions::default().to_string(), "");
+        assert_eq!("".parse(), Ok(DelegationConditions::default()));
+        assert_eq!(
+            "kind=one".parse::<DelegationConditions>(),
+            Err(Nip26Error::InvalidConditions("kind=one".to_string()))
+        );
+        assert!("created_at=5".parse::<DelegationConditions>().is_err());
+    }
+
+    #[test]
+    fn test_delegated_note() {
+        let delegator = NostrKeypair::generate(false);
+        let delegatee = NostrKeypair::generate(false);
+        let conditions = DelegationConditions {
+            kinds: Some(vec![1]),
+            since: Some(1674834236),
+            until: Some(1677426236),
+        };
+        let token = DelegationToken::create(&delegator, &delegatee.public_key(), &conditions)
+            .expect("Failed to create delegation");
+        assert_eq!(token.conditions, conditions.to_string());
+
+        let mut note = delegated_note(&delegatee, 1, 1675000000);
+        Nip26::attach_to_note(&mut note, &token);
+        delegatee.sign_note(&mut note).expect("Failed to sign note");
+        assert!(note.verify());
+        assert_eq!(Nip26::verify_delegation(&note), Ok(delegator.public_key()));
+
+        for (kind, created_at) in [(7, 1675000000), (1, 1674834236), (1, 1677426236)] {
+            let mut note = delegated_note(&delegatee, kind, created_at);
+            Nip26::attach_to_note(&mut note, &token);
+            assert_eq!(
+                Nip26::verify_delegation(&note),
+                Err(Nip26Error::ConditionsNotMet)
+            );
+        }
+    }
+
+    #[test]
+    fn test_delegation_errors() {
+        let delegator = NostrKeypair::generate(false);
+        let delegatee = NostrKeypair::generate(false);
+        let conditions = DelegationConditions::default();
+        assert!(matches!(
+            DelegationToken::create(&delegator, "not a pubkey", &conditions),
+            Err(Nip26Error::Signing(SignerError::InvalidPubkey(_)))
+        ));
+        assert_eq!(
+            Nip26::verify_delegation(&NostrNote::default()),
+            Err(Nip26Error::MissingDelegationTag)
+        );
+
+        let token = DelegationToken::create(&delegator, &delegatee.public_key(), &conditions)
+            .expect("Failed to create delegation");
+        let impostor = NostrKeypair::generate(false);
+        let mut note = delegated_note(&impostor, 1, 1675000000);
+        Nip26::attach_to_note(&mut note, &token);
+        assert_eq!(
+            Nip26::verify_delegation(&note),
+            Err(Nip26Error::InvalidToken)
+        );
+
+        let mut note = delegated_note(&delegatee, 1, 1675000000);
+        note.add_delegation_tag(&token.pubkey, &token.conditions, "00");
+        assert_eq!(
+            Nip26::verify_delegation(&note),
+            Err(Nip26Error::InvalidToken)
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use serde::{Deserialize, Serialize};
+
+use crate::notes::{NostrNote, NostrTag, TagList};
+
+const CHANNEL_CREATE_KIND: u32 = 40;
+const CHANNEL_METADATA_KIND: u32 = 41;
+const CHANNEL_MESSAGE_KIND: u32 = 42;
+const CHANNEL_HIDE_KIND: u32 = 43;
+const CHANNEL_MUTE_KIND: u32 = 44;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip28Error {
+    WrongKind(u32),
+    InvalidContent(String),
+}
+impl std::fmt::Display for Nip28Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Nip28Error::WrongKind(kind) => {
+                write!(
+                    f,
+                    "Expected a kind {} note, found {}",
+                    CHANNEL_METADATA_KIND, kind
+                )
+            }
+            Nip28Error::InvalidContent(e) => write!(f, "Invalid channel metadata: {}", e),
+        }
+    }
+}
+impl std::error::Error for Nip28Error {}
+
+// The marked `e` tag linking kind 41 and 42 notes to their channel.
+fn channel_tag(channel_id: &str, relay_url: &str) -> TagList {
+    TagList {
+        tag_type: NostrTag::Event,
+        tags: vec![
+            channel_id.to_string(),
+            relay_url.to_string(),
+            "root".to_string(),
+        ],
+    }
+}
+
+fn reason_content(reason: Option<&str>) -> String {
+    reason
+        .map(|reason| serde_json::json!({ "reason": reason }).to_string())
+        .unwrap_or_default()
+}
+
+/// Kind 40, which opens a channel; its id is the channel id.
+pub struct ChannelCreate;
+
+impl ChannelCreate {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(name: &str, about: &str, picture: &str) -> NostrNote {
+        NostrNote {
+            kind: CHANNEL_CREATE_KIND,
+            content: serde_json::json!({ "name": name, "about": about, "picture": picture })
+                .to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// The JSON content of kind 40 and 41 notes; fields left out are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub about: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub picture: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub relays: Vec<String>,
+}
+
+impl ChannelMetadata {
+    /// Unsigned kind 41 note updating the channel created by `channel_id`.
+    pub fn update_note(&self, channel_id: &str, relay_url: &str) -> NostrNote {
+        let mut note = NostrNote {
+            kind: CHANNEL_METADATA_KIND,
+            content: serde_json::to_string(self).unwrap_or_default(),
+            ..Default::default()
+        };
+        note.tags.0.push(channel_tag(channel_id, relay_url));
+        note
+    }
+    pub fn parse(note: &NostrNote) -> Result<Self, Nip28Error> {
+        if note.kind != CHANNEL_METADATA_KIND {
+            return Err(Nip28Error::WrongKind(note.kind));
+        }
+        serde_json::from_str(&note.content).map_err(|e| Nip28Error::InvalidContent(e.to_string()))
+    }
+}
+
+/// Kind 42, a message posted to a channel.
+pub struct ChannelMessage;
+
+impl ChannelMessage {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(content: &str, channel_id: &str, relay_url: &str) -> NostrNote {
+        let mut note = NostrNote {
+            kind: CHANNEL_MESSAGE_KIND,
+            content: content.to_string(),
+            ..Default::default()
+        };
+        note.tags.0.push(channel_tag(channel_id, relay_url));
+        note
+    }
+}
+
+/// Kind 43, hiding a channel message from the sender's own view.
+pub struct ChannelHide;
+
+impl ChannelHide {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(message_id: &str, reason: Option<&str>) -> NostrNote {
+        let mut note = NostrNote {
+            kind: CHANNEL_HIDE_KIND,
+            content: reason_content(reason),
+            ..Default::default()
+        };
+        note.tags.add_event_tag(message_id);
+        note
+    }
+}
+
+/// Kind 44, muting a user in channels for the sender.
+pub struct ChannelMute;
+
+impl ChannelMute {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(pubkey: &str, reason: Option<&str>) -> NostrNote {
+        let mut note = NostrNote {
+            kind: CHANNEL_MUTE_KIND,
+            content: reason_content(reason),
+            ..Default::default()
+        };
+        note.tags.add_pubkey_tag(pubkey);
+        note
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHANNEL: &str = "25e5c82273a271cb1a840d0060391a0bf4965cafeb029d5ab55350b418953fbb";
+    const MESSAGE: &str = "d5b6dd6b92c3d637bcdbc4f9ad5ac6f9a4d1e0e6d18dfa6a2c4689d0bb1187a3";
+    const PUBKEY: &str = "6e468422dfb74a5738702a8823b9b28168abab8655faacb6853cd0ee15deee93";
+    const RELAY: &str = "wss://relay.example.com";
+
+    #[test]
+    fn test_channel_create() {
+        let note = ChannelCreate::new(
+            "Demo Channel",
+            "A test channel.",
+            "https://placekitten.com/200/200",
+        );
+        assert_eq!(note.kind, 40);
+        assert!(note.tags.0.is_empty());
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&note.content).unwrap(),
+            serde_json::json!({
+                "name": "Demo Channel",
+                "about": "A test channel.",
+                "picture": "https://placekitten.com/200/200"
+            })
+        );
+    }
+
+    #[test]
+    fn test_channel_metadata() {
+        let metadata = ChannelMetadata {
+            name: Some("Updated Demo Channel".to_string()),
+            about: None,
+            picture: Some("https://placekitten.com/201/201".to_string()),
+            relays: vec![RELAY.to_string()],
+        };
+        let note = metadata.update_note(CHANNEL, RELAY);
+        assert_eq!(note.kind, 41);
+        assert_eq!(
+            serde_json::to_value(&note.tags).unwrap(),
+            serde_json::json!([["e", CHANNEL, RELAY, "root"]])
+        );
+        assert_eq!(ChannelMetadata::parse(&note), Ok(metadata));
+
+        let partial = NostrNote {
+            kind: 41,
+            content: r#"{"about":"Now with a description","extra":1}"#.to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            ChannelMetadata::parse(&partial).unwrap().about.as_deref(),
+            Some("Now with a description")
+        );
+        assert_eq!(
+            ChannelMetadata::parse(&ChannelCreate::new("a", "b", "c")),
+            Err(Nip28Error::WrongKind(40))
+        );
+        assert!(matches!(
+            ChannelMetadata::parse(&NostrNote {
+                kind: 41,
+                content: "not json".to_string(),
+                ..Default::default()
+            }),
+            Err(Nip28Error::InvalidContent(_))
+        ));
+    }
+
+    #[test]
+    fn test_channel_message() {
+        let note = ChannelMessage::new("hello channel", CHANNEL, RELAY);
+        assert_eq!(note.kind, 42);
+        assert_eq!(note.content, "hello channel");
+        assert_eq!(
+            serde_json::to_value(&note.tags).unwrap(),
+            serde_json::json!([["e", CHANNEL, RELAY, "root"]])
+        );
+    }
+
+    #[test]
+    fn test_channel_moderation() {
+        let hide = ChannelHide::new(MESSAGE, Some("Spam"));
+        assert_eq!(hide.kind, 43);
+        assert_eq!(
+            serde_json::to_value(&hide.tags).unwrap(),
+            serde_json::json!([["e", MESSAGE]])
+        );
+        assert_eq!(hide.content, r#"{"reason":"Spam"}"#);
+
+        let mute = ChannelMute::new(PUBKEY, None);
+        assert_eq!(mute.kind, 44);
+        assert_eq!(
+            serde_json::to_value(&mute.tags).unwrap(),
+            serde_json::json!([["p", PUBKEY]])
+        );
+        assert!(mute.content.is_empty());
+    }
+}
@@ -0,0 +1,277 @@
+use crate::{
+    keypair::{NostrKeypair, SignerError},
+    notes::{NostrNote, NostrTag, TagList},
+};
+
+const LABEL_KIND: u32 = 1985;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip32Error {
+    WrongKind(u32),
+    EmptyNamespace,
+    EmptyLabel,
+    MissingTargets,
+    /// Several `l` tags that do not line up one to one with the labeled targets.
+    MismatchedLabels {
+        labels: usize,
+        targets: usize,
+    },
+    Signing(SignerError),
+}
+impl std::fmt::Display for Nip32Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Nip32Error::WrongKind(kind) => {
+                write!(f, "Expected a kind {} note, found {}", LABEL_KIND, kind)
+            }
+            Nip32Error::EmptyNamespace => write!(f, "Label namespace is empty"),
+            Nip32Error::EmptyLabel => write!(f, "Label is empty"),
+            Nip32Error::MissingTargets => write!(f, "Label note has no e or p tags"),
+            Nip32Error::MismatchedLabels { labels, targets } => {
+                write!(f, "{} labels for {} targets", labels, targets)
+            }
+            Nip32Error::Signing(e) => write!(f, "{}", e),
+        }
+    }
+}
+impl std::error::Error for Nip32Error {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LabelTarget {
+    Event(String),
+    Pubkey(String),
+}
+
+/// One label applied to one note or profile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelEntry {
+    /// The `L` tag vocabulary the label belongs to.
+    pub namespace: String,
+    pub label: String,
+    pub target: LabelTarget,
+}
+
+/// Collects labeled targets under one namespace and signs them as a kind 1985 note.
+/// Each target gets its own `e` or `p` tag followed by its `l` tag.
+#[derive(Debug, Clone, Default)]
+pub struct LabelBuilder {
+    namespace: String,
+    entries: Vec<(LabelTarget, String)>,
+}
+
+impl LabelBuilder {
+    pub fn new(namespace: &str) -> Self {
+        LabelBuilder {
+            namespace: namespace.to_string(),
+            entries: vec![],
+        }
+    }
+    pub fn label_event(self, event_id: &str, label: &str) -> Self {
+        self.push(LabelTarget::Event(event_id.to_string()), label)
+    }
+    pub fn label_pubkey(self, pubkey: &str, label: &str) -> Self {
+        self.push(LabelTarget::Pubkey(pubkey.to_string()), label)
+    }
+    fn push(mut self, target: LabelTarget, label: &str) -> Self {
+        self.entries.push((target, label.to_string()));
+        self
+    }
+    /// Fails on a blank namespace or label, or when nothing was labeled.
+    pub fn build(self, signer: &NostrKeypair) -> Result<NostrNote, Nip32Error> {
+        if self.namespace.trim().is_empty() {
+            return Err(Nip32Error::EmptyNamespace);
+        }
+        if self.entries.is_empty() {
+            return Err(Nip32Error::MissingTargets);
+        }
+        let mut note = NostrNote {
+            kind: LABEL_KIND,
+            pubkey: signer.public_key(),
+            ..Default::default()
+        };
+        note.tags.0.push(TagList {
+            tag_type: NostrTag::Custom("L"),
+            tags: vec![self.namespace.clone()],
+        });
+        for (target, label) in self.entries {
+            if label.trim().is_empty() {
+                return Err(Nip32Error::EmptyLabel);
+            }
+            let (tag_type, value) = match target {
+                LabelTarget::Event(id) => (NostrTag::Event, id),
+                LabelTarget::Pubkey(pubkey) => (NostrTag::Pubkey, pubkey),
+            };
+            note.tags.0.push(TagList {
+                tag_type,
+                tags: vec![value],
+            });
+            note.tags.0.push(TagList {
+                tag_type: NostrTag::Custom("l"),
+                tags: vec![label, self.namespace.clone()],
+            });
+        }
+        signer.sign_note(&mut note).map_err(Nip32Error::Signing)?;
+        Ok(note)
+    }
+}
+
+/// Reads kind 1985 notes back into their labels.
+pub struct LabelNote;
+
+impl LabelNote {
+    /// Pairs the `l` tags with the `e` and `p` tags in order. A single `l` tag
+    /// applies to every target, as most NIP-32 clients publish it.
+    pub fn from_note(note: &NostrNote) -> Result<Vec<LabelEntry>, Nip32Error> {
+        if note.kind != LABEL_KIND {
+            return Err(Nip32Error::WrongKind(note.kind));
+        }
+        let namespace = note
+            .tags
+            .first_tag_value("L")
+            .filter(|namespace| !namespace.trim().is_empty())
+            .ok_or(Nip32Error::EmptyNamespace)?;
+        let targets: Vec<LabelTarget> = note
+            .tags
+            .0
+            .iter()
+            .filter_map(|tag_list| match tag_list.tag_type {
+                NostrTag::Event => tag_list.tags.first().cloned().map(LabelTarget::Event),
+                NostrTag::Pubkey => tag_list.tags.first().cloned().map(LabelTarget::Pubkey),
+                _ => None,
+            })
+            .collect();
+        if targets.is_empty() {
+            return Err(Nip32Error::MissingTargets);
+        }
+        let labels: Vec<&[String]> = note.tags.iter_tags_of_type("l").collect();
+        if labels.len() != 1 && labels.len() != targets.len() {
+            return Err(Nip32Error::MismatchedLabels {
+                labels: labels.len(),
+                targets: targets.len(),
+            });
+        }
+        targets
+            .into_iter()
+            .enumerate()
+            .map(|(index, target)| {
+                let values = labels[index.min(labels.len() - 1)];
+                let label = values
+                    .first()
+                    .filter(|label| !label.trim().is_empty())
+                    .ok_or(Nip32Error::EmptyLabel)?;
+                Ok(LabelEntry {
+                    namespace: values.get(1).map_or(namespace, String::as_str).to_string(),
+                    label: label.clone(),
+                    target,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALICE: &str = "b0635d6a9851d3aed0cd6c495b282167acf761729078d975fc341b22650b07b9";
+    const NOTE: &str = "4376c65d2f232afbe9b882a35baa4f6fe8667c4e684749af565f981833ed6a65";
+
+    #[test]
+    fn test_label_round_trip() {
+        let keys = NostrKeypair::generate(false);
+        let note = LabelBuilder::new("ISO-639-1")
+            .label_event(NOTE, "en")
+            .label_pubkey(ALICE, "es")
+            .build(&keys)
+            .expect("Failed to build label");
+        assert_eq!(note.kind, 1985);
+        assert!(note.verify());
+        assert_eq!(
+            serde_json::to_value(&note.tags).unwrap(),
+            serde_json::json!([
+                ["L", "ISO-639-1"],
+                ["e", NOTE],
+                ["l", "en", "ISO-639-1"],
+                ["p", ALICE],
+                ["l", "es", "ISO-639-1"]
+            ])
+        );
+        assert_eq!(
+            LabelNote::from_note(&note),
+            Ok(vec![
+                LabelEntry {
+                    namespace: "ISO-639-1".to_string(),
+                    label: "en".to_string(),
+                    target: LabelTarget::Event(NOTE.to_string()),
+                },
+                LabelEntry {
+                    namespace: "ISO-639-1".to_string(),
+                    label: "es".to_string(),
+                    target: LabelTarget::Pubkey(ALICE.to_string()),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_label_shared_by_targets() {
+        let note: NostrNote = serde_json::from_value(serde_json::json!({
+            "pubkey": ALICE,
+            "created_at": 1_700_000_000,
+            "kind": 1985,
+            "tags": [["L", "#t"], ["l", "nostr", "#t"], ["e", NOTE], ["p", ALICE]],
+            "content": ""
+        }))
+        .unwrap();
+        let entries = LabelNote::from_note(&note).expect("Failed to parse labels");
+        assert_eq!(entries.len(), 2);
+        assert!(entries
+            .iter()
+            .all(|entry| entry.label == "nostr" && entry.namespace == "#t"));
+        assert_eq!(entries[1].target, LabelTarget::Pubkey(ALICE.to_string()));
+    }
+
+    #[test]
+    fn test_label_errors() {
+        let keys = NostrKeypair::generate(false);
+        for namespace in ["", "  "] {
+            assert_eq!(
+                LabelBuilder::new(namespace)
+                    .label_event(NOTE, "en")
+                    .build(&keys),
+                Err(Nip32Error::EmptyNamespace)
+            );
+        }
+        assert_eq!(
+            LabelBuilder::new("ISO-639-1").build(&keys),
+            Err(Nip32Error::MissingTargets)
+        );
+        assert_eq!(
+            LabelBuilder::new("ISO-639-1")
+                .label_event(NOTE, "")
+                .build(&keys),
+            Err(Nip32Error::EmptyLabel)
+        );
+        assert_eq!(
+            LabelNote::from_note(&NostrNote::default()),
+            Err(Nip32Error::WrongKind(1))
+        );
+
+        let mut note = LabelBuilder::new("ISO-639-1")
+            .label_event(NOTE, "en")
+            .label_pubkey(ALICE, "es")
+            .label_event(NOTE, "fr")
+            .build(&keys)
+            .unwrap();
+        note.tags.remove_tag("l", "fr");
+        assert_eq!(
+            LabelNote::from_note(&note),
+            Err(Nip32Error::MismatchedLabels {
+                labels: 2,
+                targets: 3
+            })
+        );
+        note.tags.remove_all_of_type("L");
+        assert_eq!(LabelNote::from_note(&note), Err(Nip32Error::EmptyNamespace));
+    }
+}
@@ -0,0 +1,48 @@
+use crate::{
+    keypair::NostrKeypair,
+    notes::{NostrNote, NostrTag},
+};
+
+pub struct Nip42;
+
+impl Nip42 {
+    /// Signed kind 22242 note answering `challenge` from the relay at `relay_url`.
+    pub fn auth_note(keypair: &NostrKeypair, relay_url: &str, challenge: &str) -> NostrNote {
+        let mut note = NostrNote {
+            pubkey: keypair.public_key(),
+            kind: 22242,
+            ..Default::default()
+        };
+        note.tags
+            .add_custom_tag(NostrTag::Custom("relay"), relay_url);
+        note.tags
+            .add_custom_tag(NostrTag::Custom("challenge"), challenge);
+        // The pubkey comes from the keypair, so signing cannot fail.
+        keypair
+            .sign_note(&mut note)
+            .expect("Failed to sign auth note");
+        note
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auth_note() {
+        let keys = NostrKeypair::generate(false);
+        let note = Nip42::auth_note(&keys, "wss://relay.example.com", "challenge");
+        assert_eq!(note.kind, 22242);
+        assert_eq!(note.pubkey, keys.public_key());
+        assert_eq!(
+            note.tags.find_tags_ref("relay").collect::<Vec<_>>(),
+            vec!["wss://relay.example.com"]
+        );
+        assert_eq!(
+            note.tags.find_tags_ref("challenge").collect::<Vec<_>>(),
+            vec!["challenge"]
+        );
+        assert!(note.verify());
+    }
+}
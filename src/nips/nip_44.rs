@@ -7,7 +7,36 @@ use secp256k1::rand::rngs::OsRng;
 use secp256k1::rand::RngCore;
 use sha2::Sha256;
 
-use crate::keypair::NostrKeypair;
+use crate::{keypair::NostrKeypair, notes::NostrNote};
+
+type PayloadComponents<'a> = (&'a [u8], &'a [u8], &'a [u8], &'a [u8]);
+
+// The version byte this implementation has always written, the ASCII digit "1".
+const VERSION: u8 = b'1';
+
+#[derive(Debug)]
+pub enum Nip44Error {
+    UnsupportedVersion(u8),
+    DecryptionError(String),
+    EncryptionError(String),
+}
+impl std::fmt::Display for Nip44Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Nip44Error::UnsupportedVersion(version) => {
+                write!(f, "Unsupported NIP-44 version: {:#04x}", version)
+            }
+            Nip44Error::DecryptionError(e) => write!(f, "Decryption error: {}", e),
+            Nip44Error::EncryptionError(e) => write!(f, "Encryption error: {}", e),
+        }
+    }
+}
+impl std::error::Error for Nip44Error {}
+impl Nip44Error {
+    fn decryption(e: impl std::fmt::Display) -> Self {
+        Nip44Error::DecryptionError(e.to_string())
+    }
+}
 
 pub struct Nip44 {
     private_key: NostrKeypair,
@@ -20,22 +49,72 @@ impl Nip44 {
             peer_pubkey,
         }
     }
-    pub fn nip_44_encrypt(&self, plaintext: String) -> anyhow::Result<String> {
+    pub fn nip_44_encrypt(&self, plaintext: impl AsRef<str>) -> anyhow::Result<String> {
         let shared_secret = self.private_key.get_shared_point(&self.peer_pubkey)?;
         let conversation_key = Self::derive_conversation_key(&shared_secret, b"nip44-v2")?;
         let nonce = Self::generate_nonce();
-        let cypher_text = Self::encrypt(plaintext.as_bytes(), &conversation_key, &nonce)?;
+        let cypher_text = Self::encrypt(plaintext.as_ref().as_bytes(), &conversation_key, &nonce)?;
         let mac = Self::calculate_mac(&cypher_text, &conversation_key)?;
-        let encoded_params = Self::base64_encode_params(b"1", &nonce, &cypher_text, &mac);
+        let encoded_params = Self::base64_encode_params(&[VERSION], &nonce, &cypher_text, &mac);
         Ok(encoded_params)
     }
-    pub fn nip_44_decrypt(&self, cyphertext: String) -> anyhow::Result<String> {
-        let shared_secret = self.private_key.get_shared_point(&self.peer_pubkey)?;
-        let conversation_key = Self::derive_conversation_key(&shared_secret, b"nip44-v2")?;
-        let decoded = general_purpose::STANDARD.decode(cyphertext.as_bytes())?;
-        let (_version, nonce, ciphertext, _mac) = Self::extract_components(&decoded)?;
-        let decrypted = Self::decrypt(&ciphertext, &conversation_key, &nonce)?;
-        Ok(String::from_utf8(decrypted)?)
+    pub fn nip44_encrypt_note_for(
+        &self,
+        note: &mut NostrNote,
+        recipient_pubkey: &str,
+        relay_hint: Option<&str>,
+    ) -> Result<(), Nip44Error> {
+        let recipient = Nip44::new(self.private_key.clone(), recipient_pubkey.to_string());
+        let encrypted_content = recipient
+            .nip_44_encrypt(&note.content)
+            .map_err(|e| Nip44Error::EncryptionError(e.to_string()))?;
+        note.tags
+            .add_pubkey_tag_with_relay(recipient_pubkey, relay_hint);
+        note.content = encrypted_content;
+        Ok(())
+    }
+    pub fn nip_44_decrypt(&self, cyphertext: impl AsRef<str>) -> Result<String, Nip44Error> {
+        let decoded = Self::decode_payload(cyphertext.as_ref())?;
+        let conversation_key = self.conversation_key_with(&self.peer_pubkey)?;
+        let (_version, nonce, ciphertext, _mac) =
+            Self::extract_components(&decoded).map_err(Nip44Error::decryption)?;
+        let decrypted =
+            Self::decrypt(ciphertext, &conversation_key, nonce).map_err(Nip44Error::decryption)?;
+        String::from_utf8(decrypted).map_err(Nip44Error::decryption)
+    }
+    /// Checks the payload's MAC against the conversation key shared with `peer_pubkey`
+    /// without decrypting it, so tampered payloads can be dropped early.
+    pub fn verify_mac(
+        &self,
+        ciphertext_payload: &str,
+        peer_pubkey: &str,
+    ) -> Result<bool, Nip44Error> {
+        let decoded = Self::decode_payload(ciphertext_payload)?;
+        let conversation_key = self.conversation_key_with(peer_pubkey)?;
+        let (_version, _nonce, ciphertext, mac) =
+            Self::extract_components(&decoded).map_err(Nip44Error::decryption)?;
+        let mut expected =
+            Hmac::<Sha256>::new_from_slice(&conversation_key).map_err(Nip44Error::decryption)?;
+        expected.update(ciphertext);
+        Ok(expected.verify_slice(mac).is_ok())
+    }
+    fn decode_payload(payload: &str) -> Result<Vec<u8>, Nip44Error> {
+        let decoded = general_purpose::STANDARD
+            .decode(payload.as_bytes())
+            .map_err(Nip44Error::decryption)?;
+        // Check the version before the layout, newer versions may lay out the payload differently.
+        match decoded.first() {
+            Some(&VERSION) => Ok(decoded),
+            Some(&version) => Err(Nip44Error::UnsupportedVersion(version)),
+            None => Err(Nip44Error::decryption("Empty payload")),
+        }
+    }
+    fn conversation_key_with(&self, peer_pubkey: &str) -> Result<[u8; 32], Nip44Error> {
+        let shared_secret = self
+            .private_key
+            .get_shared_point(peer_pubkey)
+            .map_err(Nip44Error::decryption)?;
+        Self::derive_conversation_key(&shared_secret, b"nip44-v2").map_err(Nip44Error::decryption)
     }
     fn encrypt(content: &[u8], key: &[u8], nonce: &[u8]) -> anyhow::Result<Vec<u8>> {
         let mut cipher = ChaCha20::new(key.into(), nonce.into());
@@ -68,9 +147,7 @@ impl Nip44 {
         hkdf.expand(&[], &mut okm).map_err(|e| anyhow::anyhow!(e))?;
         Ok(okm)
     }
-    fn extract_components(
-        decoded: &[u8],
-    ) -> anyhow::Result<(&[u8], &[u8], &[u8], &[u8])> {
+    fn extract_components(decoded: &[u8]) -> anyhow::Result<PayloadComponents<'_>> {
         const VERSION_SIZE: usize = 1;
         const NONCE_SIZE: usize = 12;
         const MAC_SIZE: usize = 32;
@@ -144,9 +221,80 @@ mod tests {
             peer_pubkey: user_keys_1.public_key(),
         };
         let plaintext = "Hello, World!".to_string();
-        let cyphertext = nip_44_1.nip_44_encrypt(plaintext.clone()).unwrap();
+        let cyphertext = nip_44_1.nip_44_encrypt(&plaintext).unwrap();
+        let borrowed = nip_44_2.nip_44_decrypt(&cyphertext).unwrap();
         let decrypted = nip_44_2.nip_44_decrypt(cyphertext).unwrap();
 
         assert_eq!(decrypted, plaintext);
+        assert_eq!(borrowed, plaintext);
+    }
+
+    #[test]
+    fn test_nip44_encrypt_note_for() {
+        let sender = crate::keypair::NostrKeypair::generate(false);
+        let recipient = crate::keypair::NostrKeypair::generate(false);
+        let mut note = NostrNote {
+            pubkey: sender.public_key(),
+            content: "secret".to_string(),
+            ..Default::default()
+        };
+        Nip44::new(sender.clone(), recipient.public_key())
+            .nip44_encrypt_note_for(&mut note, &recipient.public_key(), None)
+            .expect("Failed to encrypt note");
+        assert_ne!(note.content, "secret");
+        assert_eq!(
+            note.tags.find_first_tagged_pubkey(),
+            Some(recipient.public_key())
+        );
+        sender.sign_note(&mut note).expect("Failed to sign note");
+        assert_eq!(recipient.decrypt_nip_44_content(&note).unwrap(), "secret");
+    }
+
+    #[test]
+    fn test_nip_44_unsupported_version() {
+        let user_keys_1 = crate::keypair::NostrKeypair::generate(true);
+        let user_keys_2 = crate::keypair::NostrKeypair::generate(true);
+        let nip_44_1 = Nip44::new(user_keys_1.clone(), user_keys_2.public_key());
+        let nip_44_2 = Nip44::new(user_keys_2, user_keys_1.public_key());
+        let cyphertext = nip_44_1.nip_44_encrypt("Hello, World!").unwrap();
+        let mut decoded = general_purpose::STANDARD.decode(cyphertext).unwrap();
+
+        decoded[0] = 0x02;
+        let newer_version = general_purpose::STANDARD.encode(&decoded);
+        assert!(matches!(
+            nip_44_2.nip_44_decrypt(newer_version),
+            Err(Nip44Error::UnsupportedVersion(0x02))
+        ));
+
+        decoded[0] = VERSION;
+        decoded.truncate(20);
+        let corrupt = general_purpose::STANDARD.encode(&decoded);
+        assert!(matches!(
+            nip_44_2.nip_44_decrypt(corrupt),
+            Err(Nip44Error::DecryptionError(_))
+        ));
+    }
+
+    #[test]
+    fn test_nip_44_verify_mac() {
+        let sender = crate::keypair::NostrKeypair::generate(false);
+        let recipient = crate::keypair::NostrKeypair::generate(false);
+        let cyphertext = Nip44::new(sender.clone(), recipient.public_key())
+            .nip_44_encrypt("Hello, World!")
+            .unwrap();
+        let nip_44 = Nip44::new(recipient, sender.public_key());
+        assert!(nip_44
+            .verify_mac(&cyphertext, &sender.public_key())
+            .unwrap());
+
+        let mut decoded = general_purpose::STANDARD.decode(&cyphertext).unwrap();
+        decoded[20] ^= 0x01;
+        let tampered = general_purpose::STANDARD.encode(&decoded);
+        assert!(!nip_44.verify_mac(&tampered, &sender.public_key()).unwrap());
+
+        let stranger = crate::keypair::NostrKeypair::generate(false);
+        assert!(!nip_44
+            .verify_mac(&cyphertext, &stranger.public_key())
+            .unwrap());
     }
 }
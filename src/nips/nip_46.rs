@@ -1,5 +1,60 @@
-use crate::{notes::NostrNote, keypair::NostrKeypair};
+use crate::{
+    keypair::{NostrKeypair, SignerError},
+    nips::{Nip04, Nip04Error, Nip44, Nip44Error},
+    notes::NostrNote,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+#[derive(Debug)]
+pub enum Nip46Error {
+    DecryptionFailed(Nip04Error),
+    EncryptionFailed(Nip04Error),
+    Nip44Failed(Nip44Error),
+    SigningFailed(SignerError),
+    SerializationError(serde_json::Error),
+    UnknownCommand(String),
+    /// A request whose `params` are too short for its method.
+    MissingParam {
+        method: String,
+        index: usize,
+    },
+    AuthFailed(String),
+    SessionExpired,
+}
+impl std::fmt::Display for Nip46Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Nip46Error::DecryptionFailed(e) => write!(f, "Could not decrypt note: {}", e),
+            Nip46Error::EncryptionFailed(e) => write!(f, "Could not encrypt note: {}", e),
+            Nip46Error::Nip44Failed(e) => write!(f, "NIP-44 failure: {}", e),
+            Nip46Error::SigningFailed(e) => write!(f, "Could not sign note: {}", e),
+            Nip46Error::SerializationError(e) => write!(f, "Invalid request payload: {}", e),
+            Nip46Error::UnknownCommand(method) => write!(f, "Unknown command: {}", method),
+            Nip46Error::MissingParam { method, index } => {
+                write!(f, "{} request is missing param {}", method, index)
+            }
+            Nip46Error::AuthFailed(reason) => write!(f, "Authentication failed: {}", reason),
+            Nip46Error::SessionExpired => write!(f, "Session expired"),
+        }
+    }
+}
+impl std::error::Error for Nip46Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Nip46Error::DecryptionFailed(e) | Nip46Error::EncryptionFailed(e) => Some(e),
+            Nip46Error::Nip44Failed(e) => Some(e),
+            Nip46Error::SigningFailed(e) => Some(e),
+            Nip46Error::SerializationError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+impl From<serde_json::Error> for Nip46Error {
+    fn from(value: serde_json::Error) -> Self {
+        Nip46Error::SerializationError(value)
+    }
+}
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub enum Nip46Commands {
@@ -22,23 +77,52 @@ pub struct Nip46Response {
 }
 
 impl Nip46Response {
+    #[deprecated(note = "use `try_get_response_note`, which returns decryption and parse errors")]
     pub fn get_response_note(signed_note: &NostrNote, user_keys: &NostrKeypair) -> NostrNote {
-        let decrypted_note_response = user_keys
-            .decrypt_nip_04_content(signed_note)
-            .expect("Could not decrypt note");
-        let response_note =
-            serde_json::from_str::<Nip46Response>(&decrypted_note_response).unwrap();
-        let parsed_note = serde_json::from_str::<NostrNote>(&response_note.result).unwrap();
-        parsed_note
+        Self::try_get_response_note(signed_note, user_keys).expect("Failed to read NIP-46 response")
+    }
+    /// Decrypts a `sign_event` response and parses the signed note it carries.
+    pub fn try_get_response_note(
+        signed_note: &NostrNote,
+        user_keys: &NostrKeypair,
+    ) -> Result<NostrNote, Nip46Error> {
+        let decrypted_note_response = nip04_decrypt(
+            user_keys,
+            signed_note.pubkey.to_string(),
+            signed_note.content.to_string(),
+        )?;
+        let response_note = serde_json::from_str::<Nip46Response>(&decrypted_note_response)?;
+        Ok(serde_json::from_str::<NostrNote>(&response_note.result)?)
     }
 }
 
-impl ToString for Nip46Response {
-    fn to_string(&self) -> String {
-        serde_json::to_string(self).unwrap()
+impl std::fmt::Display for Nip46Response {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let json = serde_json::to_string(self).map_err(|_| std::fmt::Error)?;
+        write!(f, "{}", json)
     }
 }
 
+fn nip04_encrypt(
+    keys: &NostrKeypair,
+    peer_pubkey: String,
+    plaintext: String,
+) -> Result<String, Nip46Error> {
+    Nip04::new(keys.clone(), peer_pubkey)
+        .encrypt(plaintext)
+        .map_err(|e| Nip46Error::EncryptionFailed(Nip04Error::EncryptionError(e.to_string())))
+}
+
+fn nip04_decrypt(
+    keys: &NostrKeypair,
+    peer_pubkey: String,
+    cyphertext: String,
+) -> Result<String, Nip46Error> {
+    Nip04::new(keys.clone(), peer_pubkey)
+        .decrypt(cyphertext)
+        .map_err(|e| Nip46Error::DecryptionFailed(Nip04Error::DecryptionError(e.to_string())))
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct Nip46Request {
     id: String,
@@ -47,7 +131,14 @@ pub struct Nip46Request {
 }
 
 impl Nip46Request {
+    #[deprecated(note = "use `try_ping_request`, which returns encryption and signing errors")]
     pub fn ping_request(client_keys: &NostrKeypair, user_keys: String) -> NostrNote {
+        Self::try_ping_request(client_keys, user_keys).expect("Failed to build NIP-46 request")
+    }
+    pub fn try_ping_request(
+        client_keys: &NostrKeypair,
+        user_keys: String,
+    ) -> Result<NostrNote, Nip46Error> {
         let random_id = format!("nostro2-{}", chrono::Utc::now().timestamp());
         let ping_params = vec!["ping".to_string()];
         let self_try = Self {
@@ -55,10 +146,20 @@ impl Nip46Request {
             method: "ping".to_string(),
             params: ping_params,
         };
-        self_try.sign_request(client_keys, user_keys).unwrap()
+        self_try.sign_request(client_keys, user_keys)
     }
 
+    #[deprecated(
+        note = "use `try_sign_event_request`, which returns encryption and signing errors"
+    )]
     pub fn sign_event_request(note_request: NostrNote, client_keys: &NostrKeypair) -> NostrNote {
+        Self::try_sign_event_request(note_request, client_keys)
+            .expect("Failed to build NIP-46 request")
+    }
+    pub fn try_sign_event_request(
+        note_request: NostrNote,
+        client_keys: &NostrKeypair,
+    ) -> Result<NostrNote, Nip46Error> {
         let random_id = format!("nostro2-{}", chrono::Utc::now().timestamp());
         let note_params = vec![note_request.to_string()];
         let self_try = Self {
@@ -66,12 +167,20 @@ impl Nip46Request {
             method: "sign_event".to_string(),
             params: note_params,
         };
-        self_try
-            .sign_request(client_keys, note_request.pubkey)
-            .unwrap()
+        self_try.sign_request(client_keys, note_request.pubkey)
     }
 
+    #[deprecated(
+        note = "use `try_get_public_key_request`, which returns encryption and signing errors"
+    )]
     pub fn get_public_key_request(client_keys: &NostrKeypair, user_keys: String) -> NostrNote {
+        Self::try_get_public_key_request(client_keys, user_keys)
+            .expect("Failed to build NIP-46 request")
+    }
+    pub fn try_get_public_key_request(
+        client_keys: &NostrKeypair,
+        user_keys: String,
+    ) -> Result<NostrNote, Nip46Error> {
         let random_id = format!("nostro2-{}", chrono::Utc::now().timestamp());
         let ping_params = vec!["get_public_key".to_string()];
         let self_try = Self {
@@ -79,14 +188,14 @@ impl Nip46Request {
             method: "get_public_key".to_string(),
             params: ping_params,
         };
-        self_try.sign_request(client_keys, user_keys).unwrap()
+        self_try.sign_request(client_keys, user_keys)
     }
 
     fn sign_request(
         &self,
         client_keys: &NostrKeypair,
         user_keys: String,
-    ) -> anyhow::Result<NostrNote> {
+    ) -> Result<NostrNote, Nip46Error> {
         let stringified_request = serde_json::to_string(&self)?;
         let mut request_note = NostrNote {
             pubkey: client_keys.public_key(),
@@ -94,44 +203,57 @@ impl Nip46Request {
             content: stringified_request,
             ..Default::default()
         };
-        client_keys.sign_nip_04_encrypted(&mut request_note, user_keys)?;
+        sign_nip04_encrypted(client_keys, &mut request_note, &user_keys)?;
         Ok(request_note)
     }
 
     fn decrypt_request(
         signed_note: &NostrNote,
         user_keys: &NostrKeypair,
-    ) -> anyhow::Result<Nip46Request> {
-        let nip_04_decrypted_note_request = user_keys.decrypt_nip_04_content(signed_note);
-        let nip_44_decrypted_note_request = user_keys.decrypt_nip_44_content(signed_note);
-
-        if nip_04_decrypted_note_request.is_err() && nip_44_decrypted_note_request.is_err() {
-            anyhow::bail!("Could not decrypt note");
-        }
-
-        if nip_04_decrypted_note_request.is_ok() {
-            return Ok(serde_json::from_str::<Nip46Request>(
-                &nip_04_decrypted_note_request?,
-            )?);
-        } else {
-            return Ok(serde_json::from_str::<Nip46Request>(
-                &nip_44_decrypted_note_request?,
-            )?);
-        }
+    ) -> Result<Nip46Request, Nip46Error> {
+        let peer_pubkey = signed_note.pubkey.to_string();
+        let decrypted_note_request = nip04_decrypt(
+            user_keys,
+            peer_pubkey.clone(),
+            signed_note.content.to_string(),
+        )
+        .or_else(|e| {
+            Nip44::new(user_keys.clone(), peer_pubkey)
+                .nip_44_decrypt(&signed_note.content)
+                .map_err(|_| e)
+        })?;
+        Ok(serde_json::from_str::<Nip46Request>(
+            &decrypted_note_request,
+        )?)
     }
 
     pub fn get_request_command(
         signed_note: &NostrNote,
         user_keys: &NostrKeypair,
-    ) -> anyhow::Result<Nip46Commands> {
-        let command_pubkey = signed_note.pubkey.to_string();
+    ) -> Result<Nip46Commands, Nip46Error> {
         let request_note = Self::decrypt_request(signed_note, user_keys)?;
+        Self::command_from_request(signed_note.pubkey.to_string(), request_note)
+    }
+
+    fn command_from_request(
+        command_pubkey: String,
+        request_note: Nip46Request,
+    ) -> Result<Nip46Commands, Nip46Error> {
         let command_id = request_note.id;
+        let param = |index: usize| {
+            request_note
+                .params
+                .get(index)
+                .cloned()
+                .ok_or_else(|| Nip46Error::MissingParam {
+                    method: request_note.method.clone(),
+                    index,
+                })
+        };
         match request_note.method.as_str() {
             "ping" => Ok(Nip46Commands::Ping(command_pubkey, command_id)),
             "sign_event" => {
-                let response_note =
-                    serde_json::from_str::<NostrNote>(&request_note.params[0]).unwrap();
+                let response_note = serde_json::from_str::<NostrNote>(&param(0)?)?;
                 Ok(Nip46Commands::SignEvent(
                     command_pubkey,
                     command_id,
@@ -141,195 +263,179 @@ impl Nip46Request {
             "connect" => Ok(Nip46Commands::Connect(command_pubkey, command_id)),
             "disconnect" => Ok(Nip46Commands::Disconnect(command_pubkey, command_id)),
             "get_public_key" => Ok(Nip46Commands::GetPublickKey(command_pubkey, command_id)),
-            "nip04_encrypt" => Ok(Nip46Commands::Nip04Encrypt(
-                command_pubkey,
-                command_id,
-                request_note.params[1].to_string(),
-                request_note.params[0].to_string(),
-            )),
-            "nip04_decrypt" => Ok(Nip46Commands::Nip04Decrypt(
-                command_pubkey,
-                command_id,
-                request_note.params[1].to_string(),
-                request_note.params[0].to_string(),
-            )),
-            "nip44_encrypt" => Ok(Nip46Commands::Nip44Encrypt(
-                command_pubkey,
-                command_id,
-                request_note.params[1].to_string(),
-                request_note.params[0].to_string(),
-            )),
-            "nip44_decrypt" => Ok(Nip46Commands::Nip44Decrypt(
-                command_pubkey,
-                command_id,
-                request_note.params[1].to_string(),
-                request_note.params[0].to_string(),
-            )),
-            _ => Err(anyhow::anyhow!("Unknown command")),
+            "nip04_encrypt" => {
+                let (peer_pubkey, content) = (param(0)?, param(1)?);
+                Ok(Nip46Commands::Nip04Encrypt(
+                    command_pubkey,
+                    command_id,
+                    content,
+                    peer_pubkey,
+                ))
+            }
+            "nip04_decrypt" => {
+                let (peer_pubkey, content) = (param(0)?, param(1)?);
+                Ok(Nip46Commands::Nip04Decrypt(
+                    command_pubkey,
+                    command_id,
+                    content,
+                    peer_pubkey,
+                ))
+            }
+            "nip44_encrypt" => {
+                let (peer_pubkey, content) = (param(0)?, param(1)?);
+                Ok(Nip46Commands::Nip44Encrypt(
+                    command_pubkey,
+                    command_id,
+                    content,
+                    peer_pubkey,
+                ))
+            }
+            "nip44_decrypt" => {
+                let (peer_pubkey, content) = (param(0)?, param(1)?);
+                Ok(Nip46Commands::Nip44Decrypt(
+                    command_pubkey,
+                    command_id,
+                    content,
+                    peer_pubkey,
+                ))
+            }
+            _ => Err(Nip46Error::UnknownCommand(request_note.method.clone())),
         }
     }
 
+    #[deprecated(
+        note = "use `try_respond_to_command`, which returns encryption and signing errors"
+    )]
     pub fn respond_to_command(user_keys: &NostrKeypair, command: Nip46Commands) -> NostrNote {
-        match command {
-            Nip46Commands::Connect(pubkey, id) => {
-                let response = Nip46Response {
-                    id,
-                    result: "ack".to_string(),
-                    error: None,
-                };
-                let mut response_note = NostrNote {
-                    pubkey: user_keys.public_key(),
-                    kind: 24133,
-                    content: response.to_string(),
-                    ..Default::default()
-                };
-                user_keys
-                    .sign_nip_04_encrypted(&mut response_note, pubkey)
-                    .unwrap();
-                response_note
-            }
-            Nip46Commands::Disconnect(pubkey, id) => {
-                let response = Nip46Response {
-                    id,
-                    result: "ack".to_string(),
-                    error: None,
-                };
-                let mut response_note = NostrNote {
-                    pubkey: user_keys.public_key(),
-                    kind: 24133,
-                    content: response.to_string(),
-                    ..Default::default()
-                };
-                user_keys
-                    .sign_nip_04_encrypted(&mut response_note, pubkey)
-                    .unwrap();
-                response_note
-            }
-            Nip46Commands::Ping(pubkey, id) => {
-                let response = Nip46Response {
-                    id,
-                    result: "pong".to_string(),
-                    error: None,
-                };
-                let mut response_note = NostrNote {
-                    pubkey: user_keys.public_key(),
-                    kind: 24133,
-                    content: response.to_string(),
-                    ..Default::default()
-                };
-                user_keys
-                    .sign_nip_04_encrypted(&mut response_note, pubkey)
-                    .unwrap();
-                response_note
+        Self::try_respond_to_command(user_keys, command)
+            .expect("Failed to respond to NIP-46 command")
+    }
+    /// Runs `command` with `user_keys` and returns the signed, encrypted response.
+    pub fn try_respond_to_command(
+        user_keys: &NostrKeypair,
+        command: Nip46Commands,
+    ) -> Result<NostrNote, Nip46Error> {
+        let (pubkey, id, result) = match command {
+            Nip46Commands::Connect(pubkey, id) | Nip46Commands::Disconnect(pubkey, id) => {
+                (pubkey, id, "ack".to_string())
             }
+            Nip46Commands::Ping(pubkey, id) => (pubkey, id, "pong".to_string()),
             Nip46Commands::SignEvent(pubkey, id, mut note) => {
-                user_keys.sign_nostr_event(&mut note);
-                let response = Nip46Response {
-                    id,
-                    result: note.to_string(),
-                    error: None,
-                };
-                let mut response_note = NostrNote {
-                    pubkey: user_keys.public_key(),
-                    kind: 24133,
-                    content: response.to_string(),
-                    ..Default::default()
-                };
                 user_keys
-                    .sign_nip_04_encrypted(&mut response_note, pubkey)
-                    .unwrap();
-                response_note
-            }
-            Nip46Commands::GetPublickKey(pubkey, id) => {
-                let response = Nip46Response {
-                    id,
-                    result: user_keys.public_key(),
-                    error: None,
-                };
-                let mut response_note = NostrNote {
-                    pubkey: user_keys.public_key(),
-                    kind: 24133,
-                    content: response.to_string(),
-                    ..Default::default()
-                };
-                user_keys
-                    .sign_nip_04_encrypted(&mut response_note, pubkey)
-                    .unwrap();
-                response_note
+                    .sign_note(&mut note)
+                    .map_err(Nip46Error::SigningFailed)?;
+                (pubkey, id, note.to_string())
             }
+            Nip46Commands::GetPublickKey(pubkey, id) => (pubkey, id, user_keys.public_key()),
             Nip46Commands::Nip04Encrypt(pubkey, id, content, key) => {
-                let encrypted_content = user_keys.encrypt_nip_04_plaintext(content, key).unwrap();
-                let response = Nip46Response {
-                    id,
-                    result: encrypted_content,
-                    error: None,
-                };
-                let mut response_note = NostrNote {
-                    pubkey: user_keys.public_key(),
-                    kind: 24133,
-                    content: response.to_string(),
-                    ..Default::default()
-                };
-                user_keys
-                    .sign_nip_04_encrypted(&mut response_note, pubkey)
-                    .unwrap();
-                response_note
+                (pubkey, id, nip04_encrypt(user_keys, key, content)?)
             }
             Nip46Commands::Nip04Decrypt(pubkey, id, content, key) => {
-                let decrypted_content = user_keys.decrypt_nip_04_plaintext(content, key).unwrap();
-                let response = Nip46Response {
-                    id,
-                    result: decrypted_content,
-                    error: None,
-                };
-                let mut response_note = NostrNote {
-                    pubkey: user_keys.public_key(),
-                    kind: 24133,
-                    content: response.to_string(),
-                    ..Default::default()
-                };
-                user_keys
-                    .sign_nip_04_encrypted(&mut response_note, pubkey)
-                    .unwrap();
-                response_note
+                (pubkey, id, nip04_decrypt(user_keys, key, content)?)
             }
             Nip46Commands::Nip44Encrypt(pubkey, id, content, key) => {
-                let encrypted_content = user_keys.encrypt_nip_44_plaintext(content, key).unwrap();
-                let response = Nip46Response {
-                    id,
-                    result: encrypted_content,
-                    error: None,
-                };
-                let mut response_note = NostrNote {
-                    pubkey: user_keys.public_key(),
-                    kind: 24133,
-                    content: response.to_string(),
-                    ..Default::default()
-                };
-                user_keys
-                    .sign_nip_04_encrypted(&mut response_note, pubkey)
-                    .unwrap();
-                response_note
+                let encrypted_content = Nip44::new(user_keys.clone(), key)
+                    .nip_44_encrypt(content)
+                    .map_err(|e| {
+                        Nip46Error::Nip44Failed(Nip44Error::EncryptionError(e.to_string()))
+                    })?;
+                (pubkey, id, encrypted_content)
             }
             Nip46Commands::Nip44Decrypt(pubkey, id, content, key) => {
-                let decrypted_content = user_keys.decrypt_nip_44_plaintext(content, key).unwrap();
-                let response = Nip46Response {
-                    id,
-                    result: decrypted_content,
-                    error: None,
-                };
-                let mut response_note = NostrNote {
-                    pubkey: user_keys.public_key(),
-                    kind: 24133,
-                    content: response.to_string(),
-                    ..Default::default()
-                };
-                user_keys
-                    .sign_nip_04_encrypted(&mut response_note, pubkey)
-                    .unwrap();
-                response_note
+                let decrypted_content = Nip44::new(user_keys.clone(), key)
+                    .nip_44_decrypt(content)
+                    .map_err(Nip46Error::Nip44Failed)?;
+                (pubkey, id, decrypted_content)
+            }
+        };
+        let response = Nip46Response {
+            id,
+            result,
+            error: None,
+        };
+        let mut response_note = NostrNote {
+            pubkey: user_keys.public_key(),
+            kind: 24133,
+            content: response.to_string(),
+            ..Default::default()
+        };
+        sign_nip04_encrypted(user_keys, &mut response_note, &pubkey)?;
+        Ok(response_note)
+    }
+}
+
+fn sign_nip04_encrypted(
+    keys: &NostrKeypair,
+    note: &mut NostrNote,
+    peer_pubkey: &str,
+) -> Result<(), Nip46Error> {
+    Nip04::new(keys.clone(), peer_pubkey.to_string())
+        .nip04_encrypt_note_for(note, peer_pubkey, None)
+        .map_err(Nip46Error::EncryptionFailed)?;
+    keys.sign_note(note).map_err(Nip46Error::SigningFailed)
+}
+
+pub struct Nip46Bunker {
+    keypair: NostrKeypair,
+    approved_clients: HashSet<String>,
+    required_secret: Option<String>,
+}
+
+impl Nip46Bunker {
+    pub fn new(keypair: NostrKeypair) -> Self {
+        Nip46Bunker {
+            keypair,
+            approved_clients: HashSet::new(),
+            required_secret: None,
+        }
+    }
+    pub fn approve_client(&mut self, pubkey: &str) {
+        self.approved_clients.insert(pubkey.to_string());
+    }
+    pub fn is_approved(&self, pubkey: &str) -> bool {
+        self.approved_clients.contains(pubkey)
+    }
+    /// A connect request carrying this secret approves its client.
+    pub fn set_required_secret(&mut self, secret: &str) {
+        self.required_secret = Some(secret.to_string());
+    }
+    pub async fn handle_request(
+        &mut self,
+        signed_note: &NostrNote,
+    ) -> Result<NostrNote, Nip46Error> {
+        if !signed_note.verify() {
+            return Err(Nip46Error::AuthFailed(
+                "Invalid request signature".to_string(),
+            ));
+        }
+        let client = signed_note.pubkey.to_string();
+        let request = Nip46Request::decrypt_request(signed_note, &self.keypair)?;
+        let is_connect = request.method == "connect";
+        if is_connect && !self.is_approved(&client) {
+            let secret = request.params.get(1).filter(|secret| !secret.is_empty());
+            match (&self.required_secret, secret) {
+                (Some(required), Some(secret)) if required == secret => {
+                    self.approve_client(&client)
+                }
+                _ => {
+                    return Err(Nip46Error::AuthFailed(format!(
+                        "{} is not approved",
+                        client
+                    )))
+                }
             }
         }
+        if !self.is_approved(&client) {
+            return Err(Nip46Error::AuthFailed(format!(
+                "{} is not approved",
+                client
+            )));
+        }
+        let command = Nip46Request::command_from_request(client.clone(), request)?;
+        if let Nip46Commands::Disconnect(..) = command {
+            self.approved_clients.remove(&client);
+        }
+        Nip46Request::try_respond_to_command(&self.keypair, command)
     }
 }
 
@@ -348,7 +454,8 @@ mod tests {
             content: "test".to_string(),
             ..Default::default()
         };
-        let nip46_request = Nip46Request::sign_event_request(note_request, &client_keys);
+        let nip46_request = Nip46Request::try_sign_event_request(note_request, &client_keys)
+            .expect("Could not build request");
         assert_eq!(nip46_request.kind, 24133);
         assert_ne!(nip46_request.content, "test");
     }
@@ -357,7 +464,8 @@ mod tests {
     fn test_nip46_ping_request() {
         let user_keys = NostrKeypair::generate(false);
         let client_keys = NostrKeypair::generate(false);
-        let ping_request = Nip46Request::ping_request(&client_keys, user_keys.public_key());
+        let ping_request = Nip46Request::try_ping_request(&client_keys, user_keys.public_key())
+            .expect("Could not build request");
         assert_eq!(ping_request.kind, 24133);
 
         let nip46_command = Nip46Request::get_request_command(&ping_request, &user_keys);
@@ -366,8 +474,9 @@ mod tests {
         } else {
             panic!("Not a ping command");
         }
-        let signed_note = Nip46Request::respond_to_command(&user_keys, nip46_command.unwrap());
-        assert_eq!(signed_note.verify(), true);
+        let signed_note = Nip46Request::try_respond_to_command(&user_keys, nip46_command.unwrap())
+            .expect("Could not respond to command");
+        assert!(signed_note.verify());
         let decrypted_note = client_keys
             .decrypt_nip_04_content(&signed_note)
             .expect("Could not decrypt note");
@@ -391,7 +500,8 @@ mod tests {
             ..Default::default()
         };
         // and builds the request note
-        let nip46_request = Nip46Request::sign_event_request(note_request, &client_keys);
+        let nip46_request = Nip46Request::try_sign_event_request(note_request, &client_keys)
+            .expect("Could not build request");
 
         // users bunker receives the request note and parses the command
         let nip46_command = Nip46Request::get_request_command(&nip46_request, &user_keys);
@@ -403,11 +513,225 @@ mod tests {
         }
 
         // the user bunker signs the event and sends it back
-        let signed_note = Nip46Request::respond_to_command(&user_keys, nip46_command.unwrap());
-        assert_eq!(signed_note.verify(), true);
+        let signed_note = Nip46Request::try_respond_to_command(&user_keys, nip46_command.unwrap())
+            .expect("Could not respond to command");
+        assert!(signed_note.verify());
 
         // the client bunker receives the signed note and parses the response
-        let response_note = Nip46Response::get_response_note(&signed_note, &client_keys);
+        let response_note = Nip46Response::try_get_response_note(&signed_note, &client_keys)
+            .expect("Could not parse response");
         assert_eq!(response_note.content, "sing_me_please");
     }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_nip46_deprecated_wrappers() {
+        let client_keys = NostrKeypair::generate(false);
+        let user_keys = NostrKeypair::generate(false);
+        for request in [
+            Nip46Request::ping_request(&client_keys, user_keys.public_key()),
+            Nip46Request::get_public_key_request(&client_keys, user_keys.public_key()),
+        ] {
+            let command = Nip46Request::get_request_command(&request, &user_keys).unwrap();
+            assert!(Nip46Request::respond_to_command(&user_keys, command).verify());
+        }
+
+        let note_request = NostrNote {
+            pubkey: user_keys.public_key(),
+            kind: 1,
+            content: "wrapped".to_string(),
+            ..Default::default()
+        };
+        let request = Nip46Request::sign_event_request(note_request, &client_keys);
+        let command = Nip46Request::get_request_command(&request, &user_keys).unwrap();
+        let response = Nip46Request::respond_to_command(&user_keys, command);
+        let signed = Nip46Response::get_response_note(&response, &client_keys);
+        assert_eq!(signed.content, "wrapped");
+        assert!(signed.verify());
+    }
+
+    #[test]
+    fn test_nip46_unknown_command() {
+        let user_keys = NostrKeypair::generate(false);
+        let client_keys = NostrKeypair::generate(false);
+        let request = Nip46Request {
+            id: "nostro2-test".to_string(),
+            method: "sign_everything".to_string(),
+            params: vec![],
+        };
+        let request_note = request
+            .sign_request(&client_keys, user_keys.public_key())
+            .expect("Could not sign request");
+        match Nip46Request::get_request_command(&request_note, &user_keys) {
+            Err(Nip46Error::UnknownCommand(method)) => assert_eq!(method, "sign_everything"),
+            other => panic!("Expected UnknownCommand, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_nip46_wrong_recipient() {
+        let user_keys = NostrKeypair::generate(false);
+        let client_keys = NostrKeypair::generate(false);
+        let ping_request = Nip46Request::try_ping_request(&client_keys, user_keys.public_key())
+            .expect("Could not build request");
+        let eavesdropper_keys = NostrKeypair::generate(false);
+        let nip46_command = Nip46Request::get_request_command(&ping_request, &eavesdropper_keys);
+        assert!(matches!(
+            nip46_command,
+            Err(Nip46Error::DecryptionFailed(_)) | Err(Nip46Error::SerializationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_nip46_missing_params() {
+        let user_keys = NostrKeypair::generate(false);
+        let client_keys = NostrKeypair::generate(false);
+        let methods = [
+            ("sign_event", 0),
+            ("nip04_encrypt", 1),
+            ("nip04_decrypt", 1),
+            ("nip44_encrypt", 1),
+            ("nip44_decrypt", 1),
+        ];
+        for (method, params) in methods {
+            for len in 0..=params {
+                let params = vec![user_keys.public_key(); len];
+                let request_note = Nip46Request {
+                    id: "nostro2-test".to_string(),
+                    method: method.to_string(),
+                    params,
+                }
+                .sign_request(&client_keys, user_keys.public_key())
+                .expect("Could not sign request");
+                match Nip46Request::get_request_command(&request_note, &user_keys) {
+                    Err(Nip46Error::MissingParam {
+                        method: missing,
+                        index,
+                    }) => {
+                        assert_eq!(missing, method);
+                        assert_eq!(index, len);
+                    }
+                    other => panic!("Expected MissingParam for {}, got {:?}", method, other),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_nip46_typed_errors() {
+        let user_keys = NostrKeypair::generate(false);
+        let client_keys = NostrKeypair::generate(false);
+        let command = Nip46Commands::Nip04Decrypt(
+            client_keys.public_key(),
+            "nostro2-test".to_string(),
+            "not a nip04 payload".to_string(),
+            client_keys.public_key(),
+        );
+        match Nip46Request::try_respond_to_command(&user_keys, command) {
+            Err(error @ Nip46Error::DecryptionFailed(Nip04Error::DecryptionError(_))) => {
+                assert!(std::error::Error::source(&error).is_some())
+            }
+            other => panic!("Expected DecryptionFailed, got {:?}", other),
+        }
+        let command = Nip46Commands::Nip44Decrypt(
+            client_keys.public_key(),
+            "nostro2-test".to_string(),
+            "not a nip44 payload".to_string(),
+            client_keys.public_key(),
+        );
+        assert!(matches!(
+            Nip46Request::try_respond_to_command(&user_keys, command),
+            Err(Nip46Error::Nip44Failed(_))
+        ));
+        let ping = Nip46Request::try_ping_request(&client_keys, user_keys.public_key())
+            .expect("Could not build request");
+        assert!(matches!(
+            Nip46Response::try_get_response_note(&ping, &user_keys),
+            Err(Nip46Error::SerializationError(_))
+        ));
+    }
+
+    fn bunker_request(
+        client_keys: &NostrKeypair,
+        bunker: &str,
+        method: &str,
+        params: &[&str],
+    ) -> NostrNote {
+        let request = Nip46Request {
+            id: format!("nostro2-{}", method),
+            method: method.to_string(),
+            params: params.iter().map(|param| param.to_string()).collect(),
+        };
+        request
+            .sign_request(client_keys, bunker.to_string())
+            .expect("Could not sign request")
+    }
+
+    #[tokio::test]
+    async fn test_nip46_bunker_approval() {
+        let user_keys = NostrKeypair::generate(false);
+        let bunker_pubkey = user_keys.public_key();
+        let mut bunker = Nip46Bunker::new(user_keys);
+        let client_keys = NostrKeypair::generate(false);
+        let ping = bunker_request(&client_keys, &bunker_pubkey, "ping", &[]);
+        assert!(matches!(
+            bunker.handle_request(&ping).await,
+            Err(Nip46Error::AuthFailed(_))
+        ));
+
+        bunker.approve_client(&client_keys.public_key());
+        let response = bunker
+            .handle_request(&ping)
+            .await
+            .expect("Approved ping failed");
+        assert!(response.verify());
+        let decrypted = client_keys.decrypt_nip_04_content(&response).unwrap();
+        let parsed_response = serde_json::from_str::<Nip46Response>(&decrypted).unwrap();
+        assert_eq!(parsed_response.result, "pong");
+
+        let disconnect = bunker_request(&client_keys, &bunker_pubkey, "disconnect", &[]);
+        bunker
+            .handle_request(&disconnect)
+            .await
+            .expect("Disconnect failed");
+        assert!(!bunker.is_approved(&client_keys.public_key()));
+    }
+
+    #[tokio::test]
+    async fn test_nip46_bunker_secret() {
+        let user_keys = NostrKeypair::generate(false);
+        let bunker_pubkey = user_keys.public_key();
+        let mut bunker = Nip46Bunker::new(user_keys);
+        bunker.set_required_secret("open sesame");
+        let client_keys = NostrKeypair::generate(false);
+
+        let wrong_secret = bunker_request(
+            &client_keys,
+            &bunker_pubkey,
+            "connect",
+            &[&bunker_pubkey, "guess"],
+        );
+        assert!(matches!(
+            bunker.handle_request(&wrong_secret).await,
+            Err(Nip46Error::AuthFailed(_))
+        ));
+        let connect = bunker_request(
+            &client_keys,
+            &bunker_pubkey,
+            "connect",
+            &[&bunker_pubkey, "open sesame"],
+        );
+        bunker
+            .handle_request(&connect)
+            .await
+            .expect("Connect failed");
+        assert!(bunker.is_approved(&client_keys.public_key()));
+
+        let mut forged = bunker_request(&client_keys, &bunker_pubkey, "ping", &[]);
+        forged.content = "tampered".to_string();
+        assert!(matches!(
+            bunker.handle_request(&forged).await,
+            Err(Nip46Error::AuthFailed(_))
+        ));
+    }
 }
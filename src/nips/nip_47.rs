@@ -0,0 +1,501 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    keypair::NostrKeypair,
+    notes::{NostrNote, NostrTag, TagList},
+};
+
+const REQUEST_KIND: u32 = 23194;
+const RESPONSE_KIND: u32 = 23195;
+const URI_SCHEME: &str = "nostr+walletconnect";
+
+/// Connection errors come from the URI or the request itself, method errors are
+/// returned by the wallet, decryption errors mean the response could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip47Error {
+    Connection(String),
+    Method { code: String, message: String },
+    Decryption(String),
+    WrongKind(u32),
+    InvalidResponse(String),
+}
+impl std::fmt::Display for Nip47Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Nip47Error::Connection(e) => write!(f, "Wallet connection error: {}", e),
+            Nip47Error::Method { code, message } => {
+                write!(f, "Wallet returned {}: {}", code, message)
+            }
+            Nip47Error::Decryption(e) => write!(f, "Failed to decrypt wallet response: {}", e),
+            Nip47Error::WrongKind(kind) => {
+                write!(f, "Expected a kind {} note, found {}", RESPONSE_KIND, kind)
+            }
+            Nip47Error::InvalidResponse(e) => write!(f, "Invalid wallet response: {}", e),
+        }
+    }
+}
+impl std::error::Error for Nip47Error {}
+
+/// A `nostr+walletconnect://<wallet pubkey>?relay=...&secret=...` connection string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalletConnectUri {
+    pub wallet_pubkey: String,
+    pub relays: Vec<String>,
+    /// Hex private key the client signs requests with; not the user's identity.
+    pub secret: String,
+    pub lud16: Option<String>,
+}
+
+impl WalletConnectUri {
+    pub fn parse(uri: &str) -> Result<Self, Nip47Error> {
+        let url = url::Url::parse(uri).map_err(|e| Nip47Error::Connection(e.to_string()))?;
+        if url.scheme() != URI_SCHEME {
+            return Err(Nip47Error::Connection(format!(
+                "Expected a {} uri",
+                URI_SCHEME
+            )));
+        }
+        // Some wallets leave out the `//` before the pubkey.
+        let wallet_pubkey = url
+            .host_str()
+            .filter(|host| !host.is_empty())
+            .unwrap_or(url.path())
+            .to_string();
+        if wallet_pubkey.len() != 64 || !wallet_pubkey.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(Nip47Error::Connection(format!(
+                "Invalid wallet pubkey: {}",
+                wallet_pubkey
+            )));
+        }
+        let mut relays = vec![];
+        let mut secret = None;
+        let mut lud16 = None;
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "relay" => relays.push(value.into_owned()),
+                "secret" => secret = Some(value.into_owned()),
+                "lud16" => lud16 = Some(value.into_owned()),
+                _ => {}
+            }
+        }
+        if relays.is_empty() {
+            return Err(Nip47Error::Connection("Uri has no relay".to_string()));
+        }
+        let secret =
+            secret.ok_or_else(|| Nip47Error::Connection("Uri has no secret".to_string()))?;
+        Ok(WalletConnectUri {
+            wallet_pubkey,
+            relays,
+            secret,
+            lud16,
+        })
+    }
+    /// The keypair requests are signed and encrypted with.
+    pub fn keypair(&self) -> Result<NostrKeypair, Nip47Error> {
+        NostrKeypair::from_hex(&self.secret, false)
+            .map_err(|e| Nip47Error::Connection(e.to_string()))
+    }
+}
+
+/// Wallet commands, amounts are in millisatoshis.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WalletRequest {
+    PayInvoice {
+        invoice: String,
+        amount: Option<u64>,
+    },
+    GetBalance,
+    MakeInvoice {
+        amount: u64,
+        description: Option<String>,
+        expiry: Option<u64>,
+    },
+    /// Looks an invoice up by either its payment hash or the invoice itself.
+    LookupInvoice {
+        payment_hash: Option<String>,
+        invoice: Option<String>,
+    },
+}
+
+impl WalletRequest {
+    pub fn pay_invoice(invoice: &str) -> Self {
+        WalletRequest::PayInvoice {
+            invoice: invoice.to_string(),
+            amount: None,
+        }
+    }
+    pub fn get_balance() -> Self {
+        WalletRequest::GetBalance
+    }
+    pub fn make_invoice(amount: u64, description: Option<&str>) -> Self {
+        WalletRequest::MakeInvoice {
+            amount,
+            description: description.map(str::to_string),
+            expiry: None,
+        }
+    }
+    pub fn lookup_invoice(payment_hash: &str) -> Self {
+        WalletRequest::LookupInvoice {
+            payment_hash: Some(payment_hash.to_string()),
+            invoice: None,
+        }
+    }
+    pub fn method(&self) -> &'static str {
+        match self {
+            WalletRequest::PayInvoice { .. } => "pay_invoice",
+            WalletRequest::GetBalance => "get_balance",
+            WalletRequest::MakeInvoice { .. } => "make_invoice",
+            WalletRequest::LookupInvoice { .. } => "lookup_invoice",
+        }
+    }
+    fn params(&self) -> serde_json::Value {
+        let mut params = serde_json::Map::new();
+        let mut insert = |key: &str, value: Option<serde_json::Value>| {
+            if let Some(value) = value {
+                params.insert(key.to_string(), value);
+            }
+        };
+        match self {
+            WalletRequest::PayInvoice { invoice, amount } => {
+                insert("invoice", Some(invoice.as_str().into()));
+                insert("amount", amount.map(Into::into));
+            }
+            WalletRequest::GetBalance => {}
+            WalletRequest::MakeInvoice {
+                amount,
+                description,
+                expiry,
+            } => {
+                insert("amount", Some((*amount).into()));
+                insert("description", description.as_deref().map(Into::into));
+                insert("expiry", expiry.map(Into::into));
+            }
+            WalletRequest::LookupInvoice {
+                payment_hash,
+                invoice,
+            } => {
+                insert("payment_hash", payment_hash.as_deref().map(Into::into));
+                insert("invoice", invoice.as_deref().map(Into::into));
+            }
+        }
+        params.into()
+    }
+    /// Signed kind 23194 note for the wallet in `uri`, NIP-04 encrypted with its secret.
+    pub fn to_note(&self, uri: &WalletConnectUri) -> Result<NostrNote, Nip47Error> {
+        let keys = uri.keypair()?;
+        let mut note = NostrNote {
+            pubkey: keys.public_key(),
+            kind: REQUEST_KIND,
+            content: serde_json::json!({ "method": self.method(), "params": self.params() })
+                .to_string(),
+            ..Default::default()
+        };
+        keys.sign_nip_04_encrypted(&mut note, uri.wallet_pubkey.clone())
+            .map_err(|e| Nip47Error::Connection(e.to_string()))?;
+        Ok(note)
+    }
+}
+
+/// An invoice or payment, as `make_invoice` and `lookup_invoice` return it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WalletTransaction {
+    #[serde(rename = "type")]
+    pub transaction_type: Option<String>,
+    pub invoice: Option<String>,
+    pub description: Option<String>,
+    pub payment_hash: Option<String>,
+    pub preimage: Option<String>,
+    pub amount: Option<u64>,
+    pub fees_paid: Option<u64>,
+    pub created_at: Option<i64>,
+    pub expires_at: Option<i64>,
+    pub settled_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WalletResponsePayload {
+    PayInvoice {
+        preimage: String,
+    },
+    GetBalance {
+        balance: u64,
+    },
+    MakeInvoice(WalletTransaction),
+    LookupInvoice(WalletTransaction),
+    /// Results of methods without a typed payload.
+    Other {
+        result_type: String,
+        result: serde_json::Value,
+    },
+}
+
+#[derive(Deserialize)]
+struct RawResponse {
+    result_type: String,
+    #[serde(default)]
+    error: Option<RawError>,
+    #[serde(default)]
+    result: serde_json::Value,
+}
+#[derive(Deserialize)]
+struct RawError {
+    code: String,
+    #[serde(default)]
+    message: String,
+}
+
+pub struct WalletResponse;
+
+impl WalletResponse {
+    /// Decrypts a kind 23195 response with the client keypair `signer` and
+    /// returns its result, or the wallet's error as `Nip47Error::Method`.
+    pub fn from_note(
+        note: &NostrNote,
+        signer: &NostrKeypair,
+    ) -> Result<WalletResponsePayload, Nip47Error> {
+        if note.kind != RESPONSE_KIND {
+            return Err(Nip47Error::WrongKind(note.kind));
+        }
+        let plaintext = signer
+            .decrypt_nip_04_content(note)
+            .map_err(|e| Nip47Error::Decryption(e.to_string()))?;
+        if plaintext.is_empty() {
+            return Err(Nip47Error::Decryption("Empty plaintext".to_string()));
+        }
+        let response: RawResponse = serde_json::from_str(&plaintext)
+            .map_err(|e| Nip47Error::InvalidResponse(e.to_string()))?;
+        if let Some(error) = response.error {
+            return Err(Nip47Error::Method {
+                code: error.code,
+                message: error.message,
+            });
+        }
+        let invalid = |e: serde_json::Error| Nip47Error::InvalidResponse(e.to_string());
+        match response.result_type.as_str() {
+            "pay_invoice" => {
+                let preimage = response.result["preimage"]
+                    .as_str()
+                    .ok_or_else(|| Nip47Error::InvalidResponse("Missing preimage".to_string()))?;
+                Ok(WalletResponsePayload::PayInvoice {
+                    preimage: preimage.to_string(),
+                })
+            }
+            "get_balance" => {
+                let balance = response.result["balance"]
+                    .as_u64()
+                    .ok_or_else(|| Nip47Error::InvalidResponse("Missing balance".to_string()))?;
+                Ok(WalletResponsePayload::GetBalance { balance })
+            }
+            "make_invoice" => Ok(WalletResponsePayload::MakeInvoice(
+                serde_json::from_value(response.result).map_err(invalid)?,
+            )),
+            "lookup_invoice" => Ok(WalletResponsePayload::LookupInvoice(
+                serde_json::from_value(response.result).map_err(invalid)?,
+            )),
+            _ => Ok(WalletResponsePayload::Other {
+                result_type: response.result_type,
+                result: response.result,
+            }),
+        }
+    }
+    /// Signed kind 23195 note answering `request` with `content`, as a wallet sends it.
+    pub fn to_note(
+        request: &NostrNote,
+        content: &serde_json::Value,
+        wallet: &NostrKeypair,
+    ) -> Result<NostrNote, Nip47Error> {
+        let mut note = NostrNote {
+            pubkey: wallet.public_key(),
+            kind: RESPONSE_KIND,
+            content: content.to_string(),
+            ..Default::default()
+        };
+        note.tags.0.push(TagList {
+            tag_type: NostrTag::Event,
+            tags: vec![request.id.clone().unwrap_or_default()],
+        });
+        wallet
+            .sign_nip_04_encrypted(&mut note, request.pubkey.clone())
+            .map_err(|e| Nip47Error::Connection(e.to_string()))?;
+        Ok(note)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection() -> (NostrKeypair, WalletConnectUri) {
+        let wallet = NostrKeypair::generate(false);
+        let client = NostrKeypair::generate(true);
+        let uri = WalletConnectUri::parse(&format!(
+            "nostr+walletconnect://{}?relay=wss%3A%2F%2Frelay.damus.io&secret={}&lud16=alice%40example.com",
+            wallet.public_key(),
+            hex_secret(&client)
+        ))
+        .expect("Failed to parse uri");
+        (wallet, uri)
+    }
+
+    fn hex_secret(keys: &NostrKeypair) -> String {
+        keys.get_secret_key()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_uri() {
+        let pubkey = "b889ff5b1513b641e2a139f661a661364979c5beee91842f8f0ef42ab558e9d4";
+        let secret = "71a8c14c1407c113601079c4302dab36460f0ccd0ad506f1f2dc73b5100e4f3c";
+        let uri = WalletConnectUri::parse(&format!(
+            "nostr+walletconnect://{}?relay=wss%3A%2F%2Frelay.damus.io&secret={}",
+            pubkey, secret
+        ))
+        .expect("Failed to parse uri");
+        assert_eq!(uri.wallet_pubkey, pubkey);
+        assert_eq!(uri.relays, vec!["wss://relay.damus.io"]);
+        assert_eq!(uri.secret, secret);
+        assert_eq!(uri.lud16, None);
+        assert_eq!(
+            WalletConnectUri::parse(&format!(
+                "nostr+walletconnect:{}?relay=wss://relay.one&relay=wss://relay.two&secret={}&lud16=bob@example.com",
+                pubkey, secret
+            ))
+            .expect("Failed to parse uri"),
+            WalletConnectUri {
+                wallet_pubkey: pubkey.to_string(),
+                relays: vec!["wss://relay.one".to_string(), "wss://relay.two".to_string()],
+                secret: secret.to_string(),
+                lud16: Some("bob@example.com".to_string()),
+            }
+        );
+
+        for invalid in [
+            "not a uri".to_string(),
+            format!(
+                "nostrwalletconnect://{}?relay=wss://r&secret={}",
+                pubkey, secret
+            ),
+            format!("nostr+walletconnect://abc?relay=wss://r&secret={}", secret),
+            format!("nostr+walletconnect://{}?secret={}", pubkey, secret),
+            format!("nostr+walletconnect://{}?relay=wss://r", pubkey),
+        ] {
+            assert!(
+                matches!(
+                    WalletConnectUri::parse(&invalid),
+                    Err(Nip47Error::Connection(_))
+                ),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_requests() {
+        let (wallet, uri) = connection();
+        let client = uri.keypair().unwrap();
+        let cases = [
+            (
+                WalletRequest::pay_invoice("lnbc50n1..."),
+                serde_json::json!({"method": "pay_invoice", "params": {"invoice": "lnbc50n1..."}}),
+            ),
+            (
+                WalletRequest::get_balance(),
+                serde_json::json!({"method": "get_balance", "params": {}}),
+            ),
+            (
+                WalletRequest::make_invoice(21_000, Some("coffee")),
+                serde_json::json!({"method": "make_invoice", "params": {"amount": 21000, "description": "coffee"}}),
+            ),
+            (
+                WalletRequest::lookup_invoice("31afdf1"),
+                serde_json::json!({"method": "lookup_invoice", "params": {"payment_hash": "31afdf1"}}),
+            ),
+        ];
+        for (request, expected) in cases {
+            let note = request.to_note(&uri).expect("Failed to build request");
+            assert_eq!(note.kind, 23194);
+            assert_eq!(note.pubkey, client.public_key());
+            assert!(note.verify());
+            assert_eq!(
+                note.tags.find_first_tagged_pubkey(),
+                Some(wallet.public_key())
+            );
+            assert!(note.content.contains("?iv="));
+            let plaintext = wallet.decrypt_nip_04_content(&note).unwrap();
+            assert_eq!(
+                serde_json::from_str::<serde_json::Value>(&plaintext).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_responses() {
+        let (wallet, uri) = connection();
+        let client = uri.keypair().unwrap();
+        let request = WalletRequest::get_balance().to_note(&uri).unwrap();
+        let respond = |content: serde_json::Value| {
+            let note = WalletResponse::to_note(&request, &content, &wallet).unwrap();
+            WalletResponse::from_note(&note, &client)
+        };
+
+        assert_eq!(
+            respond(
+                serde_json::json!({"result_type": "get_balance", "result": {"balance": 10000}})
+            ),
+            Ok(WalletResponsePayload::GetBalance { balance: 10000 })
+        );
+        assert_eq!(
+            respond(
+                serde_json::json!({"result_type": "pay_invoice", "result": {"preimage": "0123456789abcdef"}})
+            ),
+            Ok(WalletResponsePayload::PayInvoice {
+                preimage: "0123456789abcdef".to_string()
+            })
+        );
+        let Ok(WalletResponsePayload::MakeInvoice(transaction)) = respond(serde_json::json!({
+            "result_type": "make_invoice",
+            "result": {"type": "incoming", "invoice": "lnbc210n1...", "amount": 21000, "created_at": 1700000000}
+        })) else {
+            panic!("Expected a make_invoice result");
+        };
+        assert_eq!(transaction.transaction_type.as_deref(), Some("incoming"));
+        assert_eq!(transaction.amount, Some(21000));
+        assert_eq!(transaction.settled_at, None);
+
+        assert_eq!(
+            respond(serde_json::json!({
+                "result_type": "pay_invoice",
+                "error": {"code": "INSUFFICIENT_BALANCE", "message": "Not enough sats"},
+                "result": null
+            })),
+            Err(Nip47Error::Method {
+                code: "INSUFFICIENT_BALANCE".to_string(),
+                message: "Not enough sats".to_string()
+            })
+        );
+        assert!(matches!(
+            respond(serde_json::json!({"result_type": "get_balance", "result": {}})),
+            Err(Nip47Error::InvalidResponse(_))
+        ));
+
+        let response = WalletResponse::to_note(
+            &request,
+            &serde_json::json!({"result_type": "get_balance", "result": {"balance": 1}}),
+            &wallet,
+        )
+        .unwrap();
+        assert!(matches!(
+            WalletResponse::from_note(&response, &NostrKeypair::generate(false)),
+            Err(Nip47Error::Decryption(_))
+        ));
+        assert_eq!(
+            WalletResponse::from_note(&request, &client),
+            Err(Nip47Error::WrongKind(23194))
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{
+    keypair::{NostrKeypair, SignerError},
+    notes::{NostrNote, NostrTag, TagList},
+};
+
+// Standard lists and sets from the NIP-51 tables, plus the deprecated 30001.
+const LIST_KINDS: [u32; 31] = [
+    3, 10000, 10001, 10002, 10003, 10004, 10005, 10006, 10007, 10009, 10012, 10015, 10020, 10030,
+    10050, 10101, 10102, 30000, 30001, 30002, 30003, 30004, 30005, 30007, 30015, 30030, 30063,
+    30267, 31924, 39089, 39092,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip51Error {
+    InvalidKind(u32),
+    MissingIdentifier,
+    Signing(SignerError),
+}
+impl std::fmt::Display for Nip51Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Nip51Error::InvalidKind(kind) => write!(f, "Kind {} is not a NIP-51 list", kind),
+            Nip51Error::MissingIdentifier => write!(f, "List sets need a d tag identifier"),
+            Nip51Error::Signing(e) => write!(f, "{}", e),
+        }
+    }
+}
+impl std::error::Error for Nip51Error {}
+
+/// A NIP-51 list note; only its public tags are read, not encrypted private items.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoteList {
+    note: NostrNote,
+}
+
+impl NoteList {
+    pub fn from_note(note: NostrNote) -> Result<Self, Nip51Error> {
+        if !LIST_KINDS.contains(&note.kind) {
+            return Err(Nip51Error::InvalidKind(note.kind));
+        }
+        Ok(NoteList { note })
+    }
+    pub fn note(&self) -> &NostrNote {
+        &self.note
+    }
+    pub fn pubkeys(&self) -> Vec<String> {
+        self.note.tags.find_all_tagged_pubkeys()
+    }
+    pub fn event_ids(&self) -> Vec<String> {
+        self.note.tags.find_all_tagged_events()
+    }
+    pub fn hashtags(&self) -> Vec<String> {
+        self.first_values("t")
+    }
+    pub fn relays(&self) -> Vec<String> {
+        self.first_values("r")
+    }
+    fn first_values(&self, tag_type: &str) -> Vec<String> {
+        self.note
+            .tags
+            .0
+            .iter()
+            .filter(|tag_list| tag_list.tag_type.as_ref() == tag_type)
+            .filter_map(|tag_list| tag_list.tags.first().cloned())
+            .collect()
+    }
+}
+
+/// Collects list items in order, one tag each, and signs them as a NIP-51 list.
+#[derive(Debug, Clone, Default)]
+pub struct NoteListBuilder {
+    identifier: Option<String>,
+    tags: Vec<TagList>,
+}
+
+impl NoteListBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// The `d` tag that sets (kinds 30000 and up) are addressed by.
+    pub fn identifier(mut self, identifier: &str) -> Self {
+        self.identifier = Some(identifier.to_string());
+        self
+    }
+    pub fn add_pubkey(self, pubkey: &str) -> Self {
+        self.push(NostrTag::Pubkey, pubkey)
+    }
+    pub fn add_event(self, event_id: &str) -> Self {
+        self.push(NostrTag::Event, event_id)
+    }
+    pub fn add_hashtag(self, hashtag: &str) -> Self {
+        self.push(NostrTag::Custom("t"), hashtag)
+    }
+    pub fn add_relay(self, relay_url: &str) -> Self {
+        self.push(NostrTag::Custom("r"), relay_url)
+    }
+    fn push(mut self, tag_type: NostrTag, value: &str) -> Self {
+        self.tags.push(TagList {
+            tag_type,
+            tags: vec![value.to_string()],
+        });
+        self
+    }
+    pub fn build(self, kind: u32, signer: &NostrKeypair) -> Result<NoteList, Nip51Error> {
+        if !LIST_KINDS.contains(&kind) {
+            return Err(Nip51Error::InvalidKind(kind));
+        }
+        let mut note = NostrNote {
+            pubkey: signer.public_key(),
+            kind,
+            ..Default::default()
+        };
+        if note.requires_d_tag() {
+            let identifier = self.identifier.ok_or(Nip51Error::MissingIdentifier)?;
+            note.tags.add_parameter_tag(&identifier);
+        }
+        note.tags.0.extend(self.tags);
+        signer.sign_note(&mut note).map_err(Nip51Error::Signing)?;
+        Ok(NoteList { note })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALICE: &str = "b0635d6a9851d3aed0cd6c495b282167acf761729078d975fc341b22650b07b9";
+    const BOB: &str = "6e468422dfb74a5738702a8823b9b28168abab8655faacb6853cd0ee15deee93";
+    const NOTE: &str = "4376c65d2f232afbe9b882a35baa4f6fe8667c4e684749af565f981833ed6a65";
+
+    #[test]
+    fn test_list_round_trip() {
+        let keys = NostrKeypair::generate(false);
+        let list = NoteListBuilder::new()
+            .add_pubkey(ALICE)
+            .add_pubkey(BOB)
+            .add_event(NOTE)
+            .add_hashtag("nostr")
+            .add_relay("wss://relay.example.com")
+            .build(10000, &keys)
+            .expect("Failed to build mute list");
+        assert!(list.note().verify());
+        assert_eq!(
+            serde_json::to_value(&list.note().tags).unwrap(),
+            serde_json::json!([
+                ["p", ALICE],
+                ["p", BOB],
+                ["e", NOTE],
+                ["t", "nostr"],
+                ["r", "wss://relay.example.com"]
+            ])
+        );
+
+        let note: NostrNote = serde_json::from_str(&list.note().to_string()).unwrap();
+        let parsed = NoteList::from_note(note).expect("Failed to parse list");
+        assert_eq!(parsed.pubkeys(), vec![ALICE, BOB]);
+        assert_eq!(parsed.event_ids(), vec![NOTE]);
+        assert_eq!(parsed.hashtags(), vec!["nostr"]);
+        assert_eq!(parsed.relays(), vec!["wss://relay.example.com"]);
+        assert_eq!(parsed, list);
+    }
+
+    #[test]
+    fn test_list_sets() {
+        let keys = NostrKeypair::generate(false);
+        assert_eq!(
+            NoteListBuilder::new().add_pubkey(ALICE).build(30000, &keys),
+            Err(Nip51Error::MissingIdentifier)
+        );
+        let set = NoteListBuilder::new()
+            .identifier("friends")
+            .add_pubkey(ALICE)
+            .build(30000, &keys)
+            .expect("Failed to build follow set");
+        assert_eq!(
+            set.note().tags.find_first_parameter().as_deref(),
+            Some("friends")
+        );
+        assert_eq!(set.pubkeys(), vec![ALICE]);
+    }
+
+    #[test]
+    fn test_list_kinds() {
+        let keys = NostrKeypair::generate(false);
+        for kind in [1, 9999, 10008, 20000, 30023] {
+            assert_eq!(
+                NoteListBuilder::new().build(kind, &keys),
+                Err(Nip51Error::InvalidKind(kind))
+            );
+        }
+        assert_eq!(
+            NoteList::from_note(NostrNote::default()),
+            Err(Nip51Error::InvalidKind(1))
+        );
+        assert!(NoteListBuilder::new().build(10001, &keys).is_ok());
+    }
+}
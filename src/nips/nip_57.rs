@@ -0,0 +1,359 @@
+use bech32::{primitives::decode::CheckedHrpstring, Bech32, Fe32, Fe32IterExt};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    keypair::{NostrKeypair, SignerError},
+    notes::{NostrNote, NostrTag},
+};
+
+const ZAP_REQUEST_KIND: u32 = 9734;
+const ZAP_RECEIPT_KIND: u32 = 9735;
+// BOLT-11 layout in 5-bit words: a 35-bit timestamp up front, a 520-bit signature at
+// the end, and tagged fields in between, `h` holding the SHA-256 description hash.
+const TIMESTAMP_WORDS: usize = 7;
+const SIGNATURE_WORDS: usize = 104;
+const DESCRIPTION_HASH_FIELD: u8 = 23;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip57Error {
+    WrongKind { expected: u32, found: u32 },
+    MissingTag(String),
+    InvalidPubkey(String),
+    InvalidDescription(String),
+    Signing(SignerError),
+}
+impl std::fmt::Display for Nip57Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Nip57Error::WrongKind { expected, found } => {
+                write!(f, "Expected a kind {} note, found {}", expected, found)
+            }
+            Nip57Error::MissingTag(tag) => write!(f, "Missing {} tag", tag),
+            Nip57Error::InvalidPubkey(pubkey) => {
+                write!(f, "Expected 64 hex characters: {}", pubkey)
+            }
+            Nip57Error::InvalidDescription(e) => write!(f, "Invalid zap request: {}", e),
+            Nip57Error::Signing(e) => write!(f, "{}", e),
+        }
+    }
+}
+impl std::error::Error for Nip57Error {}
+
+/// Chained construction of a signed kind 9734 zap request for `recipient`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZapRequest {
+    recipient: String,
+    amount_msats: u64,
+    relays: Vec<String>,
+    event: Option<String>,
+    lnurl: Option<String>,
+    content: String,
+}
+impl ZapRequest {
+    pub fn new(recipient: &str, amount_msats: u64) -> Self {
+        ZapRequest {
+            recipient: recipient.to_string(),
+            amount_msats,
+            relays: vec![],
+            event: None,
+            lnurl: None,
+            content: String::new(),
+        }
+    }
+    /// Relays the recipient's wallet publishes the receipt to.
+    pub fn relays(mut self, relays: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.relays
+            .extend(relays.into_iter().map(|relay| relay.as_ref().to_string()));
+        self
+    }
+    /// Zaps a note rather than only its author.
+    pub fn event(mut self, event_id: &str) -> Self {
+        self.event = Some(event_id.to_string());
+        self
+    }
+    pub fn lnurl(mut self, lnurl: &str) -> Self {
+        self.lnurl = Some(lnurl.to_string());
+        self
+    }
+    pub fn content(mut self, content: &str) -> Self {
+        self.content = content.to_string();
+        self
+    }
+    pub fn build(self, signer: &NostrKeypair) -> Result<NostrNote, Nip57Error> {
+        if !NostrNote::is_hex(&self.recipient, 64) {
+            return Err(Nip57Error::InvalidPubkey(self.recipient));
+        }
+        if self.relays.is_empty() {
+            return Err(Nip57Error::MissingTag("relays".to_string()));
+        }
+        let mut note = NostrNote {
+            pubkey: signer.public_key(),
+            kind: ZAP_REQUEST_KIND,
+            content: self.content,
+            ..Default::default()
+        };
+        for relay in &self.relays {
+            note.tags.add_custom_tag(NostrTag::Custom("relays"), relay);
+        }
+        note.tags.add_amount_tag(self.amount_msats);
+        if let Some(lnurl) = &self.lnurl {
+            note.tags
+                .add_lnurl_tag(lnurl)
+                .map_err(|_| Nip57Error::MissingTag("lnurl".to_string()))?;
+        }
+        note.tags.add_pubkey_tag(&self.recipient);
+        if let Some(event) = &self.event {
+            note.tags.add_event_tag(event);
+        }
+        signer.sign_note(&mut note).map_err(Nip57Error::Signing)?;
+        Ok(note)
+    }
+}
+
+/// A kind 9735 zap receipt and the zap request carried in its `description` tag.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZapReceipt {
+    pub note: NostrNote,
+    pub bolt11: String,
+    pub description: String,
+    pub zap_request: NostrNote,
+    pub preimage: Option<String>,
+}
+impl ZapReceipt {
+    /// Checks the receipt carries an invoice and a signed zap request for the same
+    /// recipient; the invoice itself is checked by `verify_description_hash`.
+    pub fn from_note(note: &NostrNote) -> Result<Self, Nip57Error> {
+        if note.kind != ZAP_RECEIPT_KIND {
+            return Err(Nip57Error::WrongKind {
+                expected: ZAP_RECEIPT_KIND,
+                found: note.kind,
+            });
+        }
+        let first_tag = |tag: &'static str| note.tags.find_tags_ref(tag).next().map(str::to_string);
+        let bolt11 = note
+            .tags
+            .bolt11()
+            .ok_or_else(|| Nip57Error::MissingTag("bolt11".to_string()))?
+            .to_string();
+        let description = first_tag("description")
+            .ok_or_else(|| Nip57Error::MissingTag("description".to_string()))?;
+        let zap_request: NostrNote = serde_json::from_str(&description)
+            .map_err(|e| Nip57Error::InvalidDescription(e.to_string()))?;
+        if zap_request.kind != ZAP_REQUEST_KIND {
+            return Err(Nip57Error::InvalidDescription(format!(
+                "kind {}",
+                zap_request.kind
+            )));
+        }
+        if !zap_request.verify() {
+            return Err(Nip57Error::InvalidDescription(
+                "signature does not verify".to_string(),
+            ));
+        }
+        if zap_request.tags.find_first_tagged_pubkey() != note.tags.find_first_tagged_pubkey() {
+            return Err(Nip57Error::InvalidDescription(
+                "recipient does not match the receipt".to_string(),
+            ));
+        }
+        Ok(ZapReceipt {
+            note: note.clone(),
+            bolt11,
+            description,
+            zap_request,
+            preimage: first_tag("preimage"),
+        })
+    }
+    /// Zap amount the sender asked for, in millisats.
+    pub fn amount_msats(&self) -> Option<u64> {
+        self.zap_request.tags.amount_msats()
+    }
+    /// Whether the invoice commits to `description`, i.e. its `h` field is the
+    /// SHA-256 of it. Pass `self.description` to check the receipt as received.
+    pub fn verify_description_hash(&self, description: &str) -> bool {
+        let hash = Sha256::digest(description.as_bytes());
+        Self::invoice_description_hash(&self.bolt11).is_some_and(|h| h[..] == hash[..])
+    }
+    fn invoice_description_hash(bolt11: &str) -> Option<Vec<u8>> {
+        let invoice = CheckedHrpstring::new::<Bech32>(bolt11).ok()?;
+        let words = invoice
+            .data_part_ascii_no_checksum()
+            .iter()
+            .map(|c| Fe32::from_char(*c as char))
+            .collect::<Result<Vec<Fe32>, _>>()
+            .ok()?;
+        let fields_end = words.len().checked_sub(SIGNATURE_WORDS)?;
+        let mut index = TIMESTAMP_WORDS;
+        while index + 3 <= fields_end {
+            let field = words[index].to_u8();
+            let length = words[index + 1].to_u8() as usize * 32 + words[index + 2].to_u8() as usize;
+            let data = words.get(index + 3..index + 3 + length)?;
+            if field == DESCRIPTION_HASH_FIELD && length == 52 {
+                return Some(data.iter().copied().fes_to_bytes().collect());
+            }
+            index += 3 + length;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bech32::{ByteIterExt, Hrp};
+
+    const RECIPIENT: &str = "32e1827635450ebb3c5a7d12c1f8e7b2b514439ac10a67eef3d9fd9c5c68e245";
+    const ZAPPED_EVENT: &str = "3624762a1274dd9636e0c552b53086d70bc88c165bc4dc0f9e836a1eaf86c3b8";
+
+    // A structurally valid invoice: timestamp, an `h` field over `description`,
+    // and a blank signature.
+    fn invoice(description: &str) -> String {
+        let hrp = Hrp::parse("lnbc10u").unwrap();
+        let hash = Sha256::digest(description.as_bytes());
+        let words = [1u8; TIMESTAMP_WORDS]
+            .into_iter()
+            .chain([DESCRIPTION_HASH_FIELD, 1, 20])
+            .map(|word| Fe32::try_from(word).unwrap())
+            .chain(hash.iter().copied().bytes_to_fes())
+            .chain([Fe32::Q; SIGNATURE_WORDS])
+            .collect::<Vec<_>>();
+        words
+            .into_iter()
+            .with_checksum::<Bech32>(&hrp)
+            .chars()
+            .collect()
+    }
+
+    // Mirrors the receipt from the NIP-57 appendix, signed by a test wallet key.
+    fn receipt_note(zap_request: &NostrNote, bolt11: &str) -> NostrNote {
+        let wallet = NostrKeypair::generate(false);
+        let description = zap_request.to_string();
+        let mut note = NostrNote {
+            pubkey: wallet.public_key(),
+            kind: 9735,
+            created_at: zap_request.created_at,
+            ..Default::default()
+        };
+        note.tags.add_pubkey_tag(RECIPIENT);
+        note.tags
+            .add_custom_tag(NostrTag::Custom("P"), &zap_request.pubkey);
+        note.tags.add_event_tag(ZAPPED_EVENT);
+        note.tags.add_bolt11_tag(bolt11);
+        note.tags
+            .add_custom_tag(NostrTag::Custom("description"), &description);
+        note.tags.add_custom_tag(
+            NostrTag::Custom("preimage"),
+            "5d006d2cf1e73c7148e7519a4c68adc81642ce0e25a432b2434c99f97344c15f",
+        );
+        wallet.sign_note(&mut note).expect("Failed to sign note");
+        note
+    }
+
+    fn zap_request(sender: &NostrKeypair) -> NostrNote {
+        ZapRequest::new(RECIPIENT, 21_000)
+            .relays(["wss://relayable.org", "wss://relay.damus.io"])
+            .event(ZAPPED_EVENT)
+            .lnurl("lnurl1dp68gurn8ghj7um5v93kketj9ehx2amn9uh8wetvdskkkmn0wahz7mrww4excup")
+            .content("Zap!")
+            .build(sender)
+            .expect("Failed to build zap request")
+    }
+
+    #[test]
+    fn test_zap_request() {
+        let sender = NostrKeypair::generate(false);
+        let request = zap_request(&sender);
+        assert_eq!(request.kind, 9734);
+        assert_eq!(request.content, "Zap!");
+        assert!(request.verify());
+        let tags = serde_json::to_value(&request.tags).unwrap();
+        assert_eq!(
+            tags[0],
+            serde_json::json!(["relays", "wss://relayable.org", "wss://relay.damus.io"])
+        );
+        assert_eq!(tags[1], serde_json::json!(["amount", "21000"]));
+        assert_eq!(tags[2][0], "lnurl");
+        assert_eq!(tags[3], serde_json::json!(["p", RECIPIENT]));
+        assert_eq!(tags[4], serde_json::json!(["e", ZAPPED_EVENT]));
+
+        assert_eq!(
+            ZapRequest::new("npub", 1)
+                .relays(["wss://relay.example.com"])
+                .build(&sender),
+            Err(Nip57Error::InvalidPubkey("npub".to_string()))
+        );
+        assert_eq!(
+            ZapRequest::new(RECIPIENT, 1).build(&sender),
+            Err(Nip57Error::MissingTag("relays".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_zap_receipt() {
+        let sender = NostrKeypair::generate(false);
+        let request = zap_request(&sender);
+        let description = request.to_string();
+        let note = receipt_note(&request, &invoice(&description));
+        let receipt = ZapReceipt::from_note(&note).expect("Failed to parse receipt");
+        assert_eq!(receipt.zap_request, request);
+        assert_eq!(receipt.description, description);
+        assert_eq!(receipt.amount_msats(), Some(21_000));
+        assert_eq!(
+            receipt.preimage.as_deref(),
+            Some("5d006d2cf1e73c7148e7519a4c68adc81642ce0e25a432b2434c99f97344c15f")
+        );
+        assert!(receipt.verify_description_hash(&receipt.description));
+        assert!(!receipt.verify_description_hash("a different zap request"));
+
+        // An invoice committing to another description fails the check.
+        let other_invoice = receipt_note(&request, &invoice("something else"));
+        let other = ZapReceipt::from_note(&other_invoice).expect("Failed to parse receipt");
+        assert!(!other.verify_description_hash(&other.description));
+        let garbage = receipt_note(&request, "lnbc10u1notaninvoice");
+        let garbage = ZapReceipt::from_note(&garbage).expect("Failed to parse receipt");
+        assert!(!garbage.verify_description_hash(&garbage.description));
+    }
+
+    #[test]
+    fn test_zap_receipt_errors() {
+        let sender = NostrKeypair::generate(false);
+        let request = zap_request(&sender);
+        let bolt11 = invoice(&request.to_string());
+
+        assert_eq!(
+            ZapReceipt::from_note(&request),
+            Err(Nip57Error::WrongKind {
+                expected: 9735,
+                found: 9734
+            })
+        );
+        let mut missing_bolt11 = receipt_note(&request, &bolt11);
+        missing_bolt11
+            .tags
+            .0
+            .retain(|tag_list| tag_list.tag_type != NostrTag::Custom("bolt11"));
+        assert_eq!(
+            ZapReceipt::from_note(&missing_bolt11),
+            Err(Nip57Error::MissingTag("bolt11".to_string()))
+        );
+
+        let mut tampered = request.clone();
+        tampered.content = "Zap?".to_string();
+        assert!(matches!(
+            ZapReceipt::from_note(&receipt_note(&tampered, &bolt11)),
+            Err(Nip57Error::InvalidDescription(_))
+        ));
+        let not_a_request = NostrNote::default();
+        assert!(matches!(
+            ZapReceipt::from_note(&receipt_note(&not_a_request, &bolt11)),
+            Err(Nip57Error::InvalidDescription(_))
+        ));
+        let elsewhere = ZapRequest::new(&"a".repeat(64), 21_000)
+            .relays(["wss://relay.example.com"])
+            .build(&sender)
+            .unwrap();
+        assert!(matches!(
+            ZapReceipt::from_note(&receipt_note(&elsewhere, &bolt11)),
+            Err(Nip57Error::InvalidDescription(_))
+        ));
+    }
+}
@@ -0,0 +1,232 @@
+use crate::{
+    keypair::{NostrKeypair, SignerError},
+    notes::{NostrNote, NostrTag, TagList},
+    relays::Url,
+};
+
+const RELAY_LIST_KIND: u32 = 10002;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip65Error {
+    WrongKind(u32),
+    MissingUrl,
+    InvalidUrl(String),
+    InvalidMarker(String),
+    Signing(SignerError),
+}
+impl std::fmt::Display for Nip65Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Nip65Error::WrongKind(kind) => {
+                write!(
+                    f,
+                    "Expected a kind {} note, found {}",
+                    RELAY_LIST_KIND, kind
+                )
+            }
+            Nip65Error::MissingUrl => write!(f, "Relay tag has no url"),
+            Nip65Error::InvalidUrl(e) => write!(f, "{}", e),
+            Nip65Error::InvalidMarker(marker) => write!(f, "Unknown relay marker: {}", marker),
+            Nip65Error::Signing(e) => write!(f, "{}", e),
+        }
+    }
+}
+impl std::error::Error for Nip65Error {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayMarker {
+    Read,
+    Write,
+}
+impl AsRef<str> for RelayMarker {
+    fn as_ref(&self) -> &str {
+        match self {
+            RelayMarker::Read => "read",
+            RelayMarker::Write => "write",
+        }
+    }
+}
+impl std::str::FromStr for RelayMarker {
+    type Err = Nip65Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(RelayMarker::Read),
+            "write" => Ok(RelayMarker::Write),
+            _ => Err(Nip65Error::InvalidMarker(s.to_string())),
+        }
+    }
+}
+
+/// One `r` tag; a relay without a marker is used for both reading and writing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelayEntry {
+    pub url: String,
+    pub marker: Option<RelayMarker>,
+}
+
+/// The kind 10002 list of relays a user reads from and writes to.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RelayListMetadata {
+    pub relays: Vec<RelayEntry>,
+}
+
+impl RelayListMetadata {
+    pub fn from_note(note: &NostrNote) -> Result<Self, Nip65Error> {
+        if note.kind != RELAY_LIST_KIND {
+            return Err(Nip65Error::WrongKind(note.kind));
+        }
+        let relays = note
+            .tags
+            .0
+            .iter()
+            .filter(|tag_list| tag_list.tag_type.as_ref() == "r")
+            .map(|tag_list| {
+                let url = tag_list.tags.first().ok_or(Nip65Error::MissingUrl)?;
+                Url::new(url).map_err(|e| Nip65Error::InvalidUrl(e.to_string()))?;
+                let marker = tag_list
+                    .tags
+                    .get(1)
+                    .map(|marker| marker.parse())
+                    .transpose()?;
+                Ok(RelayEntry {
+                    url: url.to_string(),
+                    marker,
+                })
+            })
+            .collect::<Result<Vec<_>, Nip65Error>>()?;
+        Ok(RelayListMetadata { relays })
+    }
+    /// Signed kind 10002 note with one `r` tag per entry.
+    pub fn to_note(&self, signer: &NostrKeypair) -> Result<NostrNote, Nip65Error> {
+        let mut note = NostrNote {
+            pubkey: signer.public_key(),
+            kind: RELAY_LIST_KIND,
+            ..Default::default()
+        };
+        for entry in &self.relays {
+            Url::new(&entry.url).map_err(|e| Nip65Error::InvalidUrl(e.to_string()))?;
+            let mut tags = vec![entry.url.clone()];
+            tags.extend(entry.marker.map(|marker| marker.as_ref().to_string()));
+            note.tags.0.push(TagList {
+                tag_type: NostrTag::Custom("r"),
+                tags,
+            });
+        }
+        signer.sign_note(&mut note).map_err(Nip65Error::Signing)?;
+        Ok(note)
+    }
+    /// Relays to fetch the user's notes from: their `write` relays.
+    pub fn write_relays(&self) -> Vec<&str> {
+        self.relays_except(RelayMarker::Read)
+    }
+    /// Relays to send notes mentioning the user to: their `read` relays.
+    pub fn read_relays(&self) -> Vec<&str> {
+        self.relays_except(RelayMarker::Write)
+    }
+    fn relays_except(&self, excluded: RelayMarker) -> Vec<&str> {
+        self.relays
+            .iter()
+            .filter(|entry| entry.marker != Some(excluded))
+            .map(|entry| entry.url.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relay_list_round_trip() {
+        let keys = NostrKeypair::generate(false);
+        let list = RelayListMetadata {
+            relays: vec![
+                RelayEntry {
+                    url: "wss://alicerelay.example.com".to_string(),
+                    marker: None,
+                },
+                RelayEntry {
+                    url: "wss://expensive-relay.example2.com".to_string(),
+                    marker: Some(RelayMarker::Write),
+                },
+                RelayEntry {
+                    url: "wss://nostr-relay.example.com".to_string(),
+                    marker: Some(RelayMarker::Read),
+                },
+            ],
+        };
+        let note = list.to_note(&keys).expect("Failed to build relay list");
+        assert_eq!(note.kind, 10002);
+        assert!(note.verify());
+        assert_eq!(
+            serde_json::to_value(&note.tags).unwrap(),
+            serde_json::json!([
+                ["r", "wss://alicerelay.example.com"],
+                ["r", "wss://expensive-relay.example2.com", "write"],
+                ["r", "wss://nostr-relay.example.com", "read"]
+            ])
+        );
+        let parsed = RelayListMetadata::from_note(&note).expect("Failed to parse relay list");
+        assert_eq!(parsed, list);
+        assert_eq!(
+            parsed.write_relays(),
+            vec![
+                "wss://alicerelay.example.com",
+                "wss://expensive-relay.example2.com"
+            ]
+        );
+        assert_eq!(
+            parsed.read_relays(),
+            vec![
+                "wss://alicerelay.example.com",
+                "wss://nostr-relay.example.com"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_relay_list_errors() {
+        let note_with_tags = |tags: serde_json::Value| NostrNote {
+            kind: 10002,
+            tags: serde_json::from_value(tags).unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(
+            RelayListMetadata::from_note(&NostrNote::default()),
+            Err(Nip65Error::WrongKind(1))
+        );
+        assert_eq!(
+            RelayListMetadata::from_note(&note_with_tags(serde_json::json!([["r"]]))),
+            Err(Nip65Error::MissingUrl)
+        );
+        assert!(matches!(
+            RelayListMetadata::from_note(&note_with_tags(serde_json::json!([[
+                "r",
+                "https://relay.example.com"
+            ]]))),
+            Err(Nip65Error::InvalidUrl(_))
+        ));
+        assert_eq!(
+            RelayListMetadata::from_note(&note_with_tags(serde_json::json!([[
+                "r",
+                "wss://relay.example.com",
+                "both"
+            ]]))),
+            Err(Nip65Error::InvalidMarker("both".to_string()))
+        );
+        let with_other_tags = note_with_tags(serde_json::json!([
+            [
+                "p",
+                "b0635d6a9851d3aed0cd6c495b282167acf761729078d975fc341b22650b07b9"
+            ],
+            ["r", "wss://relay.example.com"]
+        ]));
+        assert_eq!(
+            RelayListMetadata::from_note(&with_other_tags)
+                .unwrap()
+                .relays
+                .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use std::marker::PhantomData;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    keypair::{NostrKeypair, SignerError},
+    nips::NostrAddress,
+    notes::NostrNote,
+    relays::NostrSubscription,
+};
+
+const APP_DATA_KIND: u32 = 30078;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip78Error {
+    WrongKind(u32),
+    MissingKey,
+    InvalidContent(String),
+    Signing(SignerError),
+}
+impl std::fmt::Display for Nip78Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Nip78Error::WrongKind(kind) => {
+                write!(f, "Expected a kind {} note, found {}", APP_DATA_KIND, kind)
+            }
+            Nip78Error::MissingKey => write!(f, "App data has no d tag"),
+            Nip78Error::InvalidContent(e) => write!(f, "Invalid app data: {}", e),
+            Nip78Error::Signing(e) => write!(f, "{}", e),
+        }
+    }
+}
+impl std::error::Error for Nip78Error {}
+
+/// A kind 30078 note storing an application's `T` as JSON under the `d` tag key.
+/// Publishing the same key again replaces the stored value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppData<T> {
+    note: NostrNote,
+    value: PhantomData<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> AppData<T> {
+    /// Fails unless `note` is a kind 30078 note with a `d` tag.
+    pub fn from_note(note: NostrNote) -> Result<Self, Nip78Error> {
+        if note.kind != APP_DATA_KIND {
+            return Err(Nip78Error::WrongKind(note.kind));
+        }
+        if note.tags.first_tag_value("d").is_none() {
+            return Err(Nip78Error::MissingKey);
+        }
+        Ok(AppData {
+            note,
+            value: PhantomData,
+        })
+    }
+    pub fn key(&self) -> &str {
+        self.note.tags.first_tag_value("d").unwrap_or_default()
+    }
+    pub fn value(&self) -> Result<T, Nip78Error> {
+        Self::load(&self.note)
+    }
+    pub fn note(&self) -> &NostrNote {
+        &self.note
+    }
+    /// Signs `value` as the JSON content of a kind 30078 note under `key`.
+    pub fn store(key: &str, value: &T, signer: &NostrKeypair) -> Result<NostrNote, Nip78Error> {
+        if key.is_empty() {
+            return Err(Nip78Error::MissingKey);
+        }
+        let mut note = NostrNote {
+            kind: APP_DATA_KIND,
+            pubkey: signer.public_key(),
+            content: serde_json::to_string(value)
+                .map_err(|e| Nip78Error::InvalidContent(e.to_string()))?,
+            ..Default::default()
+        };
+        note.tags.add_parameter_tag(key);
+        signer.sign_note(&mut note).map_err(Nip78Error::Signing)?;
+        Ok(note)
+    }
+    pub fn load(note: &NostrNote) -> Result<T, Nip78Error> {
+        if note.kind != APP_DATA_KIND {
+            return Err(Nip78Error::WrongKind(note.kind));
+        }
+        serde_json::from_str(&note.content).map_err(|e| Nip78Error::InvalidContent(e.to_string()))
+    }
+    /// Matches the value `pubkey` stored under `key`.
+    pub fn subscription_filter(pubkey: &str, key: &str) -> NostrSubscription {
+        NostrSubscription::for_address(&NostrAddress {
+            identifier: key.to_string(),
+            pubkey: pubkey.to_string(),
+            kind: APP_DATA_KIND,
+            relays: vec![],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Settings {
+        theme: String,
+        font_size: u8,
+        relays: Vec<String>,
+    }
+
+    fn settings() -> Settings {
+        Settings {
+            theme: "dark".to_string(),
+            font_size: 14,
+            relays: vec!["wss://relay.example.com".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_app_data_round_trip() {
+        let keys = NostrKeypair::generate(false);
+        let note = AppData::store("com.example.settings", &settings(), &keys)
+            .expect("Failed to store settings");
+        assert_eq!(note.kind, 30078);
+        assert!(note.verify());
+        assert_eq!(
+            serde_json::to_value(&note.tags).unwrap(),
+            serde_json::json!([["d", "com.example.settings"]])
+        );
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&note.content).unwrap(),
+            serde_json::json!({
+                "theme": "dark",
+                "font_size": 14,
+                "relays": ["wss://relay.example.com"]
+            })
+        );
+        assert_eq!(AppData::<Settings>::load(&note), Ok(settings()));
+
+        let data = AppData::<Settings>::from_note(note).unwrap();
+        assert_eq!(data.key(), "com.example.settings");
+        assert_eq!(data.value(), Ok(settings()));
+    }
+
+    #[test]
+    fn test_app_data_errors() {
+        let keys = NostrKeypair::generate(false);
+        assert_eq!(
+            AppData::store("", &settings(), &keys),
+            Err(Nip78Error::MissingKey)
+        );
+        assert_eq!(
+            AppData::<Settings>::load(&NostrNote::default()),
+            Err(Nip78Error::WrongKind(1))
+        );
+
+        let mut note = AppData::store("com.example.settings", &settings(), &keys).unwrap();
+        note.content = r#"{"theme":"dark"}"#.to_string();
+        assert!(matches!(
+            AppData::<Settings>::load(&note),
+            Err(Nip78Error::InvalidContent(_))
+        ));
+        note.tags.remove_all_of_type("d");
+        assert_eq!(
+            AppData::<Settings>::from_note(note),
+            Err(Nip78Error::MissingKey)
+        );
+    }
+
+    #[test]
+    fn test_app_data_subscription_filter() {
+        let keys = NostrKeypair::generate(false);
+        let filter =
+            AppData::<Settings>::subscription_filter(&keys.public_key(), "com.example.settings");
+        assert_eq!(
+            serde_json::to_value(&filter).unwrap(),
+            serde_json::json!({
+                "authors": [keys.public_key()],
+                "kinds": [30078],
+                "#d": ["com.example.settings"]
+            })
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use crate::notes::{NostrNote, NostrTag, TagList};
+
+pub struct Nip82;
+
+impl Nip82 {
+    pub fn issue_badge(
+        name: &str,
+        description: &str,
+        image_url: Option<&str>,
+        thumb_url: Option<&str>,
+    ) -> NostrNote {
+        let mut badge = NostrNote {
+            kind: 30009,
+            ..Default::default()
+        };
+        badge.tags.add_parameter_tag(name);
+        badge.tags.add_custom_tag(NostrTag::Custom("name"), name);
+        badge
+            .tags
+            .add_custom_tag(NostrTag::Custom("description"), description);
+        if let Some(image_url) = image_url {
+            badge
+                .tags
+                .add_custom_tag(NostrTag::Custom("image"), image_url);
+        }
+        if let Some(thumb_url) = thumb_url {
+            badge
+                .tags
+                .add_custom_tag(NostrTag::Custom("thumb"), thumb_url);
+        }
+        badge
+    }
+    pub fn badge_address(badge_def: &NostrNote) -> String {
+        format!(
+            "{}:{}:{}",
+            badge_def.kind,
+            badge_def.pubkey,
+            badge_def.tags.find_first_parameter().unwrap_or_default()
+        )
+    }
+    pub fn award_badge(badge_def: &NostrNote, recipients: &[&str]) -> NostrNote {
+        let mut award = NostrNote {
+            pubkey: badge_def.pubkey.clone(),
+            kind: 8,
+            ..Default::default()
+        };
+        award
+            .tags
+            .add_custom_tag(NostrTag::Custom("a"), &Self::badge_address(badge_def));
+        // One "p" tag per awardee, instead of the grouped list add_pubkey_tag builds.
+        award
+            .tags
+            .0
+            .extend(recipients.iter().map(|recipient| TagList {
+                tag_type: NostrTag::Pubkey,
+                tags: vec![recipient.to_string()],
+            }));
+        award
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::NostrKeypair;
+
+    #[test]
+    fn test_badge_award() {
+        let issuer = NostrKeypair::generate(false);
+        let mut badge = Nip82::issue_badge(
+            "bravery",
+            "Awarded for bravery",
+            Some("https://example.com/bravery.png"),
+            None,
+        );
+        badge.pubkey = issuer.public_key();
+        issuer.sign_note(&mut badge).expect("Failed to sign note");
+        assert_eq!(badge.kind, 30009);
+        assert_eq!(
+            badge.tags.find_first_parameter().as_deref(),
+            Some("bravery")
+        );
+        assert_eq!(
+            badge.tags.find_tags_ref("name").collect::<Vec<_>>(),
+            vec!["bravery"]
+        );
+        assert_eq!(
+            badge.tags.find_tags_ref("description").collect::<Vec<_>>(),
+            vec!["Awarded for bravery"]
+        );
+        assert_eq!(badge.tags.find_tags_ref("image").count(), 1);
+        assert!(badge.tags.find_tags_ref("thumb").next().is_none());
+
+        let alice = NostrKeypair::generate(false).public_key();
+        let bob = NostrKeypair::generate(false).public_key();
+        let award = Nip82::award_badge(&badge, &[&alice, &bob]);
+        assert_eq!(award.kind, 8);
+        assert_eq!(
+            award.tags.find_tags_ref("a").collect::<Vec<_>>(),
+            vec![format!("30009:{}:bravery", issuer.public_key())]
+        );
+        assert_eq!(
+            award.tags.find_tags_owned(NostrTag::Pubkey),
+            vec![alice, bob]
+        );
+        let serialized = serde_json::to_value(&award.tags).unwrap();
+        assert_eq!(serialized.as_array().unwrap().len(), 3);
+    }
+}
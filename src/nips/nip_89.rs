@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    keypair::{NostrKeypair, SignerError},
+    notes::{NostrNote, NostrTag, TagList},
+};
+
+const RECOMMENDATION_KIND: u32 = 31989;
+const HANDLER_KIND: u32 = 31990;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip89Error {
+    WrongKind(u32),
+    MissingIdentifier,
+    InvalidContent(String),
+    InvalidKind(String),
+    Signing(SignerError),
+}
+impl std::fmt::Display for Nip89Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Nip89Error::WrongKind(kind) => {
+                write!(f, "Expected a kind {} note, found {}", HANDLER_KIND, kind)
+            }
+            Nip89Error::MissingIdentifier => write!(f, "Handler has no d tag"),
+            Nip89Error::InvalidContent(e) => write!(f, "Invalid handler content: {}", e),
+            Nip89Error::InvalidKind(kind) => write!(f, "Invalid k tag: {}", kind),
+            Nip89Error::Signing(e) => write!(f, "{}", e),
+        }
+    }
+}
+impl std::error::Error for Nip89Error {}
+
+// The JSON content of a kind 31990 note.
+#[derive(Serialize, Deserialize)]
+struct HandlerContent {
+    name: String,
+    url: String,
+    #[serde(rename = "picture", default, skip_serializing_if = "Option::is_none")]
+    icon_url: Option<String>,
+}
+
+/// A kind 31990 note announcing an application and the event kinds it can open.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AppHandler {
+    /// The `d` tag the handler is addressed by.
+    pub identifier: String,
+    pub name: String,
+    pub url: String,
+    /// One `k` tag each.
+    pub supported_kinds: Vec<u32>,
+    pub icon_url: Option<String>,
+}
+
+impl AppHandler {
+    pub fn to_note(&self, signer: &NostrKeypair) -> Result<NostrNote, Nip89Error> {
+        if self.identifier.is_empty() {
+            return Err(Nip89Error::MissingIdentifier);
+        }
+        let content = HandlerContent {
+            name: self.name.clone(),
+            url: self.url.clone(),
+            icon_url: self.icon_url.clone(),
+        };
+        let mut note = NostrNote {
+            kind: HANDLER_KIND,
+            pubkey: signer.public_key(),
+            content: serde_json::to_string(&content)
+                .map_err(|e| Nip89Error::InvalidContent(e.to_string()))?,
+            ..Default::default()
+        };
+        note.tags.add_parameter_tag(&self.identifier);
+        for kind in &self.supported_kinds {
+            note.tags.0.push(TagList {
+                tag_type: NostrTag::Custom("k"),
+                tags: vec![kind.to_string()],
+            });
+        }
+        signer.sign_note(&mut note).map_err(Nip89Error::Signing)?;
+        Ok(note)
+    }
+    pub fn from_note(note: &NostrNote) -> Result<Self, Nip89Error> {
+        if note.kind != HANDLER_KIND {
+            return Err(Nip89Error::WrongKind(note.kind));
+        }
+        let identifier = note
+            .tags
+            .first_tag_value("d")
+            .ok_or(Nip89Error::MissingIdentifier)?
+            .to_string();
+        let supported_kinds = note
+            .tags
+            .iter_tags_of_type("k")
+            .filter_map(|values| values.first())
+            .map(|kind| {
+                kind.parse()
+                    .map_err(|_| Nip89Error::InvalidKind(kind.clone()))
+            })
+            .collect::<Result<_, _>>()?;
+        let content: HandlerContent = serde_json::from_str(&note.content)
+            .map_err(|e| Nip89Error::InvalidContent(e.to_string()))?;
+        Ok(AppHandler {
+            identifier,
+            name: content.name,
+            url: content.url,
+            supported_kinds,
+            icon_url: content.icon_url,
+        })
+    }
+    /// Unsigned kind 31989 note recommending a handler for `event_kind`, addressed
+    /// by that kind. A `31990:<pubkey>:<d>` address goes in an `a` tag, a plain
+    /// note id in an `e` tag.
+    pub fn recommend_handler(handler_note_id: &str, event_kind: u32) -> NostrNote {
+        let mut note = NostrNote {
+            kind: RECOMMENDATION_KIND,
+            ..Default::default()
+        };
+        note.tags.add_parameter_tag(&event_kind.to_string());
+        if handler_note_id.contains(':') {
+            note.tags
+                .add_custom_tag(NostrTag::Custom("a"), handler_note_id);
+        } else {
+            note.tags.add_event_tag(handler_note_id);
+        }
+        note
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handler() -> AppHandler {
+        AppHandler {
+            identifier: "reader".to_string(),
+            name: "Reader".to_string(),
+            url: "https://reader.example.com".to_string(),
+            supported_kinds: vec![1, 30023],
+            icon_url: Some("https://reader.example.com/icon.png".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_app_handler_json_format() {
+        let keys = NostrKeypair::generate(false);
+        let note = handler().to_note(&keys).expect("Failed to build handler");
+        assert_eq!(note.kind, 31990);
+        assert!(note.verify());
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&note.content).unwrap(),
+            serde_json::json!({
+                "name": "Reader",
+                "url": "https://reader.example.com",
+                "picture": "https://reader.example.com/icon.png"
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(&note.tags).unwrap(),
+            serde_json::json!([["d", "reader"], ["k", "1"], ["k", "30023"]])
+        );
+        assert_eq!(AppHandler::from_note(&note), Ok(handler()));
+
+        let mut plain = handler();
+        plain.icon_url = None;
+        let note = plain.to_note(&keys).unwrap();
+        assert_eq!(
+            note.content,
+            r#"{"name":"Reader","url":"https://reader.example.com"}"#
+        );
+        assert_eq!(AppHandler::from_note(&note), Ok(plain));
+    }
+
+    #[test]
+    fn test_app_handler_errors() {
+        let keys = NostrKeypair::generate(false);
+        let mut unnamed = handler();
+        unnamed.identifier.clear();
+        assert_eq!(unnamed.to_note(&keys), Err(Nip89Error::MissingIdentifier));
+        assert_eq!(
+            AppHandler::from_note(&NostrNote::default()),
+            Err(Nip89Error::WrongKind(1))
+        );
+
+        let mut note = handler().to_note(&keys).unwrap();
+        note.tags.replace_tag("k", "text");
+        assert_eq!(
+            AppHandler::from_note(&note),
+            Err(Nip89Error::InvalidKind("text".to_string()))
+        );
+        note.tags.remove_all_of_type("d");
+        assert_eq!(
+            AppHandler::from_note(&note),
+            Err(Nip89Error::MissingIdentifier)
+        );
+        let mut note = handler().to_note(&keys).unwrap();
+        note.content = "not json".to_string();
+        assert!(matches!(
+            AppHandler::from_note(&note),
+            Err(Nip89Error::InvalidContent(_))
+        ));
+    }
+
+    #[test]
+    fn test_recommend_handler() {
+        let address =
+            "31990:f7234bd4c1394dda46d09f35bd384dd30cc552ad5541990f98844fb06676e9ca:reader";
+        let note = AppHandler::recommend_handler(address, 30023);
+        assert_eq!(note.kind, 31989);
+        assert_eq!(
+            serde_json::to_value(&note.tags).unwrap(),
+            serde_json::json!([["d", "30023"], ["a", address]])
+        );
+
+        let id = "b3e392b11f5d4f28321cedd09303a748acfd0487aea5a7450b3481c60b6e4f87";
+        let note = AppHandler::recommend_handler(id, 1);
+        assert_eq!(
+            serde_json::to_value(&note.tags).unwrap(),
+            serde_json::json!([["d", "1"], ["e", id]])
+        );
+    }
+}
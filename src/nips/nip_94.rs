@@ -0,0 +1,315 @@
+use crate::{
+    keypair::{NostrKeypair, SignerError},
+    notes::{NostrNote, NostrTag, TagList},
+};
+
+const FILE_METADATA_KIND: u32 = 1063;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip94Error {
+    WrongKind(u32),
+    MissingUrl,
+    MissingMimeType,
+    MissingHash,
+    InvalidUrl(String),
+    InvalidMimeType(String),
+    InvalidHash(String),
+    InvalidSize(String),
+    Signing(SignerError),
+}
+impl std::fmt::Display for Nip94Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Nip94Error::WrongKind(kind) => {
+                write!(
+                    f,
+                    "Expected a kind {} note, found {}",
+                    FILE_METADATA_KIND, kind
+                )
+            }
+            Nip94Error::MissingUrl => write!(f, "File metadata has no url tag"),
+            Nip94Error::MissingMimeType => write!(f, "File metadata has no m tag"),
+            Nip94Error::MissingHash => write!(f, "File metadata has no x tag"),
+            Nip94Error::InvalidUrl(url) => write!(f, "File url must be https: {}", url),
+            Nip94Error::InvalidMimeType(mime) => write!(f, "Invalid MIME type: {}", mime),
+            Nip94Error::InvalidHash(hash) => {
+                write!(f, "File hash must be 64 hex characters: {}", hash)
+            }
+            Nip94Error::InvalidSize(size) => write!(f, "Invalid file size: {}", size),
+            Nip94Error::Signing(e) => write!(f, "{}", e),
+        }
+    }
+}
+impl std::error::Error for Nip94Error {}
+
+/// A kind 1063 note describing a shared file. The content is its description.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileMetadata {
+    pub url: String,
+    /// The lowercase MIME type, from the `m` tag.
+    pub mime_type: String,
+    /// SHA-256 of the file as hex, from the `x` tag.
+    pub hash: String,
+    /// Size in bytes.
+    pub size: Option<u64>,
+    /// Dimensions as `<width>x<height>`.
+    pub dim: Option<String>,
+    pub magnet: Option<String>,
+    /// Torrent infohash, from the `i` tag.
+    pub infohash: Option<String>,
+    pub blurhash: Option<String>,
+    pub description: String,
+}
+
+impl FileMetadata {
+    pub fn from_note(note: &NostrNote) -> Result<Self, Nip94Error> {
+        if note.kind != FILE_METADATA_KIND {
+            return Err(Nip94Error::WrongKind(note.kind));
+        }
+        let first = |tag_type: &str| note.tags.first_tag_value(tag_type).map(str::to_string);
+        let size = first("size")
+            .map(|size| size.parse().map_err(|_| Nip94Error::InvalidSize(size)))
+            .transpose()?;
+        let metadata = FileMetadata {
+            url: first("url").ok_or(Nip94Error::MissingUrl)?,
+            mime_type: first("m").ok_or(Nip94Error::MissingMimeType)?,
+            hash: first("x").ok_or(Nip94Error::MissingHash)?,
+            size,
+            dim: first("dim"),
+            magnet: first("magnet"),
+            infohash: first("i"),
+            blurhash: first("blurhash"),
+            description: note.content.clone(),
+        };
+        metadata.validate()?;
+        Ok(metadata)
+    }
+    fn validate(&self) -> Result<(), Nip94Error> {
+        if self.url.is_empty() {
+            return Err(Nip94Error::MissingUrl);
+        }
+        if self.mime_type.is_empty() {
+            return Err(Nip94Error::MissingMimeType);
+        }
+        if self.hash.is_empty() {
+            return Err(Nip94Error::MissingHash);
+        }
+        match url::Url::parse(&self.url) {
+            Ok(url) if url.scheme() == "https" => {}
+            _ => return Err(Nip94Error::InvalidUrl(self.url.clone())),
+        }
+        if !is_mime_type(&self.mime_type) {
+            return Err(Nip94Error::InvalidMimeType(self.mime_type.clone()));
+        }
+        if self.hash.len() != 64 || !self.hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(Nip94Error::InvalidHash(self.hash.clone()));
+        }
+        Ok(())
+    }
+    fn to_note(&self) -> NostrNote {
+        let mut note = NostrNote {
+            kind: FILE_METADATA_KIND,
+            content: self.description.clone(),
+            ..Default::default()
+        };
+        let mut push = |tag_type: &'static str, value: &str| {
+            note.tags.0.push(TagList {
+                tag_type: NostrTag::Custom(tag_type),
+                tags: vec![value.to_string()],
+            });
+        };
+        push("url", &self.url);
+        push("m", &self.mime_type);
+        push("x", &self.hash);
+        if let Some(size) = self.size {
+            push("size", &size.to_string());
+        }
+        for (tag_type, value) in [
+            ("dim", &self.dim),
+            ("magnet", &self.magnet),
+            ("i", &self.infohash),
+            ("blurhash", &self.blurhash),
+        ] {
+            if let Some(value) = value {
+                push(tag_type, value);
+            }
+        }
+        note
+    }
+}
+
+// `type/subtype`, both made of RFC 6838 name characters.
+fn is_mime_type(mime_type: &str) -> bool {
+    let is_name = |name: &str| {
+        name.chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphanumeric())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "!#$&-^_.+".contains(c))
+    };
+    mime_type
+        .split_once('/')
+        .is_some_and(|(top, sub)| is_name(top) && is_name(sub))
+}
+
+/// Collects the fields of a `FileMetadata` and signs it as a kind 1063 note.
+#[derive(Debug, Clone, Default)]
+pub struct FileMetadataBuilder {
+    metadata: FileMetadata,
+}
+
+impl FileMetadataBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn url(mut self, url: &str) -> Self {
+        self.metadata.url = url.to_string();
+        self
+    }
+    pub fn mime_type(mut self, mime_type: &str) -> Self {
+        self.metadata.mime_type = mime_type.to_lowercase();
+        self
+    }
+    pub fn hash(mut self, sha256_hex: &str) -> Self {
+        self.metadata.hash = sha256_hex.to_string();
+        self
+    }
+    pub fn size(mut self, bytes: u64) -> Self {
+        self.metadata.size = Some(bytes);
+        self
+    }
+    pub fn dim(mut self, width: u32, height: u32) -> Self {
+        self.metadata.dim = Some(format!("{}x{}", width, height));
+        self
+    }
+    pub fn magnet(mut self, magnet: &str) -> Self {
+        self.metadata.magnet = Some(magnet.to_string());
+        self
+    }
+    pub fn infohash(mut self, infohash: &str) -> Self {
+        self.metadata.infohash = Some(infohash.to_string());
+        self
+    }
+    pub fn blurhash(mut self, blurhash: &str) -> Self {
+        self.metadata.blurhash = Some(blurhash.to_string());
+        self
+    }
+    pub fn description(mut self, description: &str) -> Self {
+        self.metadata.description = description.to_string();
+        self
+    }
+    /// Fails unless the url is https, the MIME type is valid and the hash is 64 hex characters.
+    pub fn build(self, signer: &NostrKeypair) -> Result<NostrNote, Nip94Error> {
+        self.metadata.validate()?;
+        let mut note = self.metadata.to_note();
+        note.pubkey = signer.public_key();
+        signer.sign_note(&mut note).map_err(Nip94Error::Signing)?;
+        Ok(note)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    fn builder() -> FileMetadataBuilder {
+        FileMetadataBuilder::new()
+            .url("https://cdn.example.com/cat.png")
+            .mime_type("image/PNG")
+            .hash(HASH)
+            .size(24_601)
+            .dim(800, 600)
+            .blurhash("LEHV6nWB2yk8pyo0adR*.7kCMdnj")
+            .description("A cat")
+    }
+
+    #[test]
+    fn test_file_metadata_round_trip() {
+        let keys = NostrKeypair::generate(false);
+        let note = builder()
+            .build(&keys)
+            .expect("Failed to build file metadata");
+        assert_eq!(note.kind, 1063);
+        assert!(note.verify());
+        assert_eq!(note.content, "A cat");
+        assert_eq!(
+            serde_json::to_value(&note.tags).unwrap(),
+            serde_json::json!([
+                ["url", "https://cdn.example.com/cat.png"],
+                ["m", "image/png"],
+                ["x", HASH],
+                ["size", "24601"],
+                ["dim", "800x600"],
+                ["blurhash", "LEHV6nWB2yk8pyo0adR*.7kCMdnj"]
+            ])
+        );
+
+        let metadata = FileMetadata::from_note(&note).expect("Failed to parse file metadata");
+        assert_eq!(
+            metadata,
+            FileMetadata {
+                url: "https://cdn.example.com/cat.png".to_string(),
+                mime_type: "image/png".to_string(),
+                hash: HASH.to_string(),
+                size: Some(24_601),
+                dim: Some("800x600".to_string()),
+                magnet: None,
+                infohash: None,
+                blurhash: Some("LEHV6nWB2yk8pyo0adR*.7kCMdnj".to_string()),
+                description: "A cat".to_string(),
+            }
+        );
+        assert_eq!(metadata.to_note().tags, note.tags);
+    }
+
+    #[test]
+    fn test_file_metadata_errors() {
+        let keys = NostrKeypair::generate(false);
+        for (builder, expected) in [
+            (builder().url(""), Nip94Error::MissingUrl),
+            (builder().mime_type(""), Nip94Error::MissingMimeType),
+            (builder().hash(""), Nip94Error::MissingHash),
+            (
+                builder().url("http://cdn.example.com/cat.png"),
+                Nip94Error::InvalidUrl("http://cdn.example.com/cat.png".to_string()),
+            ),
+            (
+                builder().mime_type("png"),
+                Nip94Error::InvalidMimeType("png".to_string()),
+            ),
+            (
+                builder().mime_type("image/"),
+                Nip94Error::InvalidMimeType("image/".to_string()),
+            ),
+            (
+                builder().hash("abc"),
+                Nip94Error::InvalidHash("abc".to_string()),
+            ),
+        ] {
+            assert_eq!(builder.build(&keys), Err(expected));
+        }
+
+        assert_eq!(
+            FileMetadata::from_note(&NostrNote::default()),
+            Err(Nip94Error::WrongKind(1))
+        );
+        for (tag_type, expected) in [
+            ("url", Nip94Error::MissingUrl),
+            ("m", Nip94Error::MissingMimeType),
+            ("x", Nip94Error::MissingHash),
+        ] {
+            let mut note = builder().build(&keys).unwrap();
+            note.tags.remove_all_of_type(tag_type);
+            assert_eq!(FileMetadata::from_note(&note), Err(expected));
+        }
+        let mut note = builder().build(&keys).unwrap();
+        note.tags.replace_tag("size", "big");
+        assert_eq!(
+            FileMetadata::from_note(&note),
+            Err(Nip94Error::InvalidSize("big".to_string()))
+        );
+    }
+}
@@ -0,0 +1,48 @@
+pub trait ClockSource {
+    fn now() -> i64;
+}
+
+pub struct SystemClock;
+impl SystemClock {
+    pub fn current() -> i64 {
+        <Self as ClockSource>::now()
+    }
+}
+impl ClockSource for SystemClock {
+    fn now() -> i64 {
+        #[cfg(test)]
+        if let Some(timestamp) = TEST_CLOCK.with(|clock| clock.get()) {
+            return timestamp;
+        }
+        chrono::Utc::now().timestamp()
+    }
+}
+
+// Thread local so parallel tests can each pin their own timestamp.
+#[cfg(test)]
+std::thread_local! {
+    static TEST_CLOCK: core::cell::Cell<Option<i64>> = const { core::cell::Cell::new(None) };
+}
+
+#[cfg(test)]
+pub(crate) fn set_test_clock(timestamp: i64) {
+    TEST_CLOCK.with(|clock| clock.set(Some(timestamp)));
+}
+
+#[cfg(test)]
+pub(crate) fn reset_test_clock() {
+    TEST_CLOCK.with(|clock| clock.set(None));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_override() {
+        set_test_clock(1_714_628_274);
+        assert_eq!(SystemClock::current(), 1_714_628_274);
+        reset_test_clock();
+        assert!(SystemClock::current() > 1_714_628_274);
+    }
+}
@@ -1,5 +1,8 @@
+#[cfg(feature = "std")]
+mod clock;
 mod note;
 mod tags;
+#[cfg(feature = "std")]
+pub use clock::*;
 pub use note::*;
 pub use tags::*;
-
@@ -1,10 +1,83 @@
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt::{Display, Formatter};
+use core::hash::{Hash, Hasher};
+use core::str::FromStr;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
 use sha2::{Digest, Sha256};
-use std::fmt::{Display, Formatter};
 
 use super::NoteTags;
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NostrNoteError {
+    MissingField(String),
+    InvalidFieldFormat { field: String, reason: String },
+}
+impl NostrNoteError {
+    fn invalid(field: &str, reason: &str) -> Self {
+        NostrNoteError::InvalidFieldFormat {
+            field: field.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+impl Display for NostrNoteError {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            NostrNoteError::MissingField(field) => write!(f, "Missing field: {}", field),
+            NostrNoteError::InvalidFieldFormat { field, reason } => {
+                write!(f, "Invalid field {}: {}", field, reason)
+            }
+        }
+    }
+}
+#[cfg(feature = "std")]
+impl std::error::Error for NostrNoteError {}
+
+/// An inline NIP-27 reference, holding the bech32 entity without its `nostr:` prefix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NostrReference {
+    /// A `note1` or `nevent1` entity.
+    Event(String),
+    /// An `npub1` or `nprofile1` entity.
+    Profile(String),
+    /// An `naddr1` entity.
+    Address(String),
+}
+impl NostrReference {
+    fn from_bech32(entity: &str) -> Option<Self> {
+        let (hrp, data) = entity.split_once('1')?;
+        if data.is_empty() {
+            return None;
+        }
+        match hrp {
+            "note" | "nevent" => Some(NostrReference::Event(entity.to_string())),
+            "npub" | "nprofile" => Some(NostrReference::Profile(entity.to_string())),
+            "naddr" => Some(NostrReference::Address(entity.to_string())),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyResult {
+    Valid,
+    InvalidSignature,
+    ContentHashMismatch,
+    MalformedPubkey,
+    MalformedId,
+    MalformedSig,
+    MissingId,
+    MissingSig,
+    /// Only returned by `verify_strict`, for a validly signed note past its NIP-40 expiration.
+    Expired,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct NostrNote {
     pub pubkey: String,
     pub created_at: i64,
@@ -16,11 +89,36 @@ pub struct NostrNote {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sig: Option<String>,
 }
+// Signed notes are identified by their id, the hash of every other field, so
+// equality and hashing only look at it. Notes without an id compare every field.
+impl PartialEq for NostrNote {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.id, &other.id) {
+            (Some(id), Some(other_id)) => id == other_id,
+            _ => {
+                self.id == other.id
+                    && self.pubkey == other.pubkey
+                    && self.created_at == other.created_at
+                    && self.kind == other.kind
+                    && self.tags == other.tags
+                    && self.content == other.content
+                    && self.sig == other.sig
+            }
+        }
+    }
+}
+impl Eq for NostrNote {}
+impl Hash for NostrNote {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write(self.id.as_deref().unwrap_or("").as_bytes());
+    }
+}
+#[cfg(feature = "std")]
 impl Default for NostrNote {
     fn default() -> Self {
         NostrNote {
             pubkey: "".to_string(),
-            created_at: chrono::Utc::now().timestamp(),
+            created_at: super::SystemClock::current(),
             kind: 1,
             tags: NoteTags::default(),
             content: "".to_string(),
@@ -32,28 +130,159 @@ impl Default for NostrNote {
 impl NostrNote {
     pub fn get_note_hrp(&self) -> Option<String> {
         let hrp = bech32::Hrp::parse("note").ok()?;
-        let note_data = self.id.as_ref()?;
-        let string = bech32::encode::<bech32::Bech32>(hrp, &note_data.as_bytes()).ok()?;
+        let note_data = self.id.as_deref().filter(|id| Self::is_hex(id, 64))?;
+        let string = bech32::encode::<bech32::Bech32>(hrp, &Self::hex_decode(note_data)).ok()?;
         Some(string)
     }
+    /// First 8 hex characters of the id, for display only; use the full id for lookups.
+    pub fn short_id(&self) -> Option<String> {
+        Some(self.id.as_ref()?.chars().take(8).collect())
+    }
+    /// First 8 hex characters of the pubkey, for display only; use the full pubkey for lookups.
+    pub fn short_pubkey(&self) -> String {
+        self.pubkey.chars().take(8).collect()
+    }
+    /// Kinds 0, 3 and 10000..=19999: relays keep only the latest note per pubkey and kind.
+    pub fn is_replaceable(&self) -> bool {
+        matches!(self.kind, 0 | 3 | 10000..=19999)
+    }
+    /// Kinds 30000..=39999: replaceable per pubkey, kind and `d` tag.
+    pub fn is_addressable(&self) -> bool {
+        matches!(self.kind, 30000..=39999)
+    }
+    /// Kinds 20000..=29999: relays forward these notes without storing them.
+    pub fn is_ephemeral(&self) -> bool {
+        matches!(self.kind, 20000..=29999)
+    }
+    pub fn requires_d_tag(&self) -> bool {
+        self.is_addressable()
+    }
+    /// Approximate length of the note's JSON, computed from field lengths without
+    /// serializing. Only common escapes in the content are counted.
+    pub fn estimate_wire_size(&self) -> usize {
+        let digits = |n: u64| n.checked_ilog10().map_or(1, |d| d as usize + 1);
+        let quoted = |value: &str| value.len() + 2;
+        let mut size = r#"{"pubkey":,"created_at":,"kind":,"tags":[],"content":}"#.len()
+            + quoted(&self.pubkey)
+            + digits(self.created_at.unsigned_abs())
+            + usize::from(self.created_at < 0)
+            + digits(u64::from(self.kind))
+            + quoted(&self.content)
+            + self
+                .content
+                .bytes()
+                .filter(|b| matches!(b, b'"' | b'\\' | b'\n' | b'\r' | b'\t'))
+                .count();
+        // Each tag is `[` + its quoted values joined by commas + `]`, tags joined by commas.
+        size += self
+            .tags
+            .0
+            .iter()
+            .map(|tag_list| {
+                2 + quoted(tag_list.tag_type.as_ref())
+                    + tag_list.tags.iter().map(|v| quoted(v) + 1).sum::<usize>()
+            })
+            .sum::<usize>()
+            + self.tags.0.len().saturating_sub(1);
+        if let Some(id) = &self.id {
+            size += r#","id":"#.len() + quoted(id);
+        }
+        if let Some(sig) = &self.sig {
+            size += r#","sig":"#.len() + quoted(sig);
+        }
+        size
+    }
+    /// Full note JSON with fields in NIP-01 order: id, pubkey, created_at, kind,
+    /// tags, content, sig. Missing id or sig fields are left out.
+    pub fn to_canonical_json(&self) -> Result<String, serde_json::Error> {
+        #[derive(Serialize)]
+        struct CanonicalNote<'a> {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            id: Option<&'a str>,
+            pubkey: &'a str,
+            created_at: i64,
+            kind: u32,
+            tags: &'a NoteTags,
+            content: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            sig: Option<&'a str>,
+        }
+        serde_json::to_string(&CanonicalNote {
+            id: self.id.as_deref(),
+            pubkey: &self.pubkey,
+            created_at: self.created_at,
+            kind: self.kind,
+            tags: &self.tags,
+            content: &self.content,
+            sig: self.sig.as_deref(),
+        })
+    }
+    /// Exact length of the note's JSON, or 0 if it fails to serialize.
+    pub fn wire_size(&self) -> usize {
+        serde_json::to_vec(self).map(|v| v.len()).unwrap_or(0)
+    }
+    /// Whether the note carries a NIP-36 `content-warning` tag.
+    pub fn is_sensitive(&self) -> bool {
+        self.tags.content_warning().is_some()
+    }
+    /// Leading zero bits of the note id, its NIP-13 proof of work; 0 without an id.
+    pub fn pow_difficulty(&self) -> u8 {
+        self.id.as_deref().map(leading_zero_bits).unwrap_or(0)
+    }
+    pub fn meets_pow_target(&self, target: u8) -> bool {
+        self.pow_difficulty() >= target
+    }
+    /// Every `e` tagged note id, in tag order and without repeats.
+    pub fn referenced_note_ids(&self) -> Vec<String> {
+        dedup(self.tags.find_all_tagged_events())
+    }
+    /// Every `p` tagged pubkey, in tag order and without repeats.
+    pub fn referenced_pubkeys(&self) -> Vec<String> {
+        dedup(self.tags.find_all_tagged_pubkeys())
+    }
+    /// NIP-27 `nostr:` references inline in the content, in order and without repeats.
+    /// Secret keys and unknown prefixes are skipped; the bech32 is not decoded.
+    pub fn parse_content_references(&self) -> Vec<NostrReference> {
+        let mut references: Vec<NostrReference> = Vec::new();
+        for reference in nostr_uri_entities(&self.content).filter_map(NostrReference::from_bech32) {
+            if !references.contains(&reference) {
+                references.push(reference);
+            }
+        }
+        references
+    }
+    /// Adds a NIP-26 `["delegation", delegator, conditions, token]` tag.
+    pub fn add_delegation_tag(&mut self, delegator_pubkey: &str, conditions: &str, token: &str) {
+        self.tags.0.push(super::TagList {
+            tag_type: super::NostrTag::Custom("delegation"),
+            tags: vec![
+                delegator_pubkey.to_string(),
+                conditions.to_string(),
+                token.to_string(),
+            ],
+        });
+    }
     pub fn id_bytes(&self) -> Option<[u8; 32]> {
         let mut id_bytes = [0u8; 32];
-        let id = Self::hex_decode(&self.id.as_ref()?);
+        let id = Self::hex_decode(self.id.as_ref()?);
         id_bytes.copy_from_slice(&id);
         Some(id_bytes)
     }
+    #[cfg(feature = "std")]
     fn sig_bytes(&self) -> Option<[u8; 64]> {
         let mut sig_bytes = [0u8; 64];
-        let sig = Self::hex_decode(&self.sig.as_ref()?);
+        let sig = Self::hex_decode(self.sig.as_ref()?);
         sig_bytes.copy_from_slice(&sig);
         Some(sig_bytes)
     }
+    #[cfg(feature = "std")]
     fn pubkey_bytes(&self) -> Option<[u8; 32]> {
         let mut pubkey_bytes = [0u8; 32];
         let pubkey = Self::hex_decode(&self.pubkey);
         pubkey_bytes.copy_from_slice(&pubkey);
         Some(pubkey_bytes)
     }
+    #[cfg(feature = "std")]
     pub fn serialize_id(&mut self) -> anyhow::Result<()> {
         let serialized_data = (
             0,
@@ -69,23 +298,21 @@ impl NostrNote {
         self.id = Some(Self::hex_encode(hasher.finalize().to_vec()));
         Ok(())
     }
-    fn verify_signature(&self) -> anyhow::Result<()> {
-        use secp256k1::{schnorr, Secp256k1, XOnlyPublicKey};
-        let secp = Secp256k1::verification_only();
-        let id = self
-            .id_bytes()
-            .ok_or(anyhow::anyhow!("Failed to get id bytes."))?;
-        let sig = self
-            .sig_bytes()
-            .ok_or(anyhow::anyhow!("Failed to get sig bytes."))?;
-        let public_key = XOnlyPublicKey::from_slice(
-            &self
-                .pubkey_bytes()
-                .ok_or(anyhow::anyhow!("Failed to get pubkey bytes."))?,
-        )?;
-        let signature = schnorr::Signature::from_byte_array(sig);
-        Ok(secp.verify_schnorr(&signature, &id, &public_key)?)
+    /// Unsigned copy of the note with its id computed, as sealed in NIP-59 gift wraps.
+    #[cfg(feature = "std")]
+    pub fn to_rumor(&self) -> NostrNote {
+        let mut rumor = self.clone();
+        if rumor.id.is_none() {
+            // Serializing plain strings and numbers cannot fail.
+            let _ = rumor.serialize_id();
+        }
+        rumor.sig = None;
+        rumor
+    }
+    pub fn is_rumor(&self) -> bool {
+        self.sig.is_none() && self.id.is_some()
     }
+    #[cfg(feature = "std")]
     fn verify_content(&self) -> bool {
         let mut copied_note = Self {
             pubkey: self.pubkey.to_string(),
@@ -100,54 +327,279 @@ impl NostrNote {
         }
         self.id == copied_note.id
     }
+    #[cfg(feature = "std")]
     pub fn verify(&self) -> bool {
-        if self.verify_signature().is_ok() && self.verify_content() {
-            return true;
+        self.verify_detailed() == VerifyResult::Valid
+    }
+    /// Like `verify_detailed`, but also rejects notes whose expiration has passed.
+    #[cfg(feature = "std")]
+    pub fn verify_strict(&self) -> VerifyResult {
+        match self.verify_detailed() {
+            VerifyResult::Valid if self.is_expired() => VerifyResult::Expired,
+            result => result,
         }
-        false
+    }
+    /// Whether the NIP-40 `expiration` tag is at or before the current time.
+    #[cfg(feature = "std")]
+    pub fn is_expired(&self) -> bool {
+        self.tags
+            .expiration()
+            .is_some_and(|expiration| expiration <= super::SystemClock::current())
+    }
+    #[cfg(feature = "std")]
+    pub fn verify_detailed(&self) -> VerifyResult {
+        use secp256k1::{schnorr, Secp256k1, XOnlyPublicKey};
+        let Some(id) = self.id.as_deref() else {
+            return VerifyResult::MissingId;
+        };
+        if !Self::is_hex(id, 64) {
+            return VerifyResult::MalformedId;
+        }
+        let Some(sig) = self.sig.as_deref() else {
+            return VerifyResult::MissingSig;
+        };
+        if !Self::is_hex(sig, 128) {
+            return VerifyResult::MalformedSig;
+        }
+        if !Self::is_hex(&self.pubkey, 64) {
+            return VerifyResult::MalformedPubkey;
+        }
+        let Some(Ok(public_key)) = self
+            .pubkey_bytes()
+            .map(|pubkey| XOnlyPublicKey::from_slice(&pubkey))
+        else {
+            return VerifyResult::MalformedPubkey;
+        };
+        if !self.verify_content() {
+            return VerifyResult::ContentHashMismatch;
+        }
+        let (Some(id), Some(sig)) = (self.id_bytes(), self.sig_bytes()) else {
+            return VerifyResult::MalformedId;
+        };
+        let signature = schnorr::Signature::from_byte_array(sig);
+        match Secp256k1::verification_only().verify_schnorr(&signature, &id, &public_key) {
+            Ok(()) => VerifyResult::Valid,
+            Err(_) => VerifyResult::InvalidSignature,
+        }
+    }
+    pub(crate) fn is_hex(value: &str, len: usize) -> bool {
+        value.len() == len && value.chars().all(|c| c.is_ascii_hexdigit())
     }
     fn hex_decode(hex_string: &str) -> Vec<u8> {
         hex_string
             .as_bytes()
             .chunks(2)
-            .filter_map(|b| u8::from_str_radix(std::str::from_utf8(b).ok()?, 16).ok())
+            .filter_map(|b| u8::from_str_radix(core::str::from_utf8(b).ok()?, 16).ok())
             .collect()
     }
+    #[cfg(feature = "std")]
     fn hex_encode(bytes: Vec<u8>) -> String {
         bytes.iter().map(|b| format!("{:02x}", b)).collect()
     }
 }
-impl Into<crate::relays::WebSocketMessage> for NostrNote {
-    fn into(self) -> crate::relays::WebSocketMessage {
+fn dedup(values: Vec<String>) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::with_capacity(values.len());
+    for value in values {
+        if !unique.contains(&value) {
+            unique.push(value);
+        }
+    }
+    unique
+}
+/// The entity of every `nostr:` URI in `content`, in order and without its prefix.
+/// An entity ends at the first character outside the bech32 alphabet, so
+/// trailing punctuation is left out. Entities are neither checked nor decoded.
+pub(crate) fn nostr_uri_entities(content: &str) -> impl Iterator<Item = &str> {
+    content.match_indices("nostr:").map(move |(start, scheme)| {
+        let entity = &content[start + scheme.len()..];
+        let end = entity
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(entity.len());
+        &entity[..end]
+    })
+}
+/// Leading zero bits of a hex id, stopping at the first non-hex character.
+/// An all-zero id saturates at 255.
+pub(crate) fn leading_zero_bits(id: &str) -> u8 {
+    let mut bits: u32 = 0;
+    for nibble in id.chars().map(|c| c.to_digit(16)) {
+        match nibble {
+            Some(0) => bits += 4,
+            Some(nibble) => {
+                bits += nibble.leading_zeros() - 28;
+                break;
+            }
+            None => break,
+        }
+    }
+    bits.min(u8::MAX as u32) as u8
+}
+#[cfg(feature = "std")]
+impl NostrNote {
+    /// Notes that reference this note's id, such as replies and reactions.
+    /// `None` for an unsigned note, which has no id to reference.
+    pub fn replies_subscription(&self) -> Option<crate::relays::NostrSubscription> {
+        let mut subscription = crate::relays::NostrSubscription::default();
+        subscription.add_tag("#e", self.id.as_deref()?);
+        Some(subscription)
+    }
+    /// Notes that mention this note's author.
+    pub fn mentions_subscription(&self) -> crate::relays::NostrSubscription {
+        let mut subscription = crate::relays::NostrSubscription::default();
+        subscription.add_tag("#p", &self.pubkey);
+        subscription
+    }
+    /// The NIP-33 address of an addressable note with a `d` tag.
+    pub fn to_address(&self) -> Option<crate::nips::NostrAddress> {
+        if !self.is_addressable() {
+            return None;
+        }
+        Some(crate::nips::NostrAddress {
+            identifier: self.tags.first_tag_value("d")?.to_string(),
+            pubkey: self.pubkey.clone(),
+            kind: self.kind,
+            relays: vec![],
+        })
+    }
+    /// Unsigned NIP-10 reply to `target`, with the same kind and empty content and pubkey.
+    /// Tags the thread `root` first when given, then `target` and its author; a `root` that
+    /// is `target` itself gets a single `root` tag. Fails if either note has no id.
+    pub fn clone_as_reply(
+        target: &NostrNote,
+        root: Option<&NostrNote>,
+    ) -> Result<NostrNote, NostrNoteError> {
+        let event_id = |note: &NostrNote| {
+            note.id
+                .clone()
+                .ok_or(NostrNoteError::MissingField("id".to_string()))
+        };
+        let target_id = event_id(target)?;
+        let root_id = root.map(event_id).transpose()?;
+        let mut reply = NostrNote {
+            pubkey: String::new(),
+            kind: target.kind,
+            ..Default::default()
+        };
+        let marked_event = |id: String, marker: &str| super::TagList {
+            tag_type: super::NostrTag::Event,
+            tags: vec![id, String::new(), marker.to_string()],
+        };
+        match root_id {
+            Some(root_id) if root_id == target_id => {
+                reply.tags.0.push(marked_event(root_id, "root"));
+            }
+            Some(root_id) => {
+                reply.tags.0.push(marked_event(root_id, "root"));
+                reply.tags.0.push(marked_event(target_id, "reply"));
+            }
+            None => reply.tags.0.push(marked_event(target_id, "reply")),
+        }
+        reply.tags.add_pubkey_tag(&target.pubkey);
+        Ok(reply)
+    }
+    /// Starts building an unsigned note of `kind`, see `NostrNoteBuilder`.
+    pub fn builder(kind: u32) -> NostrNoteBuilder {
+        NostrNoteBuilder {
+            note: NostrNote {
+                kind,
+                ..Default::default()
+            },
+        }
+    }
+}
+/// Chained construction of an unsigned note; `build` validates the pubkey and
+/// computes the id, leaving only the signature to add.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct NostrNoteBuilder {
+    note: NostrNote,
+}
+#[cfg(feature = "std")]
+impl NostrNoteBuilder {
+    pub fn pubkey(mut self, pubkey: &str) -> Self {
+        self.note.pubkey = pubkey.to_string();
+        self
+    }
+    pub fn content(mut self, content: &str) -> Self {
+        self.note.content = content.to_string();
+        self
+    }
+    pub fn created_at(mut self, created_at: i64) -> Self {
+        self.note.created_at = created_at;
+        self
+    }
+    pub fn tags(mut self, tags: NoteTags) -> Self {
+        self.note.tags = tags;
+        self
+    }
+    pub fn tag(mut self, tag_type: super::NostrTag, value: &str) -> Self {
+        self.note.tags.add_custom_tag(tag_type, value);
+        self
+    }
+    pub fn build(mut self) -> Result<NostrNote, NostrNoteError> {
+        if self.note.pubkey.is_empty() {
+            return Err(NostrNoteError::MissingField("pubkey".to_string()));
+        }
+        if !NostrNote::is_hex(&self.note.pubkey, 64) {
+            return Err(NostrNoteError::invalid(
+                "pubkey",
+                "expected 64 hex characters",
+            ));
+        }
+        self.note
+            .serialize_id()
+            .map_err(|e| NostrNoteError::invalid("id", &e.to_string()))?;
+        Ok(self.note)
+    }
+}
+#[cfg(feature = "std")]
+impl TryFrom<&NostrNote> for crate::relays::NostrSubscription {
+    type Error = NostrNoteError;
+    fn try_from(value: &NostrNote) -> Result<Self, Self::Error> {
+        value
+            .replies_subscription()
+            .ok_or(NostrNoteError::MissingField("id".to_string()))
+    }
+}
+#[cfg(feature = "std")]
+impl From<NostrNote> for crate::relays::WebSocketMessage {
+    fn from(value: NostrNote) -> Self {
         let note: String =
-            crate::relays::SendNoteEvent(crate::relays::RelayEventTag::EVENT, self).into();
+            crate::relays::SendNoteEvent(crate::relays::RelayEventTag::EVENT, value).into();
         crate::relays::WebSocketMessage::Text(note.into())
     }
 }
+#[cfg(feature = "std")]
 impl Display for NostrNote {
-    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
         write!(
             f,
             "{}",
-            serde_json::to_string_pretty(self).expect("Failed to serialize NostrNote.")
+            serde_json::to_string(self).expect("Failed to serialize NostrNote.")
         )
     }
 }
+impl FromStr for NostrNote {
+    type Err = serde_json::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_str(s)
+    }
+}
 impl TryFrom<String> for NostrNote {
     type Error = serde_json::Error;
     fn try_from(value: String) -> Result<Self, Self::Error> {
         serde_json::from_str(&value)
     }
 }
-impl Into<String> for NostrNote {
-    fn into(self) -> String {
-        serde_json::to_string(&self).unwrap()
+impl From<NostrNote> for String {
+    fn from(value: NostrNote) -> Self {
+        serde_json::to_string(&value).unwrap()
     }
 }
 impl TryFrom<&str> for NostrNote {
     type Error = serde_json::Error;
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        serde_json::from_str(&value)
+        serde_json::from_str(value)
     }
 }
 impl TryFrom<serde_json::Value> for NostrNote {
@@ -156,27 +608,679 @@ impl TryFrom<serde_json::Value> for NostrNote {
         serde_json::from_value(value)
     }
 }
+pub struct StrictNostrNoteValue(pub serde_json::Value);
+impl TryFrom<StrictNostrNoteValue> for NostrNote {
+    type Error = NostrNoteError;
+    fn try_from(value: StrictNostrNoteValue) -> Result<Self, Self::Error> {
+        let object = value
+            .0
+            .as_object()
+            .ok_or(NostrNoteError::invalid("note", "expected a JSON object"))?;
+        let field = |name: &str| {
+            object
+                .get(name)
+                .ok_or(NostrNoteError::MissingField(name.to_string()))
+        };
+        let pubkey = field("pubkey")?
+            .as_str()
+            .ok_or(NostrNoteError::invalid("pubkey", "expected a string"))?;
+        if !NostrNote::is_hex(pubkey, 64) {
+            return Err(NostrNoteError::invalid(
+                "pubkey",
+                "expected 64 hex characters",
+            ));
+        }
+        field("created_at")?
+            .as_i64()
+            .filter(|created_at| *created_at >= 0)
+            .ok_or(NostrNoteError::invalid(
+                "created_at",
+                "expected a non-negative integer",
+            ))?;
+        field("kind")?
+            .as_u64()
+            .filter(|kind| u32::try_from(*kind).is_ok())
+            .ok_or(NostrNoteError::invalid(
+                "kind",
+                "expected an unsigned 32-bit integer",
+            ))?;
+        let tags_are_valid = field("tags")?.as_array().is_some_and(|tags| {
+            tags.iter().all(|tag| {
+                tag.as_array()
+                    .is_some_and(|values| values.iter().all(|value| value.is_string()))
+            })
+        });
+        if !tags_are_valid {
+            return Err(NostrNoteError::invalid(
+                "tags",
+                "expected an array of string arrays",
+            ));
+        }
+        if !field("content")?.is_string() {
+            return Err(NostrNoteError::invalid("content", "expected a string"));
+        }
+        serde_json::from_value(value.0).map_err(|e| NostrNoteError::invalid("note", &e.to_string()))
+    }
+}
 impl TryFrom<&serde_json::Value> for NostrNote {
     type Error = serde_json::Error;
     fn try_from(value: &serde_json::Value) -> Result<Self, Self::Error> {
         serde_json::from_value(value.clone())
     }
 }
-impl Into<serde_json::Value> for NostrNote {
-    fn into(self) -> serde_json::Value {
-        serde_json::to_value(&self).unwrap()
+impl From<NostrNote> for serde_json::Value {
+    fn from(value: NostrNote) -> Self {
+        serde_json::to_value(&value).unwrap()
     }
 }
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(feature = "std", target_arch = "wasm32"))]
 impl TryFrom<wasm_bindgen_futures::wasm_bindgen::JsValue> for NostrNote {
     type Error = wasm_bindgen_futures::wasm_bindgen::JsError;
     fn try_from(value: wasm_bindgen_futures::wasm_bindgen::JsValue) -> Result<Self, Self::Error> {
         Ok(serde_wasm_bindgen::from_value(value)?)
     }
 }
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(feature = "std", target_arch = "wasm32"))]
 impl Into<wasm_bindgen_futures::wasm_bindgen::JsValue> for NostrNote {
     fn into(self) -> wasm_bindgen_futures::wasm_bindgen::JsValue {
         serde_wasm_bindgen::to_value(&self).unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::NostrKeypair;
+    use crate::notes::{NostrTag, TagList};
+
+    #[test]
+    fn test_note_parse_round_trip() {
+        let user_keys = NostrKeypair::generate(false);
+        let mut note = NostrNote {
+            pubkey: user_keys.public_key(),
+            content: "Hello, \"world\"!".to_string(),
+            ..Default::default()
+        };
+        note.tags.add_pubkey_tag(&user_keys.public_key());
+        user_keys.sign_note(&mut note).expect("Failed to sign note");
+        let note_string = note.to_string();
+        assert_eq!(note_string, serde_json::to_string(&note).unwrap());
+        let parsed_note = note_string
+            .parse::<NostrNote>()
+            .expect("Failed to parse note");
+        assert_eq!(parsed_note, note);
+        assert!(parsed_note.verify());
+        assert!("not a note".parse::<NostrNote>().is_err());
+    }
+
+    #[test]
+    fn test_note_value_omits_missing_fields() {
+        crate::notes::clock::set_test_clock(1714628274);
+        let note = NostrNote {
+            pubkey: "a".repeat(64),
+            content: "unsigned".to_string(),
+            ..Default::default()
+        };
+        crate::notes::clock::reset_test_clock();
+        assert_eq!(note.created_at, 1714628274);
+        let value: serde_json::Value = note.clone().into();
+        let object = value
+            .as_object()
+            .expect("Note should serialize to an object");
+        assert!(!object.contains_key("id"));
+        assert!(!object.contains_key("sig"));
+        assert!(object.values().all(|field| !field.is_null()));
+
+        let mut id_only = note;
+        id_only.serialize_id().unwrap();
+        let value: serde_json::Value = id_only.clone().into();
+        assert_eq!(value["id"], serde_json::json!(id_only.id.unwrap()));
+        assert!(value.get("sig").is_none());
+    }
+
+    #[test]
+    fn test_strict_note_value() {
+        let valid_note = serde_json::json!({
+            "pubkey": "a".repeat(64),
+            "created_at": 1714628274,
+            "kind": 1,
+            "tags": [["p", "b".repeat(64)]],
+            "content": "strict",
+        });
+        let note = NostrNote::try_from(StrictNostrNoteValue(valid_note.clone()))
+            .expect("Valid note should parse");
+        assert_eq!(note.content, "strict");
+
+        let mut missing_content = valid_note.clone();
+        missing_content.as_object_mut().unwrap().remove("content");
+        assert_eq!(
+            NostrNote::try_from(StrictNostrNoteValue(missing_content)),
+            Err(NostrNoteError::MissingField("content".to_string()))
+        );
+
+        let invalid_field = |field: &str, value: serde_json::Value| {
+            let mut note = valid_note.clone();
+            note[field] = value;
+            match NostrNote::try_from(StrictNostrNoteValue(note)) {
+                Err(NostrNoteError::InvalidFieldFormat { field, .. }) => field,
+                other => panic!("Expected InvalidFieldFormat, got {:?}", other),
+            }
+        };
+        assert_eq!(invalid_field("pubkey", serde_json::json!("abc")), "pubkey");
+        assert_eq!(
+            invalid_field("pubkey", serde_json::json!("z".repeat(64))),
+            "pubkey"
+        );
+        assert_eq!(
+            invalid_field("created_at", serde_json::json!(-1)),
+            "created_at"
+        );
+        assert_eq!(invalid_field("kind", serde_json::json!(u64::MAX)), "kind");
+        assert_eq!(invalid_field("kind", serde_json::json!("1")), "kind");
+        assert_eq!(
+            invalid_field("tags", serde_json::json!(["p", "abc"])),
+            "tags"
+        );
+        assert_eq!(invalid_field("content", serde_json::json!(42)), "content");
+    }
+
+    #[test]
+    fn test_note_identity_by_id() {
+        let user_keys = NostrKeypair::generate(false);
+        let mut note = NostrNote {
+            pubkey: user_keys.public_key(),
+            content: "original".to_string(),
+            ..Default::default()
+        };
+        let unsigned = note.clone();
+        let mut other_unsigned = unsigned.clone();
+        other_unsigned.content = "edited".to_string();
+        assert_ne!(unsigned, other_unsigned);
+
+        user_keys.sign_note(&mut note).expect("Failed to sign note");
+        assert_ne!(note, unsigned);
+        let mut same_id = note.clone();
+        same_id.content = "tampered".to_string();
+        assert_eq!(note, same_id);
+
+        let library: std::collections::HashSet<NostrNote> =
+            [note, same_id, unsigned, other_unsigned]
+                .into_iter()
+                .collect();
+        assert_eq!(library.len(), 3);
+    }
+
+    #[test]
+    fn test_verify_detailed() {
+        let user_keys = NostrKeypair::generate(false);
+        let mut note = NostrNote {
+            pubkey: user_keys.public_key(),
+            content: "verify me".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(note.verify_detailed(), VerifyResult::MissingId);
+        user_keys.sign_note(&mut note).expect("Failed to sign note");
+        assert_eq!(note.verify_detailed(), VerifyResult::Valid);
+        assert!(note.verify());
+
+        let with = |edit: fn(&mut NostrNote)| {
+            let mut edited = note.clone();
+            edit(&mut edited);
+            edited.verify_detailed()
+        };
+        assert_eq!(
+            with(|n| n.id = Some("abc".to_string())),
+            VerifyResult::MalformedId
+        );
+        assert_eq!(with(|n| n.sig = None), VerifyResult::MissingSig);
+        assert_eq!(
+            with(|n| n.sig = Some("zz".repeat(64))),
+            VerifyResult::MalformedSig
+        );
+        assert_eq!(
+            with(|n| n.pubkey = "abc".to_string()),
+            VerifyResult::MalformedPubkey
+        );
+        assert_eq!(
+            with(|n| n.content = "tampered".to_string()),
+            VerifyResult::ContentHashMismatch
+        );
+        let other_keys = NostrKeypair::generate(false);
+        let mut forged = note.clone();
+        other_keys
+            .sign_note(&mut forged)
+            .expect("Failed to sign note");
+        forged.pubkey = note.pubkey.clone();
+        forged.id = note.id.clone();
+        assert_eq!(forged.verify_detailed(), VerifyResult::InvalidSignature);
+        assert!(!forged.verify());
+    }
+
+    #[test]
+    fn test_expiration() {
+        let user_keys = NostrKeypair::generate(false);
+        let mut note = NostrNote {
+            pubkey: user_keys.public_key(),
+            content: "gone soon".to_string(),
+            ..Default::default()
+        };
+        note.tags.add_expiration_tag(1_000);
+        user_keys.sign_note(&mut note).expect("Failed to sign note");
+        for (now, expired) in [(999, false), (1_000, true), (1_001, true)] {
+            crate::notes::clock::set_test_clock(now);
+            assert_eq!(note.is_expired(), expired, "at {}", now);
+            assert!(note.verify());
+            let strict = if expired {
+                VerifyResult::Expired
+            } else {
+                VerifyResult::Valid
+            };
+            assert_eq!(note.verify_strict(), strict, "at {}", now);
+        }
+        let mut unsigned = note.clone();
+        unsigned.sig = None;
+        assert_eq!(unsigned.verify_strict(), VerifyResult::MissingSig);
+        crate::notes::clock::reset_test_clock();
+
+        let mut permanent = NostrNote {
+            pubkey: user_keys.public_key(),
+            ..Default::default()
+        };
+        user_keys
+            .sign_note(&mut permanent)
+            .expect("Failed to sign note");
+        assert!(!permanent.is_expired());
+        assert_eq!(permanent.verify_strict(), VerifyResult::Valid);
+    }
+
+    #[test]
+    fn test_note_hrp_encodes_raw_id() {
+        let mut note = NostrNote {
+            id: Some(
+                "a0bec1e5b029394436ed20382f22b549e88b12ea079a0db4cb7091a0f585cc30".to_string(),
+            ),
+            ..Default::default()
+        };
+        assert_eq!(
+            note.get_note_hrp().as_deref(),
+            Some("note15zlvreds9yu5gdhdyquz7g44f85gkyh2q7dqmdxtwzg6pav9escq8lgrp4")
+        );
+        note.id = Some("not hex".to_string());
+        assert_eq!(note.get_note_hrp(), None);
+        note.id = None;
+        assert_eq!(note.get_note_hrp(), None);
+    }
+
+    #[test]
+    fn test_note_subscriptions() {
+        let user_keys = NostrKeypair::generate(false);
+        let mut note = NostrNote {
+            pubkey: user_keys.public_key(),
+            content: "reply to me".to_string(),
+            ..Default::default()
+        };
+        assert!(note.replies_subscription().is_none());
+        assert_eq!(
+            crate::relays::NostrSubscription::try_from(&note).unwrap_err(),
+            NostrNoteError::MissingField("id".to_string())
+        );
+        user_keys.sign_note(&mut note).expect("Failed to sign note");
+        let id = note.id.clone().unwrap();
+        let replies: crate::relays::NostrSubscription = (&note).try_into().unwrap();
+        assert_eq!(
+            serde_json::to_value(&replies).unwrap(),
+            serde_json::json!({"#e": [id]})
+        );
+        assert_eq!(
+            serde_json::to_value(note.replies_subscription()).unwrap(),
+            serde_json::to_value(&replies).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_value(note.mentions_subscription()).unwrap(),
+            serde_json::json!({"#p": [user_keys.public_key()]})
+        );
+    }
+
+    #[test]
+    fn test_to_address() {
+        let mut note = NostrNote {
+            pubkey: "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d".to_string(),
+            kind: 30023,
+            ..Default::default()
+        };
+        assert_eq!(note.to_address(), None);
+        note.tags.add_parameter_tag("banana");
+        let address = note.to_address().expect("Addressable note with a d tag");
+        assert_eq!(address.identifier, "banana");
+        assert_eq!(address.kind, 30023);
+        assert_eq!(address.pubkey, note.pubkey);
+        assert!(address.relays.is_empty());
+        note.kind = 1;
+        assert_eq!(note.to_address(), None);
+    }
+
+    #[test]
+    fn test_clone_as_reply() {
+        let user_keys = NostrKeypair::generate(false);
+        let mut root = NostrNote {
+            pubkey: user_keys.public_key(),
+            content: "thread root".to_string(),
+            ..Default::default()
+        };
+        user_keys.sign_note(&mut root).expect("Failed to sign note");
+        let mut target = NostrNote {
+            pubkey: user_keys.public_key(),
+            kind: 1111,
+            content: "first reply".to_string(),
+            ..Default::default()
+        };
+        user_keys
+            .sign_note(&mut target)
+            .expect("Failed to sign note");
+        let (root_id, target_id) = (root.id.clone().unwrap(), target.id.clone().unwrap());
+
+        let reply = NostrNote::clone_as_reply(&target, Some(&root)).unwrap();
+        assert_eq!(reply.kind, 1111);
+        assert!(reply.content.is_empty());
+        assert!(reply.pubkey.is_empty());
+        assert_eq!(reply.id, None);
+        assert_eq!(
+            serde_json::to_value(&reply.tags).unwrap(),
+            serde_json::json!([
+                ["e", root_id, "", "root"],
+                ["e", target_id, "", "reply"],
+                ["p", user_keys.public_key()]
+            ])
+        );
+        assert_eq!(
+            reply.tags.find_all_tagged_events(),
+            vec![root_id.clone(), target_id.clone()]
+        );
+
+        let direct = NostrNote::clone_as_reply(&target, None).unwrap();
+        assert_eq!(
+            serde_json::to_value(&direct.tags).unwrap(),
+            serde_json::json!([["e", target_id, "", "reply"], ["p", user_keys.public_key()]])
+        );
+
+        let to_root = NostrNote::clone_as_reply(&root, Some(&root)).unwrap();
+        assert_eq!(
+            serde_json::to_value(&to_root.tags).unwrap(),
+            serde_json::json!([["e", root_id, "", "root"], ["p", user_keys.public_key()]])
+        );
+
+        let unsigned = NostrNote {
+            pubkey: user_keys.public_key(),
+            ..Default::default()
+        };
+        assert_eq!(
+            NostrNote::clone_as_reply(&unsigned, None),
+            Err(NostrNoteError::MissingField("id".to_string()))
+        );
+        assert_eq!(
+            NostrNote::clone_as_reply(&target, Some(&unsigned)),
+            Err(NostrNoteError::MissingField("id".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_short_identifiers() {
+        let user_keys = NostrKeypair::generate(false);
+        let mut note = NostrNote {
+            pubkey: user_keys.public_key(),
+            ..Default::default()
+        };
+        assert_eq!(note.short_id(), None);
+        user_keys.sign_note(&mut note).expect("Failed to sign note");
+        let short_id = note.short_id().unwrap();
+        assert_eq!(short_id.len(), 8);
+        assert!(note.id.as_ref().unwrap().starts_with(&short_id));
+        assert_eq!(note.short_pubkey().len(), 8);
+        assert!(note.pubkey.starts_with(&note.short_pubkey()));
+    }
+
+    #[test]
+    fn test_to_rumor() {
+        let user_keys = NostrKeypair::generate(false);
+        let mut note = NostrNote {
+            pubkey: user_keys.public_key(),
+            content: "Just between us".to_string(),
+            ..Default::default()
+        };
+        assert!(!note.is_rumor());
+        let rumor = note.to_rumor();
+        assert!(rumor.is_rumor());
+        assert_eq!(rumor.verify_detailed(), VerifyResult::MissingSig);
+
+        user_keys.sign_note(&mut note).expect("Failed to sign note");
+        assert!(!note.is_rumor());
+        let signed_rumor = note.to_rumor();
+        assert!(signed_rumor.is_rumor());
+        assert_eq!(signed_rumor.id, note.id);
+        assert_eq!(signed_rumor.id, rumor.id);
+        assert!(note.sig.is_some());
+    }
+
+    #[test]
+    fn test_wire_size() {
+        let keys = NostrKeypair::generate(false);
+        let signed = |content: String, tags: usize| {
+            let mut note = NostrNote {
+                pubkey: keys.public_key(),
+                content,
+                ..Default::default()
+            };
+            for i in 0..tags {
+                note.tags
+                    .add_custom_tag(NostrTag::Custom("t"), &format!("topic{}", i));
+                note.tags.0.push(TagList {
+                    tag_type: NostrTag::Pubkey,
+                    tags: vec![keys.public_key(), "wss://relay.example.com".to_string()],
+                });
+            }
+            keys.sign_note(&mut note).expect("Failed to sign note");
+            note
+        };
+        let notes = [
+            NostrNote::default(),
+            signed(String::new(), 0),
+            signed("gm".to_string(), 1),
+            signed("A \"quoted\" line\nand another\n".repeat(20), 10),
+            signed("x".repeat(100_000), 500),
+        ];
+        for note in notes {
+            let actual = note.wire_size();
+            assert_eq!(actual, note.to_string().len());
+            let estimate = note.estimate_wire_size();
+            assert!(
+                estimate.abs_diff(actual) * 10 <= actual,
+                "estimate {} vs actual {}",
+                estimate,
+                actual
+            );
+        }
+    }
+
+    #[test]
+    fn test_canonical_json() {
+        let keys = NostrKeypair::generate(false);
+        let mut note = NostrNote {
+            pubkey: keys.public_key(),
+            created_at: 1_700_000_000,
+            content: "canonical \"json\"".to_string(),
+            ..Default::default()
+        };
+        note.tags.add_custom_tag(NostrTag::Custom("t"), "nostr");
+        keys.sign_note(&mut note).expect("Failed to sign note");
+        // serde_json maps sort their keys, so this puts `content` first.
+        let shuffled = serde_json::to_value(&note).unwrap().to_string();
+        assert!(shuffled.starts_with(r#"{"content""#));
+
+        let parsed: NostrNote = shuffled.parse().expect("Failed to parse note");
+        assert!(parsed.verify());
+        assert_eq!(
+            parsed.to_canonical_json().unwrap(),
+            format!(
+                r#"{{"id":"{}","pubkey":"{}","created_at":1700000000,"kind":1,"tags":[["t","nostr"]],"content":"canonical \"json\"","sig":"{}"}}"#,
+                note.id.as_deref().unwrap(),
+                keys.public_key(),
+                note.sig.as_deref().unwrap()
+            )
+        );
+        let unsigned = NostrNote {
+            pubkey: keys.public_key(),
+            created_at: 1_700_000_000,
+            ..Default::default()
+        };
+        assert_eq!(
+            unsigned.to_canonical_json().unwrap(),
+            format!(
+                r#"{{"pubkey":"{}","created_at":1700000000,"kind":1,"tags":[],"content":""}}"#,
+                keys.public_key()
+            )
+        );
+    }
+
+    #[test]
+    fn test_pow_difficulty() {
+        for (id, bits) in [
+            // The example id from NIP-13.
+            (
+                "000000000e9d97a1ab09fc381030b346cdd7a142ad57e6df0b46dc9bef6c7e2d",
+                36,
+            ),
+            (
+                "002f0a7b6e4a8f0b9c3d1e2f3a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f1a2b",
+                10,
+            ),
+            (
+                "8f0a7b6e4a8f0b9c3d1e2f3a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f1a2b3c",
+                0,
+            ),
+        ] {
+            let note = NostrNote {
+                id: Some(id.to_string()),
+                ..Default::default()
+            };
+            assert_eq!(note.pow_difficulty(), bits);
+            assert!(note.meets_pow_target(bits));
+            assert!(!note.meets_pow_target(bits + 1));
+        }
+        assert_eq!(NostrNote::default().pow_difficulty(), 0);
+        assert!(NostrNote::default().meets_pow_target(0));
+    }
+
+    #[test]
+    fn test_is_sensitive() {
+        let mut note = NostrNote::default();
+        assert!(!note.is_sensitive());
+        note.tags.add_content_warning(None);
+        assert!(note.is_sensitive());
+        note.tags.add_content_warning(Some("spoilers"));
+        assert!(note.is_sensitive());
+    }
+
+    #[test]
+    fn test_references() {
+        const NOTE: &str = "4376c65d2f232afbe9b882a35baa4f6fe8667c4e684749af565f981833ed6a65";
+        const ALICE: &str = "b0635d6a9851d3aed0cd6c495b282167acf761729078d975fc341b22650b07b9";
+        const BOB: &str = "6e468422dfb74a5738702a8823b9b28168abab8655faacb6853cd0ee15deee93";
+        let npub = "npub1kp346k5c28f6a5xdd3y4kgppv7k0wctjjpudja0ux3djyegtq7usfewsqx";
+        let nevent = "nevent1qqsyxakxt5hjx2hma8ug9g6m4f8klsu89ehxjh2wp4gvj0yr56w2e7s3t7kf3";
+        let naddr = "naddr1qqxnzd3cxqmrzv3exgmr2wfeqgsxu3k5ut7nrvtrn3nlx6wcmy4ejmeurv7";
+        let mut note = NostrNote {
+            content: format!(
+                "Thanks nostr:{npub}! See nostr:{nevent}, nostr:{naddr} and again nostr:{npub}. \
+                 Not a reference: nostr:nsec1xyz nostr: npub1"
+            ),
+            ..Default::default()
+        };
+        note.tags.add_event_tag(NOTE);
+        note.tags.add_event_tag(NOTE);
+        note.tags
+            .add_pubkey_tag_with_relay(ALICE, Some("wss://relay.example.com"));
+        note.tags.add_pubkey_tag_with_relay(BOB, None);
+        note.tags.add_pubkey_tag_with_relay(ALICE, None);
+
+        assert_eq!(note.referenced_note_ids(), vec![NOTE]);
+        assert_eq!(note.referenced_pubkeys(), vec![ALICE, BOB]);
+        assert_eq!(
+            note.parse_content_references(),
+            vec![
+                NostrReference::Profile(npub.to_string()),
+                NostrReference::Event(nevent.to_string()),
+                NostrReference::Address(naddr.to_string()),
+            ]
+        );
+        assert!(NostrNote::default().parse_content_references().is_empty());
+    }
+
+    #[test]
+    fn test_kind_ranges() {
+        // (kind, replaceable, ephemeral, addressable)
+        let cases = [
+            (0, true, false, false),
+            (1, false, false, false),
+            (2, false, false, false),
+            (3, true, false, false),
+            (4, false, false, false),
+            (9999, false, false, false),
+            (10000, true, false, false),
+            (10002, true, false, false),
+            (19999, true, false, false),
+            (20000, false, true, false),
+            (22242, false, true, false),
+            (29999, false, true, false),
+            (30000, false, false, true),
+            (30023, false, false, true),
+            (39999, false, false, true),
+            (40000, false, false, false),
+        ];
+        for (kind, replaceable, ephemeral, addressable) in cases {
+            let note = NostrNote {
+                kind,
+                ..Default::default()
+            };
+            assert_eq!(note.is_replaceable(), replaceable, "kind {}", kind);
+            assert_eq!(note.is_ephemeral(), ephemeral, "kind {}", kind);
+            assert_eq!(note.is_addressable(), addressable, "kind {}", kind);
+            assert_eq!(note.requires_d_tag(), addressable, "kind {}", kind);
+        }
+    }
+
+    #[test]
+    fn test_note_builder() {
+        let user_keys = NostrKeypair::generate(false);
+        let mut note = NostrNote::builder(1)
+            .pubkey(&user_keys.public_key())
+            .content("hello")
+            .created_at(1714628274)
+            .tag(crate::notes::NostrTag::Custom("t"), "builder")
+            .build()
+            .expect("Failed to build note");
+        assert_eq!(note.kind, 1);
+        assert_eq!(note.content, "hello");
+        assert_eq!(note.created_at, 1714628274);
+        assert_eq!(
+            note.tags
+                .find_tags_owned(crate::notes::NostrTag::Custom("t")),
+            vec!["builder"]
+        );
+        let id = note.id.clone().expect("Builder should compute the id");
+        assert!(note.verify_content());
+        user_keys.sign_note(&mut note).expect("Failed to sign note");
+        assert_eq!(note.id, Some(id));
+        assert!(note.verify());
+
+        assert_eq!(
+            NostrNote::builder(1).content("hello").build(),
+            Err(NostrNoteError::MissingField("pubkey".to_string()))
+        );
+        for pubkey in ["abc", &"g".repeat(64)] {
+            assert!(matches!(
+                NostrNote::builder(1).pubkey(pubkey).build(),
+                Err(NostrNoteError::InvalidFieldFormat { ref field, .. }) if field == "pubkey"
+            ));
+        }
+    }
+}
@@ -1,5 +1,11 @@
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::str::FromStr;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::str::FromStr;
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
 #[serde(untagged)]
@@ -9,16 +15,21 @@ pub enum NostrTag {
     Parameterized,
     Custom(&'static str),
 }
-impl Into<String> for NostrTag {
-    fn into(self) -> String {
+impl AsRef<str> for NostrTag {
+    fn as_ref(&self) -> &str {
         match self {
-            NostrTag::Pubkey => "p".to_string(),
-            NostrTag::Event => "e".to_string(),
-            NostrTag::Parameterized => "d".to_string(),
-            NostrTag::Custom(tag_type) => tag_type.to_string(),
+            NostrTag::Pubkey => "p",
+            NostrTag::Event => "e",
+            NostrTag::Parameterized => "d",
+            NostrTag::Custom(tag_type) => tag_type,
         }
     }
 }
+impl From<NostrTag> for String {
+    fn from(value: NostrTag) -> Self {
+        value.as_ref().to_string()
+    }
+}
 impl FromStr for NostrTag {
     type Err = ();
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -36,6 +47,15 @@ pub struct TagList {
     pub tag_type: NostrTag,
     pub tags: Vec<String>,
 }
+impl TagList {
+    // A list grouped by `add_pubkey_tag` or `add_event_tag` holds nothing but ids,
+    // any other list is `[id, relay, ...]` and only points at its first value.
+    fn is_grouped_ids(&self) -> bool {
+        self.tags
+            .iter()
+            .all(|value| value.len() == 64 && value.bytes().all(|b| b.is_ascii_hexdigit()))
+    }
+}
 impl Serialize for TagList {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -67,13 +87,8 @@ impl<'de> Deserialize<'de> for TagList {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
 pub struct NoteTags(pub Vec<TagList>);
-impl Default for NoteTags {
-    fn default() -> Self {
-        NoteTags(Vec::new())
-    }
-}
 impl Serialize for NoteTags {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -94,31 +109,93 @@ impl<'de> Deserialize<'de> for NoteTags {
 
 impl NoteTags {
     pub fn find_first_tagged_pubkey(&self) -> Option<String> {
-        self.0
-            .iter()
-            .find(|tag_list| tag_list.tag_type == NostrTag::Pubkey)
-            .and_then(|tag_list| tag_list.tags.first().cloned())
+        self.first_tag_value("p").map(str::to_string)
     }
     pub fn find_first_tagged_event(&self) -> Option<String> {
-        self.0
-            .iter()
-            .find(|tag_list| tag_list.tag_type == NostrTag::Event)
-            .and_then(|tag_list| tag_list.tags.first().cloned())
+        self.first_tag_value("e").map(str::to_string)
     }
     pub fn find_first_parameter(&self) -> Option<String> {
+        self.first_tag_value("d").map(str::to_string)
+    }
+    /// The values of every tag named `tag_type`, borrowed and in tag order.
+    pub fn iter_tags_of_type<'a>(
+        &'a self,
+        tag_type: &'a str,
+    ) -> impl Iterator<Item = &'a [String]> + 'a {
         self.0
             .iter()
-            .find(|tag_list| tag_list.tag_type == NostrTag::Parameterized)
-            .and_then(|tag_list| tag_list.tags.first().cloned())
+            .filter(move |tag_list| tag_list.tag_type.as_ref() == tag_type)
+            .map(|tag_list| tag_list.tags.as_slice())
     }
-    pub fn find_tags(&self, tag_type: NostrTag) -> Vec<String> 
-    {
+    /// The first value of the first tag named `tag_type`, without allocating.
+    pub fn first_tag_value<'a>(&'a self, tag_type: &'a str) -> Option<&'a str> {
+        self.iter_tags_of_type(tag_type)
+            .next()
+            .and_then(|values| values.first())
+            .map(String::as_str)
+    }
+    /// Same as `find_tags_owned`.
+    #[deprecated(note = "use `find_tags_owned`, or `find_tags_ref` to avoid cloning")]
+    pub fn find_tags(&self, tag_type: impl AsRef<str>) -> Vec<String> {
+        self.find_tags_owned(tag_type)
+    }
+    /// Every value of every tag named `tag_type`, cloned. Prefer `find_tags_ref`
+    /// when the values are only read.
+    pub fn find_tags_owned(&self, tag_type: impl AsRef<str>) -> Vec<String> {
+        self.find_tags_ref(tag_type).map(str::to_string).collect()
+    }
+    /// Every value of every tag named `tag_type`, borrowed and in tag order. Takes a
+    /// `NostrTag` or a tag name and matches by name, so `NostrTag::Custom("p")`
+    /// finds `NostrTag::Pubkey` tags.
+    pub fn find_tags_ref<'a>(
+        &'a self,
+        tag_type: impl AsRef<str> + 'a,
+    ) -> impl Iterator<Item = &'a str> + 'a {
         self.0
             .iter()
-            .filter(|tag_list| tag_list.tag_type == tag_type)
-            .flat_map(|tag_list| tag_list.tags.iter().cloned())
+            .filter(move |tag_list| tag_list.tag_type.as_ref() == tag_type.as_ref())
+            .flat_map(|tag_list| tag_list.tags.iter().map(String::as_str))
+    }
+    /// Every tagged pubkey, in tag order.
+    pub fn find_all_tagged_pubkeys(&self) -> Vec<String> {
+        self.tagged_ids(NostrTag::Pubkey)
+            .map(|(pubkey, _)| pubkey.to_string())
+            .collect()
+    }
+    /// Every tagged event id, in tag order.
+    pub fn find_all_tagged_events(&self) -> Vec<String> {
+        self.tagged_ids(NostrTag::Event)
+            .map(|(id, _)| id.to_string())
             .collect()
     }
+    /// Every tagged event id with the relay hint from its tag, if it has one.
+    pub fn find_all_tagged_events_with_relay(&self) -> Vec<(String, Option<String>)> {
+        self.tagged_ids(NostrTag::Event)
+            .map(|(id, relay)| (id.to_string(), relay.map(str::to_string)))
+            .collect()
+    }
+    fn tag_lists(&self, tag_type: NostrTag) -> impl Iterator<Item = &TagList> + '_ {
+        self.0
+            .iter()
+            .filter(move |tag_list| tag_list.tag_type == tag_type)
+    }
+    fn tagged_ids(&self, tag_type: NostrTag) -> impl Iterator<Item = (&str, Option<&str>)> + '_ {
+        self.tag_lists(tag_type).flat_map(move |tag_list| {
+            let (ids, relay) = if tag_list.is_grouped_ids() {
+                (&tag_list.tags[..], None)
+            } else {
+                let relay = tag_list.tags.get(1).filter(|relay| !relay.is_empty());
+                (&tag_list.tags[..tag_list.tags.len().min(1)], relay)
+            };
+            ids.iter()
+                .map(move |id| (id.as_str(), relay.map(String::as_str)))
+        })
+    }
+    /// Matches tags by their string name, so `NostrTag::Custom("p")` and
+    /// `NostrTag::Pubkey` find the same values.
+    pub fn find_tags_by_type(&self, tag: &NostrTag) -> Vec<String> {
+        self.find_tags_owned(tag)
+    }
     pub fn add_custom_tag(&mut self, tag_type: NostrTag, tag: &str) {
         if let Some(index) = self.0.iter().position(|inner| inner.tag_type == tag_type) {
             self.0[index].tags.push(tag.to_string());
@@ -160,6 +237,16 @@ impl NoteTags {
             self.0.push(new_inner);
         }
     }
+    /// Unlike add_pubkey_tag this always starts a new ["p", pubkey, relay] tag,
+    /// so the relay hint is not mistaken for another pubkey.
+    pub fn add_pubkey_tag_with_relay(&mut self, pubkey: &str, relay_hint: Option<&str>) {
+        let mut tags = vec![pubkey.to_string()];
+        tags.extend(relay_hint.map(str::to_string));
+        self.0.push(TagList {
+            tag_type: NostrTag::Pubkey,
+            tags,
+        });
+    }
     pub fn add_event_tag(&mut self, event_id: &str) {
         if let Some(index) = self
             .0
@@ -175,4 +262,510 @@ impl NoteTags {
             self.0.push(new_inner);
         }
     }
+    /// Removes the first `tag_type` tag pointing at `value`, returning whether one
+    /// was found. Ids grouped by `add_pubkey_tag` or `add_event_tag` are removed
+    /// one at a time, any other tag only matches on its first value.
+    pub fn remove_tag(&mut self, tag_type: &str, value: &str) -> bool {
+        let Some(index) = self.0.iter().position(|tag_list| {
+            tag_list.tag_type.as_ref() == tag_type
+                && if tag_list.is_grouped_ids() {
+                    tag_list.tags.iter().any(|tag| tag == value)
+                } else {
+                    tag_list.tags.first().is_some_and(|tag| tag == value)
+                }
+        }) else {
+            return false;
+        };
+        let tag_list = &mut self.0[index];
+        if tag_list.is_grouped_ids() && tag_list.tags.len() > 1 {
+            tag_list.tags.retain(|tag| tag != value);
+        } else {
+            self.0.remove(index);
+        }
+        true
+    }
+    /// Removes every `tag_type` tag, returning how many serialized tags were dropped.
+    pub fn remove_all_of_type(&mut self, tag_type: &str) -> usize {
+        let before = self.0.len();
+        self.0
+            .retain(|tag_list| tag_list.tag_type.as_ref() != tag_type);
+        before - self.0.len()
+    }
+    /// Leaves `new_value` as the only `tag_type` tag.
+    pub fn replace_tag(&mut self, tag_type: &str, new_value: &str) {
+        // Reusing the removed tag's type saves `from_str` leaking a custom name.
+        let existing = self
+            .0
+            .iter()
+            .find(|tag_list| tag_list.tag_type.as_ref() == tag_type)
+            .map(|tag_list| tag_list.tag_type.clone());
+        self.remove_all_of_type(tag_type);
+        if let Some(tag_type) = existing.or_else(|| tag_type.parse().ok()) {
+            self.add_custom_tag(tag_type, new_value);
+        }
+    }
+    /// Sets the NIP-40 `["expiration", unix_ts]` tag, replacing any previous one.
+    pub fn add_expiration_tag(&mut self, unix_ts: i64) {
+        self.remove_all_of_type("expiration");
+        self.0.push(TagList {
+            tag_type: NostrTag::Custom("expiration"),
+            tags: vec![unix_ts.to_string()],
+        });
+    }
+    /// Appends a `["r", relay_url]` tag, refusing urls that are not `ws://` or `wss://`.
+    pub fn add_relay_tag(&mut self, relay_url: &str) -> Result<(), super::NostrNoteError> {
+        if !relay_url.starts_with("wss://") && !relay_url.starts_with("ws://") {
+            return Err(super::NostrNoteError::InvalidFieldFormat {
+                field: "r".to_string(),
+                reason: "Relay urls must start with ws:// or wss://".to_string(),
+            });
+        }
+        self.0.push(TagList {
+            tag_type: NostrTag::Custom("r"),
+            tags: vec![relay_url.to_string()],
+        });
+        Ok(())
+    }
+    /// Urls of every `r` tag, without their read or write markers.
+    pub fn all_relay_tags(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .filter(|tag_list| tag_list.tag_type.as_ref() == "r")
+            .filter_map(|tag_list| tag_list.tags.first().cloned())
+            .collect()
+    }
+    /// Sets the NIP-14 `["subject", subject]` tag, replacing any previous one.
+    pub fn add_subject_tag(&mut self, subject: &str) {
+        self.remove_all_of_type("subject");
+        self.0.push(TagList {
+            tag_type: NostrTag::Custom("subject"),
+            tags: vec![subject.to_string()],
+        });
+    }
+    pub fn subject(&self) -> Option<&str> {
+        self.find_tags_ref("subject").next()
+    }
+    /// Sets the NIP-36 `["content-warning"]` tag, with `reason` when given,
+    /// replacing any previous one.
+    pub fn add_content_warning(&mut self, reason: Option<&str>) {
+        self.remove_all_of_type("content-warning");
+        self.0.push(TagList {
+            tag_type: NostrTag::Custom("content-warning"),
+            tags: reason
+                .map(|reason| reason.to_string())
+                .into_iter()
+                .collect(),
+        });
+    }
+    /// `None` without a `content-warning` tag, otherwise its reason if it has one.
+    pub fn content_warning(&self) -> Option<Option<&str>> {
+        self.0
+            .iter()
+            .find(|tag_list| tag_list.tag_type.as_ref() == "content-warning")
+            .map(|tag_list| tag_list.tags.first().map(String::as_str))
+    }
+    /// Sets the NIP-13 `["nonce", counter, target_bits]` tag, replacing any previous one.
+    pub fn add_nonce_tag(&mut self, counter: u64, target_bits: u8) {
+        self.remove_all_of_type("nonce");
+        self.0.push(TagList {
+            tag_type: NostrTag::Custom("nonce"),
+            tags: vec![counter.to_string(), target_bits.to_string()],
+        });
+    }
+    /// Counter and committed target of the first `nonce` tag, if both parse.
+    pub fn nonce(&self) -> Option<(u64, u8)> {
+        let mut values = self.iter_tags_of_type("nonce").next()?.iter();
+        let counter = values.next()?.parse().ok()?;
+        let target_bits = values.next()?.parse().ok()?;
+        Some((counter, target_bits))
+    }
+    /// Sets the NIP-57 `["amount", msats]` tag, replacing any previous one.
+    pub fn add_amount_tag(&mut self, msats: u64) {
+        self.remove_all_of_type("amount");
+        self.0.push(TagList {
+            tag_type: NostrTag::Custom("amount"),
+            tags: vec![msats.to_string()],
+        });
+    }
+    /// Sets the NIP-57 `["lnurl", lnurl]` tag, replacing any previous one.
+    pub fn add_lnurl_tag(&mut self, lnurl: &str) -> Result<(), super::NostrNoteError> {
+        if lnurl.is_empty() {
+            return Err(super::NostrNoteError::InvalidFieldFormat {
+                field: "lnurl".to_string(),
+                reason: "The lnurl must not be empty".to_string(),
+            });
+        }
+        self.remove_all_of_type("lnurl");
+        self.0.push(TagList {
+            tag_type: NostrTag::Custom("lnurl"),
+            tags: vec![lnurl.to_string()],
+        });
+        Ok(())
+    }
+    /// Sets the NIP-57 `["bolt11", invoice]` tag, replacing any previous one.
+    pub fn add_bolt11_tag(&mut self, bolt11: &str) {
+        self.remove_all_of_type("bolt11");
+        self.0.push(TagList {
+            tag_type: NostrTag::Custom("bolt11"),
+            tags: vec![bolt11.to_string()],
+        });
+    }
+    /// The first `amount` tag in millisats, if it parses.
+    pub fn amount_msats(&self) -> Option<u64> {
+        self.first_tag_value("amount")?.parse().ok()
+    }
+    pub fn lnurl(&self) -> Option<&str> {
+        self.first_tag_value("lnurl")
+    }
+    pub fn bolt11(&self) -> Option<&str> {
+        self.first_tag_value("bolt11")
+    }
+    /// The first `expiration` tag, if it holds a valid unix timestamp.
+    pub fn expiration(&self) -> Option<i64> {
+        self.find_tags_ref("expiration")
+            .next()
+            .and_then(|expiration| expiration.parse().ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+
+    #[test]
+    fn test_find_tags_ref() {
+        let mut tags = NoteTags::default();
+        for i in 0..100 {
+            tags.add_custom_tag(NostrTag::Custom("t"), &format!("tag{}", i));
+            tags.add_pubkey_tag(&format!("pubkey{}", i));
+        }
+        let borrowed: Vec<&str> = tags.find_tags_ref(NostrTag::Custom("t")).collect();
+        assert_eq!(borrowed.len(), 100);
+        assert_eq!(borrowed.first(), Some(&"tag0"));
+        assert_eq!(borrowed, tags.find_tags_owned(NostrTag::Custom("t")));
+        assert_eq!(borrowed, tags.find_tags_owned("t"));
+        assert_eq!(tags.find_tags_ref(NostrTag::Event).count(), 0);
+
+        let by_name: Vec<&str> = tags.find_tags_ref("p").collect();
+        assert_eq!(by_name.len(), 100);
+        assert_eq!(
+            by_name,
+            tags.find_tags_ref(NostrTag::Pubkey).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            by_name,
+            tags.find_tags_ref(NostrTag::Custom("p"))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_iter_tags_of_type() {
+        let mut tags = NoteTags::default();
+        for i in 0..60 {
+            tags.0.push(TagList {
+                tag_type: NostrTag::Custom("t"),
+                tags: vec![format!("tag{}", i)],
+            });
+        }
+        tags.0.push(TagList {
+            tag_type: NostrTag::Custom("d"),
+            tags: vec!["identifier".to_string()],
+        });
+        tags.0.push(TagList {
+            tag_type: NostrTag::Pubkey,
+            tags: vec!["pubkey".to_string(), "wss://relay.example.com".to_string()],
+        });
+        tags.0.push(TagList {
+            tag_type: NostrTag::Event,
+            tags: vec![],
+        });
+
+        assert_eq!(tags.iter_tags_of_type("t").count(), 60);
+        assert_eq!(
+            tags.iter_tags_of_type("p").next(),
+            Some(&["pubkey".to_string(), "wss://relay.example.com".to_string()][..])
+        );
+        assert_eq!(tags.first_tag_value("t"), Some("tag0"));
+        assert_eq!(tags.first_tag_value("p"), Some("pubkey"));
+        assert_eq!(tags.first_tag_value("e"), None);
+        assert_eq!(tags.first_tag_value("x"), None);
+        // The iterator path agrees with the allocating one, custom names included.
+        assert_eq!(
+            tags.iter_tags_of_type("t").flatten().collect::<Vec<_>>(),
+            tags.find_tags_owned(NostrTag::Custom("t"))
+                .iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(tags.find_first_parameter().as_deref(), Some("identifier"));
+        assert_eq!(tags.find_first_tagged_pubkey().as_deref(), Some("pubkey"));
+        assert_eq!(tags.find_first_tagged_event(), None);
+    }
+
+    #[test]
+    fn test_find_all_tagged() {
+        let id = |c: char| c.to_string().repeat(64);
+        let mut tags: NoteTags = serde_json::from_value(serde_json::json!([
+            ["e", id('3'), "wss://relay.example.com", "root"],
+            ["p", id('b')],
+            ["e", id('1'), "", "reply", id('b')],
+            ["t", id('f')],
+            ["p", id('a'), "wss://relay.example.com"],
+            ["e", id('2')]
+        ]))
+        .unwrap();
+        // add_pubkey_tag groups these into the first ["p", ...] tag.
+        tags.add_pubkey_tag(&id('c'));
+        tags.add_pubkey_tag(&id('d'));
+        assert_eq!(
+            tags.find_all_tagged_pubkeys(),
+            vec![id('b'), id('c'), id('d'), id('a')]
+        );
+        assert_eq!(
+            tags.find_all_tagged_events(),
+            vec![id('3'), id('1'), id('2')]
+        );
+        assert_eq!(
+            tags.find_all_tagged_events_with_relay(),
+            vec![
+                (id('3'), Some("wss://relay.example.com".to_string())),
+                (id('1'), None),
+                (id('2'), None),
+            ]
+        );
+        assert_eq!(
+            tags.find_first_tagged_pubkey(),
+            tags.find_all_tagged_pubkeys().first().cloned()
+        );
+        assert!(NoteTags::default().find_all_tagged_events().is_empty());
+    }
+
+    #[test]
+    fn test_remove_tags() {
+        let id = |c: char| c.to_string().repeat(64);
+        let mut tags: NoteTags = serde_json::from_value(serde_json::json!([
+            ["e", id('1'), "wss://relay.example.com", "root"],
+            ["p", id('a'), id('b')],
+            ["t", "nostr"],
+            ["e", id('2')],
+            ["t", "rust"]
+        ]))
+        .unwrap();
+
+        assert!(!tags.remove_tag("e", "wss://relay.example.com"));
+        assert!(!tags.remove_tag("p", &id('c')));
+        assert!(!tags.remove_tag("x", "nostr"));
+        assert!(tags.remove_tag("e", &id('1')));
+        assert_eq!(tags.find_all_tagged_events(), vec![id('2')]);
+        assert!(tags.remove_tag("p", &id('a')));
+        assert_eq!(tags.find_all_tagged_pubkeys(), vec![id('b')]);
+        assert!(tags.remove_tag("p", &id('b')));
+        assert!(!tags.remove_tag("p", &id('b')));
+        assert_eq!(
+            tags.0
+                .iter()
+                .filter(|t| t.tag_type == NostrTag::Pubkey)
+                .count(),
+            0
+        );
+
+        assert_eq!(tags.remove_all_of_type("x"), 0);
+        assert_eq!(tags.remove_all_of_type("t"), 2);
+        assert_eq!(tags.remove_all_of_type("t"), 0);
+        assert_eq!(
+            serde_json::to_value(&tags).unwrap(),
+            serde_json::json!([["e", id('2')]])
+        );
+
+        tags.replace_tag("e", &id('3'));
+        tags.replace_tag("d", "identifier");
+        tags.replace_tag("d", "renamed");
+        tags.replace_tag("alt", "a reply");
+        assert_eq!(
+            serde_json::to_value(&tags).unwrap(),
+            serde_json::json!([["e", id('3')], ["d", "renamed"], ["alt", "a reply"]])
+        );
+        assert_eq!(tags.find_first_parameter().as_deref(), Some("renamed"));
+        assert_eq!(NoteTags::default().remove_all_of_type("e"), 0);
+    }
+
+    #[test]
+    fn test_expiration_tag() {
+        let mut tags = NoteTags::default();
+        assert_eq!(tags.expiration(), None);
+        tags.add_expiration_tag(1_600_000_000);
+        tags.add_expiration_tag(1_700_000_000);
+        assert_eq!(tags.expiration(), Some(1_700_000_000));
+        assert_eq!(
+            tags.find_tags_owned(NostrTag::Custom("expiration")),
+            vec!["1700000000"]
+        );
+        tags.replace_tag("expiration", "soon");
+        assert_eq!(tags.expiration(), None);
+    }
+
+    #[test]
+    fn test_relay_and_subject_tags() {
+        let mut tags = NoteTags::default();
+        assert!(tags.add_relay_tag("wss://relay.example.com").is_ok());
+        assert!(tags.add_relay_tag("ws://localhost:7777").is_ok());
+        for invalid in [
+            "https://relay.example.com",
+            "relay.example.com",
+            "",
+            "wss:/typo",
+        ] {
+            assert!(tags.add_relay_tag(invalid).is_err(), "{}", invalid);
+        }
+        assert_eq!(
+            tags.all_relay_tags(),
+            vec!["wss://relay.example.com", "ws://localhost:7777"]
+        );
+
+        assert_eq!(tags.subject(), None);
+        tags.add_subject_tag("Lunch?");
+        tags.add_subject_tag("Re: Lunch?");
+        assert_eq!(tags.subject(), Some("Re: Lunch?"));
+        assert_eq!(tags.find_tags_owned(NostrTag::Custom("subject")).len(), 1);
+    }
+
+    #[test]
+    fn test_content_warning() {
+        let mut tags = NoteTags::default();
+        assert_eq!(tags.content_warning(), None);
+        tags.add_content_warning(None);
+        assert_eq!(tags.content_warning(), Some(None));
+        assert_eq!(
+            serde_json::to_value(&tags).unwrap(),
+            serde_json::json!([["content-warning"]])
+        );
+        tags.add_content_warning(Some("spoilers"));
+        assert_eq!(tags.content_warning(), Some(Some("spoilers")));
+        assert_eq!(
+            serde_json::to_value(&tags).unwrap(),
+            serde_json::json!([["content-warning", "spoilers"]])
+        );
+
+        for (json, expected) in [
+            (serde_json::json!([["content-warning"]]), Some(None)),
+            (
+                serde_json::json!([["t", "art"], ["content-warning", "nudity"]]),
+                Some(Some("nudity")),
+            ),
+            (serde_json::json!([["t", "art"]]), None),
+        ] {
+            let tags: NoteTags = serde_json::from_value(json).unwrap();
+            assert_eq!(tags.content_warning(), expected);
+        }
+    }
+
+    #[test]
+    fn test_nonce_tag() {
+        let mut tags = NoteTags::default();
+        assert_eq!(tags.nonce(), None);
+        tags.add_nonce_tag(776797, 20);
+        tags.add_nonce_tag(776798, 21);
+        assert_eq!(tags.nonce(), Some((776798, 21)));
+        assert_eq!(
+            serde_json::to_value(&tags).unwrap(),
+            serde_json::json!([["nonce", "776798", "21"]])
+        );
+        for json in [
+            serde_json::json!([["nonce", "776797"]]),
+            serde_json::json!([["nonce", "abc", "20"]]),
+            serde_json::json!([["nonce", "1", "300"]]),
+        ] {
+            let tags: NoteTags = serde_json::from_value(json).unwrap();
+            assert_eq!(tags.nonce(), None);
+        }
+    }
+
+    #[test]
+    fn test_zap_tags() {
+        let mut tags = NoteTags::default();
+        assert_eq!(tags.amount_msats(), None);
+        assert_eq!(tags.lnurl(), None);
+        assert_eq!(tags.bolt11(), None);
+        tags.add_amount_tag(1);
+        tags.add_amount_tag(u64::MAX);
+        tags.add_lnurl_tag("lnurl1dp68gurn8ghj7um5v93kketj9ehx2amn9uh8wetvdskkkmn0wahz7mrww4excup")
+            .unwrap();
+        tags.add_bolt11_tag("lnbc210n1pj4");
+        assert_eq!(tags.amount_msats(), Some(u64::MAX));
+        assert_eq!(
+            tags.lnurl(),
+            Some("lnurl1dp68gurn8ghj7um5v93kketj9ehx2amn9uh8wetvdskkkmn0wahz7mrww4excup")
+        );
+        assert_eq!(tags.bolt11(), Some("lnbc210n1pj4"));
+        assert_eq!(tags.0.len(), 3);
+        assert_eq!(
+            serde_json::to_value(&tags).unwrap()[0],
+            serde_json::json!(["amount", "18446744073709551615"])
+        );
+        assert!(tags.add_lnurl_tag("").is_err());
+
+        for (json, expected) in [
+            (serde_json::json!([["amount", "21000"]]), Some(21_000)),
+            (serde_json::json!([["amount", "0"]]), Some(0)),
+            (serde_json::json!([["amount", "-5"]]), None),
+            (serde_json::json!([["amount", "21 sats"]]), None),
+            (serde_json::json!([["amount"]]), None),
+        ] {
+            let tags: NoteTags = serde_json::from_value(json).unwrap();
+            assert_eq!(tags.amount_msats(), expected);
+        }
+    }
+
+    #[test]
+    fn test_find_tags_by_type() {
+        let mut tags = NoteTags::default();
+        tags.add_pubkey_tag("pubkey");
+        tags.add_event_tag("event");
+        tags.add_parameter_tag("identifier");
+        tags.add_custom_tag(NostrTag::Custom("t"), "topic");
+        for tag in [
+            NostrTag::Pubkey,
+            NostrTag::Event,
+            NostrTag::Parameterized,
+            NostrTag::Custom("t"),
+        ] {
+            assert_eq!(
+                tags.find_tags_by_type(&tag),
+                tags.find_tags_owned(tag.clone())
+            );
+            assert_eq!(tags.find_tags_by_type(&tag).len(), 1);
+        }
+        assert_eq!(
+            tags.find_tags_by_type(&NostrTag::Custom("p")),
+            tags.find_tags_owned(NostrTag::Pubkey)
+        );
+        assert!(tags.find_tags_by_type(&NostrTag::Custom("x")).is_empty());
+    }
+
+    #[test]
+    fn test_tags_serialize_as_nested_arrays() {
+        let mut note = crate::notes::NostrNote {
+            pubkey: "a".repeat(64),
+            created_at: 1714628274,
+            kind: 1,
+            tags: NoteTags::default(),
+            content: "tagged".to_string(),
+            id: None,
+            sig: None,
+        };
+        note.tags.add_pubkey_tag("abc");
+        note.tags.add_event_tag("def");
+        note.tags.add_custom_tag(NostrTag::Custom("t"), "nostr");
+        let json = serde_json::to_string(&note).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value["tags"],
+            serde_json::json!([["p", "abc"], ["e", "def"], ["t", "nostr"]])
+        );
+        assert!(json.contains(r#""tags":[["p","abc"],["e","def"],["t","nostr"]]"#));
+        let parsed: crate::notes::NostrNote = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.tags, note.tags);
+        assert_eq!(parsed, note);
+    }
 }
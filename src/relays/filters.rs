@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 
 use super::SubscribeEvent;
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct NostrSubscription {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub authors: Option<Vec<String>>,
@@ -18,41 +18,100 @@ pub struct NostrSubscription {
     pub until: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub limit: Option<u32>,
+    /// NIP-50 full-text query, only honored by relays that list NIP-50.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub search: Option<String>,
     #[serde(flatten)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tags: Option<HashMap<String, Vec<String>>>,
+    /// Further filters sent in the same `REQ`; relays return notes matching any of them.
+    /// Not part of this filter's own JSON, see `SubscribeEvent`.
+    #[serde(skip)]
+    pub additional_filters: Vec<NostrSubscription>,
 }
-impl Default for NostrSubscription {
-    fn default() -> Self {
-        NostrSubscription {
-            authors: None,
-            ids: None,
-            kinds: None,
-            since: None,
-            until: None,
-            limit: None,
-            tags: None,
-        }
-    }
-}
-impl Into<crate::relays::WebSocketMessage> for NostrSubscription {
-    fn into(self) -> crate::relays::WebSocketMessage {
-        let event: SubscribeEvent = self.into();
+impl From<NostrSubscription> for crate::relays::WebSocketMessage {
+    fn from(value: NostrSubscription) -> Self {
+        let event: SubscribeEvent = value.into();
         crate::relays::WebSocketMessage::Text(event.into())
     }
 }
-impl Into<SubscribeEvent> for NostrSubscription {
-    fn into(self) -> SubscribeEvent {
+impl From<NostrSubscription> for SubscribeEvent {
+    fn from(value: NostrSubscription) -> Self {
         let random_bits: [u8; 16] = thread_rng().gen();
         let random_id = random_bits.iter().map(|b| format!("{:02x}", b)).collect::<String>();
         SubscribeEvent(
             super::RelayEventTag::REQ,
             random_id,
-            self.clone(),
+            value,
         )
     }
 }
 impl NostrSubscription {
+    /// Matches the notes at a NIP-33 address: its author, kind and `d` tag.
+    pub fn for_address(address: &crate::nips::NostrAddress) -> Self {
+        NostrSubscription {
+            authors: Some(vec![address.pubkey.clone()]),
+            kinds: Some(vec![address.kind]),
+            ..Default::default()
+        }
+        .with_tag_filter("d", vec![address.identifier.clone()])
+    }
+    /// A subscription with `filter` as its only filter.
+    pub fn single(mut filter: NostrSubscription) -> Self {
+        filter.additional_filters.clear();
+        filter
+    }
+    /// Adds `filter`, along with any filters it carries, to the same `REQ`.
+    pub fn with_additional_filter(mut self, mut filter: NostrSubscription) -> Self {
+        let nested = std::mem::take(&mut filter.additional_filters);
+        self.additional_filters.push(filter);
+        self.additional_filters.extend(nested);
+        self
+    }
+    /// This filter followed by the additional ones, in `REQ` order.
+    pub fn filters(&self) -> impl Iterator<Item = &NostrSubscription> + '_ {
+        std::iter::once(self).chain(&self.additional_filters)
+    }
+    /// Whether a relay would send `note` for this subscription: it passes any one
+    /// of its filters, see `matches_filter`.
+    pub fn matches(&self, note: &crate::notes::NostrNote) -> bool {
+        self.filters().any(|filter| filter.matches_filter(note))
+    }
+    /// NIP-01 matching against this filter alone: every field that is set must
+    /// match. `limit` only applies to stored notes and `search` is left to the relay.
+    pub fn matches_filter(&self, note: &crate::notes::NostrNote) -> bool {
+        let listed = |values: &Option<Vec<String>>, value: Option<&str>| {
+            values.as_ref().is_none_or(|values| {
+                value.is_some_and(|value| values.iter().any(|listed| listed == value))
+            })
+        };
+        let created_at = u64::try_from(note.created_at).unwrap_or_default();
+        listed(&self.ids, note.id.as_deref())
+            && listed(&self.authors, Some(&note.pubkey))
+            && self
+                .kinds
+                .as_ref()
+                .is_none_or(|kinds| kinds.contains(&note.kind))
+            && self.since.is_none_or(|since| created_at >= since)
+            && self.until.is_none_or(|until| created_at <= until)
+            && self.tags.iter().flatten().all(|(key, values)| {
+                let Some(letter) = key.strip_prefix('#') else {
+                    return true;
+                };
+                note.tags.iter_tags_of_type(letter).any(|tag| {
+                    tag.first()
+                        .is_some_and(|value| values.iter().any(|listed| listed == value))
+                })
+            })
+    }
+    pub fn to_wire_json(&self, subscription_id: &str) -> String {
+        SubscribeEvent(
+            super::RelayEventTag::REQ,
+            subscription_id.to_string(),
+            self.clone(),
+        )
+        .into()
+    }
     pub fn add_tag(&mut self, tag: &str, value: &str) {
         if let Some(tags) = &mut self.tags {
             if let Some(tag_values) = tags.get_mut(tag) {
@@ -66,6 +125,27 @@ impl NostrSubscription {
             self.tags = Some(tags);
         }
     }
+    /// Adds a NIP-01 `#<letter>` tag query; `letter` may be given with or without the `#`.
+    pub fn with_tag_filter(mut self, letter: &str, values: Vec<String>) -> Self {
+        let key = format!("#{}", letter.trim_start_matches('#'));
+        for value in values {
+            self.add_tag(&key, &value);
+        }
+        self
+    }
+    /// Matches notes carrying the `["t", tag]` hashtag.
+    pub fn with_hashtag(self, tag: &str) -> Self {
+        self.with_tag_filter("t", vec![tag.to_string()])
+    }
+    /// Matches notes tagging any of `pubkeys`.
+    pub fn with_p_tag(self, pubkeys: Vec<String>) -> Self {
+        self.with_tag_filter("p", pubkeys)
+    }
+    /// Sets the NIP-50 `search` query.
+    pub fn with_search(mut self, query: &str) -> Self {
+        self.search = Some(query.to_string());
+        self
+    }
 }
 
 #[cfg(test)]
@@ -107,5 +187,184 @@ mod tests {
             })
         );
     }
-}
+    #[test]
+    fn test_filter_search() {
+        let filter = NostrSubscription {
+            kinds: Some(vec![1]),
+            search: Some("bitcoin".to_string()),
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&filter).unwrap();
+        assert!(json.contains(r#""search":"bitcoin""#));
+        assert_eq!(
+            serde_json::to_value(&filter).unwrap(),
+            serde_json::json!({"kinds": [1], "search": "bitcoin"})
+        );
+        let filter = NostrSubscription::default().with_search("best nostr apps");
+        assert_eq!(
+            filter.to_wire_json("search"),
+            r#"["REQ","search",{"search":"best nostr apps"}]"#
+        );
+        let json = serde_json::to_string(&NostrSubscription::default()).unwrap();
+        assert!(!json.contains("search"));
+        let parsed: NostrSubscription =
+            serde_json::from_str(r##"{"search":"purple","#t":["art"]}"##).unwrap();
+        assert_eq!(parsed.search.as_deref(), Some("purple"));
+        assert_eq!(parsed.tags.unwrap()["#t"], vec!["art"]);
+    }
+    #[test]
+    fn test_filter_tag_builders() {
+        let filter = NostrSubscription {
+            kinds: Some(vec![1]),
+            ..Default::default()
+        }
+        .with_hashtag("nostr")
+        .with_hashtag("bitcoin");
+        assert_eq!(
+            filter.to_wire_json("hashtags"),
+            r##"["REQ","hashtags",{"kinds":[1],"#t":["nostr","bitcoin"]}]"##
+        );
 
+        let pubkey = "6e468422dfb74a5738702a8823b9b28168abab8655faacb6853cd0ee15deee93";
+        let filter = NostrSubscription::default().with_p_tag(vec![pubkey.to_string()]);
+        assert_eq!(
+            filter.to_wire_json("mentions"),
+            format!(r##"["REQ","mentions",{{"#p":["{}"]}}]"##, pubkey)
+        );
+
+        let filter = NostrSubscription::default()
+            .with_tag_filter("d", vec!["profile".to_string(), "settings".to_string()])
+            .with_tag_filter("#e", vec!["root".to_string()]);
+        assert_eq!(
+            serde_json::to_value(&filter).unwrap(),
+            serde_json::json!({
+                "#d": ["profile", "settings"],
+                "#e": ["root"]
+            })
+        );
+    }
+    #[test]
+    fn test_multiple_filters() {
+        let profiles = NostrSubscription {
+            kinds: Some(vec![0]),
+            ..Default::default()
+        };
+        let notes = NostrSubscription {
+            kinds: Some(vec![1]),
+            limit: Some(10),
+            ..Default::default()
+        };
+        let subscription = NostrSubscription::single(profiles.clone()).with_additional_filter(notes);
+        assert_eq!(subscription.filters().count(), 2);
+        assert_eq!(
+            subscription.to_wire_json("multi"),
+            r#"["REQ","multi",{"kinds":[0]},{"kinds":[1],"limit":10}]"#
+        );
+        assert_eq!(
+            serde_json::to_value(&subscription).unwrap(),
+            serde_json::json!({"kinds": [0]})
+        );
+        assert_eq!(
+            profiles.to_wire_json("single"),
+            r#"["REQ","single",{"kinds":[0]}]"#
+        );
+    }
+    #[test]
+    fn test_filter_matches() {
+        const ID: &str = "4376c65d2f232afbe9b882a35baa4f6fe8667c4e684749af565f981833ed6a65";
+        const AUTHOR: &str = "6e468422dfb74a5738702a8823b9b28168abab8655faacb6853cd0ee15deee93";
+        const MENTION: &str = "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d";
+        let note: crate::notes::NostrNote = serde_json::from_value(serde_json::json!({
+            "id": ID,
+            "pubkey": AUTHOR,
+            "created_at": 1_700_000_000,
+            "kind": 1,
+            "tags": [["t", "nostr"], ["p", MENTION, "wss://relay.example.com"]],
+            "content": "hello"
+        }))
+        .unwrap();
+        assert!(NostrSubscription::default().matches(&note));
+        let filter = NostrSubscription {
+            ids: Some(vec![ID.to_string()]),
+            authors: Some(vec![AUTHOR.to_string()]),
+            kinds: Some(vec![0, 1]),
+            since: Some(1_700_000_000),
+            until: Some(1_700_000_000),
+            limit: Some(1),
+            ..Default::default()
+        }
+        .with_hashtag("nostr")
+        .with_p_tag(vec![MENTION.to_string()]);
+        assert!(filter.matches(&note));
+
+        let misses = [
+            NostrSubscription {
+                ids: Some(vec![ID[..8].to_string()]),
+                ..Default::default()
+            },
+            NostrSubscription {
+                authors: Some(vec![MENTION.to_string()]),
+                ..Default::default()
+            },
+            NostrSubscription {
+                kinds: Some(vec![7]),
+                ..Default::default()
+            },
+            NostrSubscription {
+                since: Some(1_700_000_001),
+                ..Default::default()
+            },
+            NostrSubscription {
+                until: Some(1_699_999_999),
+                ..Default::default()
+            },
+            NostrSubscription::default().with_hashtag("bitcoin"),
+            NostrSubscription::default().with_tag_filter("e", vec!["nostr".to_string()]),
+        ];
+        for miss in misses {
+            assert!(!miss.matches(&note), "{:?}", miss);
+            // Passing any one of the filters is enough.
+            let either = miss.with_additional_filter(filter.clone());
+            assert!(!either.matches_filter(&note));
+            assert!(either.matches(&note));
+        }
+    }
+    #[test]
+    fn test_filter_for_address() {
+        let address = crate::nips::NostrAddress {
+            identifier: "banana".to_string(),
+            pubkey: "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d".to_string(),
+            kind: 30023,
+            relays: vec!["wss://relay.example.com".to_string()],
+        };
+        assert_eq!(
+            NostrSubscription::for_address(&address).to_wire_json("article"),
+            r##"["REQ","article",{"authors":["3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d"],"kinds":[30023],"#d":["banana"]}]"##
+        );
+    }
+    #[test]
+    fn test_filter_wire_json() {
+        let filter = NostrSubscription {
+            ids: Some(vec!["4376c65d2f232afbe9b882a35baa4f6fe8667c4e684749af565f981833ed6a65".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(
+            filter.to_wire_json("by-id"),
+            r#"["REQ","by-id",{"ids":["4376c65d2f232afbe9b882a35baa4f6fe8667c4e684749af565f981833ed6a65"]}]"#
+        );
+
+        let mut filter = NostrSubscription {
+            authors: Some(vec!["6e468422dfb74a5738702a8823b9b28168abab8655faacb6853cd0ee15deee93".to_string()]),
+            kinds: Some(vec![1, 6]),
+            since: Some(1700000000),
+            until: Some(1700086400),
+            limit: Some(20),
+            ..Default::default()
+        };
+        filter.add_tag("#e", "5c83da77af1dec6d7289834998ad7aafbd9e2191396d75ec3cc27f5a77226f36");
+        assert_eq!(
+            filter.to_wire_json("feed"),
+            r##"["REQ","feed",{"authors":["6e468422dfb74a5738702a8823b9b28168abab8655faacb6853cd0ee15deee93"],"kinds":[1,6],"since":1700000000,"until":1700086400,"limit":20,"#e":["5c83da77af1dec6d7289834998ad7aafbd9e2191396d75ec3cc27f5a77226f36"]}]"##
+        );
+    }
+}
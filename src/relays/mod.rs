@@ -1,11 +1,15 @@
 mod filters; 
 mod relay_connection;
 mod relay_events;
+#[cfg(feature = "nip11")]
+mod relay_info;
 mod pool;
 mod tcp;
 pub use filters::NostrSubscription;
 pub use relay_connection::*;
 pub use relay_events::*;
+#[cfg(feature = "nip11")]
+pub use relay_info::*;
 pub use pool::*;
 pub use tcp::*;
 
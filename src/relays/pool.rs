@@ -1,82 +1,735 @@
-use super::relay_connection::WebsocketStatus;
+use super::relay_connection::{RelayConfig, RelayConnectState, Subscriptions, WebsocketStatus};
 use crate::{
     notes::NostrNote,
-    relays::{NostrRelay, RelayEvent},
+    relays::{CloseEvent, NostrRelay, NostrSubscription, RelayEvent, SubscribeEvent},
 };
 use std::{
     collections::{HashMap, HashSet},
-    sync::Arc,
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 use tokio::{
     select,
     sync::{
-        broadcast::Sender,
-        mpsc::{UnboundedReceiver, UnboundedSender},
-        RwLock,
+        broadcast::{error::RecvError, Sender},
+        mpsc::{Receiver, Sender as EventSender},
+        oneshot, watch, RwLock,
     },
 };
+use tracing::Instrument;
 
-pub type PoolRelayReceiver = UnboundedReceiver<(String, RelayEvent)>;
-pub type PoolRelaySender = UnboundedSender<(String, RelayEvent)>;
+pub type PoolRelayReceiver = Receiver<(String, RelayEvent)>;
+pub type PoolRelaySender = EventSender<(String, RelayEvent)>;
 
 pub type RelayTableMap = HashMap<String, WebsocketStatus>;
-pub type NostrNoteLibrary = HashSet<NostrNote>;
+/// Builds the signed NIP-42 note answering `(relay_url, challenge)`.
+pub type AuthHandler = Arc<dyn Fn(&str, &str) -> NostrNote + Send + Sync>;
+pub type NoticeHandler = Arc<dyn Fn(&str, &str) + Send + Sync>;
+pub type NostrNoteLibrary = lru::LruCache<String, ()>;
+
+/// How long `NostrRelayPool::close` waits for relay tasks to finish.
+pub const DEFAULT_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long `NostrRelayPool::publish` waits for each relay's OK.
+pub const DEFAULT_PUBLISH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Note ids remembered for deduplication when no capacity is given.
+pub const DEFAULT_DEDUP_CAPACITY: usize = 50_000;
+
+fn dedup_cache<V>(capacity: usize) -> lru::LruCache<String, V> {
+    lru::LruCache::new(NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN))
+}
+
+#[derive(Debug)]
+pub enum PoolError {
+    BroadcastFailed(String),
+    CloseFailed(String),
+    TasksStillRunning(usize),
+    InvalidRelay(String),
+    DuplicateRelay(String),
+    UnknownRelay(String),
+    PublishTimedOut(String),
+    /// Only signed notes, which carry an id, can be published.
+    MissingNoteId,
+    /// The relay disconnected for good before it answered.
+    RelayClosed(String),
+}
+impl std::fmt::Display for PoolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PoolError::BroadcastFailed(e) => write!(f, "Failed to send to relay pool: {}", e),
+            PoolError::CloseFailed(e) => write!(f, "Failed to close relay pool: {}", e),
+            PoolError::TasksStillRunning(count) => {
+                write!(f, "{} relay tasks still running after close", count)
+            }
+            PoolError::InvalidRelay(e) => write!(f, "Invalid relay: {}", e),
+            PoolError::DuplicateRelay(url) => write!(f, "Relay {} is already in the pool", url),
+            PoolError::UnknownRelay(url) => write!(f, "Relay {} is not in the pool", url),
+            PoolError::PublishTimedOut(url) => write!(f, "Relay {} did not answer in time", url),
+            PoolError::MissingNoteId => write!(f, "Note has no id"),
+            PoolError::RelayClosed(url) => write!(f, "Relay {} is closed", url),
+        }
+    }
+}
+impl std::error::Error for PoolError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolConfig {
+    /// Outbound messages each relay task can fall behind by before it starts
+    /// skipping them, see `NostrRelayPool::lagged_messages`.
+    pub broadcast_capacity: usize,
+    /// Relay events queued for the pool reader before relay tasks wait for it.
+    pub channel_capacity: usize,
+    /// Most recent note ids the pool remembers to drop duplicates.
+    pub dedup_capacity: usize,
+    /// Timeouts for every relay in the pool, including ones added later.
+    pub relay: RelayConfig,
+    /// Relays that send nothing but keepalive pongs for this long are closed and
+    /// dropped from the pool, see `NostrRelayPool::pool_events`. `None` keeps idle
+    /// relays.
+    pub idle_timeout: Option<Duration>,
+}
+impl Default for PoolConfig {
+    fn default() -> Self {
+        PoolConfig {
+            broadcast_capacity: 256,
+            channel_capacity: 4096,
+            dedup_capacity: DEFAULT_DEDUP_CAPACITY,
+            relay: RelayConfig::default(),
+            idle_timeout: None,
+        }
+    }
+}
+
+/// Changes to the pool itself, rather than events from its relays.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PoolEvent {
+    /// The relay hit `PoolConfig::idle_timeout` and was closed.
+    RelayRemoved(String),
+}
+
+struct PoolEvents {
+    sender: tokio::sync::mpsc::UnboundedSender<PoolEvent>,
+    receiver: tokio::sync::mpsc::UnboundedReceiver<PoolEvent>,
+}
+impl Default for PoolEvents {
+    fn default() -> Self {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        PoolEvents { sender, receiver }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct PublishReport {
+    pub confirmed: Vec<String>,
+    pub rejected: Vec<(String, String)>,
+    pub timed_out: Vec<String>,
+    /// OKs for the note beyond each relay's first answer. Every other event
+    /// stays in the pool reader.
+    pub other_events: Vec<(String, RelayEvent)>,
+}
+
+/// An open pool subscription, returned by `NostrRelayPool::subscribe`.
+/// Dropping it sends `CLOSE` for the subscription to every relay.
+#[must_use = "dropping the handle closes the subscription"]
+pub struct SubscriptionHandle {
+    id: String,
+    // Weak, so an outstanding handle does not keep the relay tasks alive.
+    broadcaster: tokio::sync::broadcast::WeakSender<crate::relays::WebSocketMessage>,
+    subscriptions: Subscriptions,
+}
+impl SubscriptionHandle {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    /// Notes `pool` receives for this subscription, each once. Notes already
+    /// queued in `reader` come from there and other events queued with them are
+    /// dropped; later notes are routed straight to the stream.
+    pub fn stream<'a>(
+        &self,
+        pool: &'a mut NostrRelayPool,
+    ) -> impl futures_util::Stream<Item = NostrNote> + 'a {
+        let subscription_id = self.id.clone();
+        let mut route = pool
+            .routes
+            .claim(RouteKey::Subscription(subscription_id.clone()));
+        let routed = futures_util::stream::poll_fn(move |cx| route.poll_recv(cx));
+        let events = futures_util::stream::select(pool.event_stream(), routed);
+        let mut seen = HashSet::new();
+        futures_util::StreamExt::filter_map(events, move |(_, event)| {
+            futures_util::future::ready(match event {
+                RelayEvent::NewNote((_, id, note))
+                    if id == subscription_id
+                        && note.id.as_ref().is_none_or(|id| seen.insert(id.clone())) =>
+                {
+                    Some(note)
+                }
+                _ => None,
+            })
+        })
+    }
+}
+impl Drop for SubscriptionHandle {
+    fn drop(&mut self) {
+        let close: crate::relays::WebSocketMessage = CloseEvent::from(self.id.clone()).into();
+        if let Some(broadcaster) = self.broadcaster.upgrade() {
+            let _ = broadcaster.send(close.clone());
+        }
+        let subscriptions = self.subscriptions.clone();
+        crate::relays::spawn_thread(async move {
+            subscriptions.track(&close).await;
+        });
+    }
+}
+
+/// Traffic through one relay's pool task, see `NostrRelayPool::relay_stats`.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RelayStats {
+    pub connected: bool,
+    pub sent_count: u64,
+    pub received_count: u64,
+    pub last_event_at: Option<std::time::Instant>,
+    /// Round trip of the relay's last answered keepalive ping.
+    pub ping_latency_ms: Option<u64>,
+}
+
+// Updated by a relay's task, `Instant` is native only.
+#[derive(Default)]
+struct RelayCounters {
+    sent: AtomicU64,
+    received: AtomicU64,
+    #[cfg(not(target_arch = "wasm32"))]
+    last_event_at: std::sync::Mutex<Option<std::time::Instant>>,
+}
+impl RelayCounters {
+    fn sent(&self) {
+        self.sent.fetch_add(1, Ordering::SeqCst);
+    }
+    fn received(&self) {
+        self.received.fetch_add(1, Ordering::SeqCst);
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Ok(mut last_event_at) = self.last_event_at.lock() {
+            *last_event_at = Some(std::time::Instant::now());
+        }
+    }
+}
 
+/// Remembers the ids of the most recently seen notes, up to its capacity.
+///
+/// Once an id is evicted, a relay sending that note again gets it through as
+/// new, so the capacity trades memory for how far back duplicates are caught.
 #[derive(Clone)]
 pub struct NoteLibrary(pub Arc<RwLock<NostrNoteLibrary>>);
+impl Default for NoteLibrary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl NoteLibrary {
     pub fn new() -> Self {
-        Self(Arc::new(RwLock::new(HashSet::new())))
+        Self::with_capacity(DEFAULT_DEDUP_CAPACITY)
+    }
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self(Arc::new(RwLock::new(dedup_cache(capacity))))
+    }
+    pub async fn insert(&self, note: NostrNote) -> bool {
+        let id = note.id.unwrap_or_default();
+        let mut library = self.0.write().await;
+        if library.get(&id).is_some() {
+            return false;
+        }
+        library.put(id, ());
+        true
+    }
+}
+
+// What an in-flight pool call waits on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum RouteKey {
+    /// Notes, EOSE and CLOSED for a subscription id.
+    Subscription(String),
+    /// OKs for a note id.
+    Ok(String),
+}
+impl RouteKey {
+    fn of(event: &RelayEvent) -> Option<Self> {
+        match event {
+            RelayEvent::NewNote((_, id, _))
+            | RelayEvent::EndOfSubscription((_, id))
+            | RelayEvent::ClosedSubscription((_, id)) => Some(RouteKey::Subscription(id.clone())),
+            RelayEvent::SentOk((_, id, _, _)) => Some(RouteKey::Ok(id.clone())),
+            _ => None,
+        }
+    }
+}
+
+type RouteSender = tokio::sync::mpsc::UnboundedSender<(String, RelayEvent)>;
+
+/// Events claimed by pool calls such as `subscribe_until_eose` and `publish`.
+/// Relay tasks hand those to the call instead of `reader`, so the call never
+/// reads, and drops, events meant for anyone else. Claimed notes skip the pool's
+/// note library and are deduplicated per route instead, so a call also gets
+/// notes the pool already received for another subscription.
+#[derive(Clone, Default)]
+struct EventRoutes(Arc<std::sync::RwLock<HashMap<RouteKey, RouteSender>>>);
+impl EventRoutes {
+    fn claim(&self, key: RouteKey) -> EventRoute {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        if let Ok(mut routes) = self.0.write() {
+            routes.insert(key.clone(), sender);
+        }
+        EventRoute {
+            key,
+            routes: self.clone(),
+            receiver,
+            seen: None,
+        }
+    }
+    /// Hands `event` to the call that claimed it, or gives it back for `reader`.
+    fn route(&self, relay_url: &str, event: RelayEvent) -> Option<RelayEvent> {
+        let Some(key) = RouteKey::of(&event) else {
+            return Some(event);
+        };
+        let Ok(routes) = self.0.read() else {
+            return Some(event);
+        };
+        match routes.get(&key) {
+            Some(sender) => sender
+                .send((relay_url.to_string(), event))
+                .err()
+                .map(|returned| returned.0 .1),
+            None => Some(event),
+        }
+    }
+}
+
+/// Receives the events for one `RouteKey` until dropped, each note once.
+struct EventRoute {
+    key: RouteKey,
+    routes: EventRoutes,
+    receiver: tokio::sync::mpsc::UnboundedReceiver<(String, RelayEvent)>,
+    // Created with the first note, so routes for OKs never allocate one.
+    seen: Option<NostrNoteLibrary>,
+}
+impl EventRoute {
+    async fn recv(&mut self) -> Option<(String, RelayEvent)> {
+        futures_util::future::poll_fn(|cx| self.poll_recv(cx)).await
+    }
+    fn poll_recv(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<(String, RelayEvent)>> {
+        loop {
+            let Some((relay_url, event)) = std::task::ready!(self.receiver.poll_recv(cx)) else {
+                return std::task::Poll::Ready(None);
+            };
+            if let RelayEvent::NewNote((_, _, NostrNote { id: Some(id), .. })) = &event {
+                let seen = self.seen.get_or_insert_with(lru::LruCache::unbounded);
+                if seen.put(id.clone(), ()).is_some() {
+                    continue;
+                }
+                if seen.len() > DEFAULT_DEDUP_CAPACITY {
+                    seen.pop_lru();
+                }
+            }
+            return std::task::Poll::Ready(Some((relay_url, event)));
+        }
+    }
+}
+impl Drop for EventRoute {
+    fn drop(&mut self) {
+        if let Ok(mut routes) = self.routes.0.write() {
+            routes.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(feature = "nip40")]
+pub type ExpiringNostrNoteLibrary = lru::LruCache<String, Option<u64>>;
+// Stored ids by expiration, soonest first.
+#[cfg(feature = "nip40")]
+type ExpirationQueue = std::collections::BinaryHeap<std::cmp::Reverse<(u64, String)>>;
+
+// When an `ExpiringNoteLibrary`'s notes expire, and the one task removing them.
+#[cfg(feature = "nip40")]
+#[derive(Default)]
+struct ExpirySchedule {
+    queue: std::sync::Mutex<ExpirationQueue>,
+    // Wakes the sweeper for a note expiring before the one it sleeps on.
+    sooner: tokio::sync::Notify,
+    // Set while the sweeper runs, which is while `queue` holds anything.
+    sweeping: std::sync::atomic::AtomicBool,
+}
+
+/// Deduplicates notes by id like `NoteLibrary`, but refuses notes whose NIP-40
+/// `expiration` has passed and forgets stored notes once they expire. Notes
+/// without an id are refused too.
+/// Holds at most its capacity of ids, evicting the least recently seen ones.
+#[cfg(feature = "nip40")]
+#[derive(Clone)]
+pub struct ExpiringNoteLibrary(
+    pub Arc<RwLock<ExpiringNostrNoteLibrary>>,
+    Arc<ExpirySchedule>,
+);
+#[cfg(feature = "nip40")]
+impl Default for ExpiringNoteLibrary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+#[cfg(feature = "nip40")]
+impl ExpiringNoteLibrary {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_DEDUP_CAPACITY)
+    }
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self(
+            Arc::new(RwLock::new(dedup_cache(capacity))),
+            Arc::new(ExpirySchedule::default()),
+        )
     }
     pub async fn insert(&self, note: NostrNote) -> bool {
+        let Some(id) = note.id else {
+            return false;
+        };
+        let expiration = note
+            .tags
+            .expiration()
+            .and_then(|expiration| u64::try_from(expiration).ok());
+        let now = crate::notes::SystemClock::current().max(0) as u64;
         let mut library = self.0.write().await;
-        library.insert(note)
+        let Ok(mut queue) = self.1.queue.lock() else {
+            return false;
+        };
+        Self::forget_expired(&mut library, &mut queue, now);
+        if expiration.is_some_and(|expiration| expiration <= now) {
+            return false;
+        }
+        if library.get(&id).is_some() {
+            return false;
+        }
+        library.put(id.clone(), expiration);
+        if let Some(expiration) = expiration {
+            let sooner = queue
+                .peek()
+                .is_none_or(|std::cmp::Reverse((next, _))| expiration < *next);
+            queue.push(std::cmp::Reverse((expiration, id)));
+            // Ids evicted from the library would otherwise stay queued until
+            // they expire.
+            if queue.len() > library.cap().get() * 2 {
+                queue.retain(|std::cmp::Reverse((_, id))| library.contains(id));
+            }
+            if !self.1.sweeping.swap(true, Ordering::SeqCst) {
+                crate::relays::spawn_thread(Self::sweep(
+                    Arc::downgrade(&self.0),
+                    Arc::downgrade(&self.1),
+                ));
+            } else if sooner {
+                self.1.sooner.notify_one();
+            }
+        }
+        true
+    }
+    // Pops every queued id expiring at or before `now` and forgets it, unless it
+    // was evicted and stored again since.
+    fn forget_expired(
+        library: &mut ExpiringNostrNoteLibrary,
+        queue: &mut ExpirationQueue,
+        now: u64,
+    ) {
+        while queue
+            .peek()
+            .is_some_and(|std::cmp::Reverse((expiration, _))| *expiration <= now)
+        {
+            if let Some(std::cmp::Reverse((expiration, id))) = queue.pop() {
+                if library.peek(&id) == Some(&Some(expiration)) {
+                    library.pop(&id);
+                }
+            }
+        }
+    }
+    // Sleeps until the next queued expiration and forgets the notes due then.
+    // Holds the library weakly, and returns once it is dropped or nothing is queued.
+    async fn sweep(
+        library: std::sync::Weak<RwLock<ExpiringNostrNoteLibrary>>,
+        schedule: std::sync::Weak<ExpirySchedule>,
+    ) {
+        // The last expiration slept until, so the clock lagging behind does not
+        // stretch the next sleep.
+        let mut swept_until = 0;
+        loop {
+            let Some(schedule) = schedule.upgrade() else {
+                return;
+            };
+            let next = {
+                let Ok(queue) = schedule.queue.lock() else {
+                    return;
+                };
+                let next = queue.peek().map(|std::cmp::Reverse((next, _))| *next);
+                if next.is_none() {
+                    schedule.sweeping.store(false, Ordering::SeqCst);
+                }
+                next
+            };
+            let Some(next) = next else {
+                return;
+            };
+            let now = (crate::notes::SystemClock::current().max(0) as u64).max(swept_until);
+            select! {
+                _ = crate::relays::sleep(Duration::from_secs(next.saturating_sub(now))) => {}
+                _ = schedule.sooner.notified() => continue,
+            }
+            let Some(library) = library.upgrade() else {
+                return;
+            };
+            let mut library = library.write().await;
+            if let Ok(mut queue) = schedule.queue.lock() {
+                swept_until = now.max(next);
+                Self::forget_expired(&mut library, &mut queue, swept_until);
+            };
+        }
     }
 }
+#[cfg(feature = "nip40")]
+type PoolNoteLibrary = ExpiringNoteLibrary;
+#[cfg(not(feature = "nip40"))]
+type PoolNoteLibrary = NoteLibrary;
 
 pub struct NostrRelayPool {
     pub relays: Vec<NostrRelay>,
     pub reader: PoolRelayReceiver,
     pub broadcaster: Sender<crate::relays::WebSocketMessage>,
+    lagged: Arc<AtomicUsize>,
+    shutdown: watch::Sender<bool>,
+    /// Resolve once their relay task has returned, on native and WASM alike.
+    tasks: Vec<oneshot::Receiver<()>>,
+    // Kept to start tasks for relays added later.
+    library: PoolNoteLibrary,
+    events: PoolRelaySender,
+    on_auth: Option<AuthHandler>,
+    // Shared with the relay tasks so `on_notice` also reaches relays already running.
+    on_notice: Arc<std::sync::RwLock<Option<NoticeHandler>>>,
+    subscriptions: Subscriptions,
+    counters: HashMap<String, Arc<RelayCounters>>,
+    config: PoolConfig,
+    pool_events: PoolEvents,
+    routes: EventRoutes,
 }
 
 impl NostrRelayPool {
-    pub async fn new(urls: Vec<String>) -> anyhow::Result<Self> {
-        let library = NoteLibrary::new();
+    pub async fn new(urls: impl IntoIterator<Item = impl AsRef<str>>) -> anyhow::Result<Self> {
+        Self::new_with_progress(urls, |_, _, _| {}).await
+    }
+    pub async fn new_with_config(
+        urls: impl IntoIterator<Item = impl AsRef<str>>,
+        config: PoolConfig,
+    ) -> anyhow::Result<Self> {
+        Self::build(urls, config, |_, _, _| {}, None)
+    }
+    /// Like `new`, with `config` applied to every relay.
+    pub async fn with_config(
+        urls: impl IntoIterator<Item = impl AsRef<str>>,
+        config: RelayConfig,
+    ) -> anyhow::Result<Self> {
+        let config = PoolConfig {
+            relay: config,
+            ..Default::default()
+        };
+        Self::build(urls, config, |_, _, _| {}, None)
+    }
+    /// Like `new`, but answers every relay `AUTH` challenge with the note
+    /// `on_auth(relay_url, challenge)` returns, see `Nip42::auth_note`.
+    pub async fn new_with_auth(
+        urls: impl IntoIterator<Item = impl AsRef<str>>,
+        on_auth: impl Fn(&str, &str) -> NostrNote + Send + Sync + 'static,
+    ) -> anyhow::Result<Self> {
+        Self::build(
+            urls,
+            PoolConfig::default(),
+            |_, _, _| {},
+            Some(Arc::new(on_auth)),
+        )
+    }
+    /// Like `new`, but remembers at most `capacity` note ids for deduplication.
+    pub async fn with_dedup_capacity(
+        urls: impl IntoIterator<Item = impl AsRef<str>>,
+        capacity: usize,
+    ) -> anyhow::Result<Self> {
+        let config = PoolConfig {
+            dedup_capacity: capacity,
+            ..Default::default()
+        };
+        Self::build(urls, config, |_, _, _| {}, None)
+    }
+    /// Like `new`, but calls `on_progress(connected_count, total_count, relay_url)`
+    /// each time one of the relays finishes opening.
+    pub async fn new_with_progress(
+        urls: impl IntoIterator<Item = impl AsRef<str>>,
+        on_progress: impl Fn(usize, usize, &str) + Send + Sync + 'static,
+    ) -> anyhow::Result<Self> {
+        Self::build(urls, PoolConfig::default(), on_progress, None)
+    }
+    fn build(
+        urls: impl IntoIterator<Item = impl AsRef<str>>,
+        config: PoolConfig,
+        on_progress: impl Fn(usize, usize, &str) + Send + Sync + 'static,
+        on_auth: Option<AuthHandler>,
+    ) -> anyhow::Result<Self> {
+        let library = PoolNoteLibrary::with_capacity(config.dedup_capacity);
         let relays = urls
             .into_iter()
-            .filter_map(|url| NostrRelay::new(&url).ok())
+            .filter_map(|url| NostrRelay::with_config(url.as_ref(), config.relay).ok())
             .collect::<Vec<_>>();
-        let (in_tx, in_rx) = tokio::sync::mpsc::unbounded_channel();
-        let (broadcast_tx, _) = tokio::sync::broadcast::channel(16);
+        let total = relays.len();
+        let connected = Arc::new(AtomicUsize::new(0));
+        let on_progress = Arc::new(on_progress);
+        for relay in &relays {
+            let relay = relay.clone();
+            let connected = connected.clone();
+            let on_progress = on_progress.clone();
+            crate::relays::spawn_thread(async move {
+                if relay.wait_for_open().await.is_ok() {
+                    let count = connected.fetch_add(1, Ordering::SeqCst) + 1;
+                    on_progress(count, total, &relay.url);
+                }
+            });
+        }
+        let (in_tx, in_rx) = tokio::sync::mpsc::channel(config.channel_capacity);
+        let (broadcast_tx, _) = tokio::sync::broadcast::channel(config.broadcast_capacity);
+        let lagged = Arc::new(AtomicUsize::new(0));
 
-        let broadcast_tx_clone = broadcast_tx.clone();
-        let relay_tasks = relays
-            .iter()
-            .map(move |relay| {
-                Box::pin(NostrRelayPool::process_relay_events(
-                    library.clone(),
-                    relay.clone(),
-                    in_tx.clone(),
-                    broadcast_tx_clone.subscribe(),
-                ))
-            })
-            .collect::<Vec<_>>();
-        crate::relays::spawn_thread(async move {
-            let _ = futures_util::future::select_ok(relay_tasks).await;
-        });
-        Ok(Self {
-            relays,
+        let (shutdown, _) = watch::channel(false);
+        let mut pool = Self {
+            relays: Vec::with_capacity(relays.len()),
             reader: in_rx,
             broadcaster: broadcast_tx,
-        })
+            lagged,
+            shutdown,
+            tasks: Vec::with_capacity(relays.len()),
+            library,
+            events: in_tx,
+            on_auth,
+            on_notice: Default::default(),
+            subscriptions: Subscriptions::default(),
+            counters: HashMap::new(),
+            config,
+            pool_events: PoolEvents::default(),
+            routes: EventRoutes::default(),
+        };
+        for relay in relays {
+            pool.spawn_relay_task(&relay);
+            pool.relays.push(relay);
+        }
+        Ok(pool)
+    }
+    fn spawn_relay_task(&mut self, relay: &NostrRelay) {
+        let span = tracing::info_span!("relay_connection", url = %relay.url);
+        let counters = Arc::new(RelayCounters::default());
+        self.counters.insert(relay.url.clone(), counters.clone());
+        let relay_task = NostrRelayPool::process_relay_events(
+            self.library.clone(),
+            relay.clone(),
+            counters,
+            self.events.clone(),
+            self.broadcaster.subscribe(),
+            self.lagged.clone(),
+            self.shutdown.subscribe(),
+            self.on_auth.clone(),
+            self.on_notice.clone(),
+            self.config.idle_timeout,
+            self.pool_events.sender.clone(),
+            self.routes.clone(),
+        )
+        .instrument(span);
+        let (done_tx, done_rx) = oneshot::channel();
+        crate::relays::spawn_thread(async move {
+            let _ = relay_task.await;
+            let _ = done_tx.send(());
+        });
+        self.tasks.push(done_rx);
+    }
+    /// Calls `on_notice(relay_url, notice)` for every relay `NOTICE`, replacing any
+    /// previous callback. The notices still reach the pool reader as well.
+    pub fn on_notice(&self, on_notice: impl Fn(&str, &str) + Send + Sync + 'static) {
+        if let Ok(mut handler) = self.on_notice.write() {
+            *handler = Some(Arc::new(on_notice));
+        }
+    }
+    /// Connects to `url` and sends it every subscription the pool still has open.
+    pub async fn add_relay(&mut self, url: &str) -> Result<(), PoolError> {
+        // Relays dropped for being idle may come back.
+        self.relays.retain(|relay| !relay.is_closed());
+        if self.relays.iter().any(|relay| relay.url == url) {
+            return Err(PoolError::DuplicateRelay(url.to_string()));
+        }
+        let relay = NostrRelay::with_config(url, self.config.relay)
+            .map_err(|e| PoolError::InvalidRelay(e.to_string()))?;
+        let requests = self.subscriptions.requests().await;
+        self.spawn_relay_task(&relay);
+        let resubscriber = relay.clone();
+        crate::relays::spawn_thread(async move {
+            for request in requests {
+                if let Err(e) = resubscriber.send_to_relay(request).await {
+                    tracing::warn!("Failed to subscribe new relay {}: {}", resubscriber.url, e);
+                    break;
+                }
+            }
+        });
+        self.relays.push(relay);
+        tracing::debug!(%url, "Relay added to pool");
+        Ok(())
+    }
+    /// Sends `CLOSE` for the relay's open subscriptions, then disconnects it.
+    /// Its task stops once the socket closes, so no more of its notes reach the pool.
+    pub async fn remove_relay(&mut self, url: &str) -> Result<(), PoolError> {
+        let index = self
+            .relays
+            .iter()
+            .position(|relay| relay.url == url)
+            .ok_or_else(|| PoolError::UnknownRelay(url.to_string()))?;
+        let relay = self.relays.remove(index);
+        self.counters.remove(url);
+        // A relay that is still (re)connecting holds no subscriptions to close.
+        if relay.state().await == WebsocketStatus::Open {
+            for subscription_id in relay.subscription_ids().await {
+                let close: crate::relays::WebSocketMessage =
+                    CloseEvent::from(subscription_id).into();
+                if let Err(e) = relay.send_to_relay(close).await {
+                    tracing::warn!("Failed to close subscription on {}: {}", url, e);
+                    break;
+                }
+            }
+        }
+        relay.close().await;
+        tracing::debug!(%url, "Relay removed from pool");
+        Ok(())
     }
+    #[allow(clippy::too_many_arguments)]
     async fn process_relay_events(
-        notes: NoteLibrary,
+        notes: PoolNoteLibrary,
         relay: NostrRelay,
+        counters: Arc<RelayCounters>,
         event_writer: PoolRelaySender,
         mut broadcast_rx: tokio::sync::broadcast::Receiver<crate::relays::WebSocketMessage>,
+        lagged: Arc<AtomicUsize>,
+        mut shutdown: watch::Receiver<bool>,
+        on_auth: Option<AuthHandler>,
+        on_notice: Arc<std::sync::RwLock<Option<NoticeHandler>>>,
+        idle_timeout: Option<Duration>,
+        pool_events: tokio::sync::mpsc::UnboundedSender<PoolEvent>,
+        routes: EventRoutes,
     ) -> anyhow::Result<()> {
+        // Re-armed by every event but pings, so a relay is dropped once it has been
+        // quiet for the whole timeout.
+        let idle_after = idle_timeout.unwrap_or(Duration::from_secs(3600));
+        let mut idle_deadline = Box::pin(crate::relays::sleep(idle_after));
+        let mut idle = false;
         loop {
             if let WebsocketStatus::Closed(e) = relay.state().await {
                 tracing::error!("Relay disconnected: {}", e);
@@ -86,17 +739,65 @@ impl NostrRelayPool {
                 event = relay.next_relay_event() => {
                     match event {
                         Some(event) => {
+                            counters.received();
+                            // Keepalive pongs arrive as pings and do not make a relay active.
+                            if event != RelayEvent::Ping && idle_timeout.is_some() {
+                                idle_deadline.set(crate::relays::sleep(idle_after));
+                            }
                             match event {
                                 RelayEvent::NewNote((_, _, ref note)) => {
-                                    if notes.insert(note.clone()).await {
-                                        if let Err(e) = event_writer.send((relay.url.clone(), event)) {
-                                            tracing::error!("Failed to send event: {:?}", e);
+                                    tracing::debug!(id = ?note.id, "Note received");
+                                    // Routed before the library sees it, claimed notes are
+                                    // deduplicated by their route.
+                                    let Some(event) = routes.route(&relay.url, event) else {
+                                        continue;
+                                    };
+                                    if let RelayEvent::NewNote((_, _, ref note)) = event {
+                                        if !notes.insert(note.clone()).await {
+                                            tracing::warn!(id = ?note.id, "Dropped duplicate note");
+                                            continue;
+                                        }
+                                    }
+                                    if let Err(e) = event_writer.send((relay.url.clone(), event)).await {
+                                        tracing::error!("Failed to send event: {:?}", e);
+                                        break;
+                                    }
+                                }
+                                RelayEvent::Auth((_, ref challenge)) if on_auth.is_some() => {
+                                    tracing::debug!("Answering auth challenge");
+                                    let note = on_auth.as_ref().map(|on_auth| on_auth(&relay.url, challenge));
+                                    if let Some(note) = note {
+                                        let auth: crate::relays::AuthEvent = note.into();
+                                        if let Err(e) = relay.send_to_relay(auth.into()).await {
+                                            tracing::error!("Failed to send auth to relay {}: {:?}", relay.url, e);
                                             break;
                                         }
+                                        counters.sent();
+                                    }
+                                    if let Err(e) = event_writer.send((relay.url.clone(), event)).await {
+                                        tracing::error!("Failed to send event: {:?}", e);
+                                        break;
+                                    }
+                                }
+                                RelayEvent::Notice((_, ref notice)) => {
+                                    tracing::info!(%notice, "Relay notice received");
+                                    let handler = on_notice.read().ok().and_then(|handler| handler.clone());
+                                    if let Some(handler) = handler {
+                                        handler(&relay.url, notice);
+                                    }
+                                    if let Err(e) = event_writer.send((relay.url.clone(), event)).await {
+                                        tracing::error!("Failed to send event: {:?}", e);
+                                        break;
                                     }
                                 }
                                 _ => {
-                                    if let Err(e) = event_writer.send((relay.url.clone(), event)) {
+                                    if let RelayEvent::EndOfSubscription((_, ref subscription_id)) = event {
+                                        tracing::debug!(%subscription_id, "End of stored events received");
+                                    }
+                                    let Some(event) = routes.route(&relay.url, event) else {
+                                        continue;
+                                    };
+                                    if let Err(e) = event_writer.send((relay.url.clone(), event)).await {
                                         tracing::error!("Failed to send event: {:?}", e);
                                         break;
                                     }
@@ -104,48 +805,406 @@ impl NostrRelayPool {
                             }
                         }
                         None => {
+                            tracing::debug!("Relay stream ended");
                             break;
                         }
                     }
                 }
                 note = broadcast_rx.recv() => {
-                    if let Ok(note) = note {
-                        if let Err(e) = relay.send_to_relay(note).await {
-                            tracing::error!("Failed to send note to relay {}: {:?}", relay.url, e);
-                            break;
+                    match note {
+                        Ok(note) => {
+                            if let Err(e) = relay.send_to_relay(note).await {
+                                tracing::error!("Failed to send note to relay {}: {:?}", relay.url, e);
+                                break;
+                            }
+                            counters.sent();
+                            tracing::debug!("Message sent to relay");
+                        }
+                        Err(RecvError::Lagged(skipped)) => {
+                            tracing::warn!("Relay {} skipped {} lagged messages", relay.url, skipped);
+                            lagged.fetch_add(skipped as usize, Ordering::SeqCst);
+                            if let Err(e) = event_writer.send((relay.url.clone(), RelayEvent::Lag(skipped))).await {
+                                tracing::error!("Failed to send event: {:?}", e);
+                                break;
+                            }
                         }
+                        Err(RecvError::Closed) => break,
                     }
                 }
+                _ = &mut idle_deadline, if idle_timeout.is_some() => {
+                    tracing::warn!("Relay idle for {:?}, removing it", idle_timeout);
+                    idle = true;
+                    break;
+                }
+                _ = shutdown.changed() => {
+                    tracing::debug!("Relay pool shutting down");
+                    break;
+                }
                 else => {
                     break;
                 }
             }
         }
+        let url = relay.url.clone();
         relay.close().await;
+        if idle {
+            let _ = pool_events.send(PoolEvent::RelayRemoved(url));
+        }
+        tracing::debug!("Relay disconnected");
         Err(anyhow::anyhow!("Relay closed"))
     }
+    /// Urls of the relays in the pool, leaving out ones closed for being idle.
+    pub fn relay_urls(&self) -> Vec<String> {
+        self.relays
+            .iter()
+            .filter(|relay| !relay.is_closed())
+            .map(|relay| relay.url.clone())
+            .collect()
+    }
+    /// `None` when `url` is not in the pool.
+    pub async fn relay_status(&self, url: &str) -> Option<RelayConnectState> {
+        let relay = self.relays.iter().find(|relay| relay.url == url)?;
+        Some(relay.connect_state().await)
+    }
+    /// Messages sent to and events received from each relay since it joined the pool.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn relay_stats(&self) -> HashMap<String, RelayStats> {
+        let mut stats = HashMap::with_capacity(self.relays.len());
+        for relay in &self.relays {
+            let Some(counters) = self.counters.get(&relay.url) else {
+                continue;
+            };
+            stats.insert(
+                relay.url.clone(),
+                RelayStats {
+                    connected: relay.state().await == WebsocketStatus::Open,
+                    sent_count: counters.sent.load(Ordering::SeqCst),
+                    received_count: counters.received.load(Ordering::SeqCst),
+                    last_event_at: counters.last_event_at.lock().ok().and_then(|last| *last),
+                    ping_latency_ms: relay
+                        .ping_latency()
+                        .map(|latency| latency.as_millis() as u64),
+                },
+            );
+        }
+        stats
+    }
+    /// Total outbound messages relay tasks skipped because they fell more than
+    /// `PoolConfig::broadcast_capacity` messages behind.
+    pub fn lagged_messages(&self) -> usize {
+        self.lagged.load(Ordering::SeqCst)
+    }
     pub async fn send_to_relay(
         &self,
         signed_note: crate::relays::WebSocketMessage,
     ) -> anyhow::Result<()> {
+        self.subscriptions.track(&signed_note).await;
         if let Err(e) = self.broadcaster.send(signed_note) {
             tracing::error!("Failed to send note to relay pool: {:?}", e);
         }
         Ok(())
     }
-    pub async fn close(mut self) -> anyhow::Result<()> {
+    /// Sends `subscription` to every relay, keeping it open until the handle drops.
+    pub async fn subscribe(
+        &self,
+        subscription: &NostrSubscription,
+    ) -> Result<SubscriptionHandle, PoolError> {
+        self.send_subscription(subscription.clone().into()).await
+    }
+    async fn send_subscription(
+        &self,
+        subscribe_event: SubscribeEvent,
+    ) -> Result<SubscriptionHandle, PoolError> {
+        let subscription_id = subscribe_event.1.clone();
+        let request: crate::relays::WebSocketMessage = subscribe_event.into();
+        self.subscriptions.track(&request).await;
+        self.broadcaster
+            .send(request)
+            .map_err(|e| PoolError::BroadcastFailed(e.to_string()))?;
+        tracing::debug!(%subscription_id, "Subscription sent");
+        Ok(SubscriptionHandle {
+            id: subscription_id,
+            broadcaster: self.broadcaster.downgrade(),
+            subscriptions: self.subscriptions.clone(),
+        })
+    }
+    /// Subscribes with `subscription` and collects its notes until the first relay
+    /// sends EOSE or `timeout` runs out, then closes the subscription. Notes come back
+    /// deduplicated, newest first; events for other subscriptions stay in `reader`.
+    pub async fn subscribe_until_eose(
+        &mut self,
+        subscription: &NostrSubscription,
+        timeout: Duration,
+    ) -> Result<Vec<NostrNote>, PoolError> {
+        let subscribe_event: SubscribeEvent = subscription.clone().into();
+        let subscription_id = subscribe_event.1.clone();
+        // Claimed before the REQ goes out so no reply can reach `reader` first.
+        let mut route = self
+            .routes
+            .claim(RouteKey::Subscription(subscription_id.clone()));
+        let handle = self.send_subscription(subscribe_event).await?;
+        let mut notes = vec![];
+        let deadline = crate::relays::sleep(timeout);
+        tokio::pin!(deadline);
+        loop {
+            select! {
+                event = route.recv() => {
+                    match event {
+                        Some((_, RelayEvent::NewNote((_, _, note)))) => notes.push(note),
+                        Some((_, RelayEvent::EndOfSubscription(_))) => break,
+                        Some(_) => {}
+                        None => break,
+                    }
+                }
+                _ = &mut deadline => {
+                    tracing::debug!(%subscription_id, "Timed out waiting for EOSE");
+                    break;
+                }
+            }
+        }
+        drop(route);
+        drop(handle);
+        notes.sort_by_key(|note| std::cmp::Reverse(note.created_at));
+        Ok(notes)
+    }
+    /// Events from every relay as a `Stream`, like `reader.recv()` in a loop; it ends
+    /// once all relay tasks have stopped.
+    pub fn event_stream(&mut self) -> impl futures_util::Stream<Item = (String, RelayEvent)> + '_ {
+        futures_util::stream::poll_fn(move |cx| self.reader.poll_recv(cx))
+    }
+    /// Changes to the pool, such as relays removed for being idle.
+    pub fn pool_events(&mut self) -> impl futures_util::Stream<Item = PoolEvent> + '_ {
+        futures_util::stream::poll_fn(move |cx| self.pool_events.receiver.poll_recv(cx))
+    }
+    /// Notes from every subscription, with the url of the relay that sent each.
+    pub fn note_stream(&mut self) -> impl futures_util::Stream<Item = (String, NostrNote)> + '_ {
+        futures_util::StreamExt::filter_map(self.event_stream(), |(relay_url, event)| {
+            futures_util::future::ready(match event {
+                RelayEvent::NewNote((_, _, note)) => Some((relay_url, note)),
+                _ => None,
+            })
+        })
+    }
+    /// Subscribes with `filter` and streams its notes, skipping any that none of its
+    /// filters match, see `NostrSubscription::matches`. Events for other
+    /// subscriptions stay in `reader`.
+    pub async fn note_stream_filtered(
+        &mut self,
+        filter: NostrSubscription,
+    ) -> Result<impl futures_util::Stream<Item = NostrNote> + '_, PoolError> {
+        let subscribe_event: SubscribeEvent = filter.clone().into();
+        let route = self
+            .routes
+            .claim(RouteKey::Subscription(subscribe_event.1.clone()));
+        // Moved into the stream, so dropping the stream closes the subscription.
+        let handle = self.send_subscription(subscribe_event).await?;
+        let events =
+            futures_util::stream::unfold((route, handle), |(mut route, handle)| async move {
+                let event = route.recv().await?;
+                Some((event, (route, handle)))
+            });
+        Ok(futures_util::StreamExt::filter_map(
+            events,
+            move |(_, event)| {
+                futures_util::future::ready(match event {
+                    RelayEvent::NewNote((_, _, note)) if filter.matches(&note) => Some(note),
+                    _ => None,
+                })
+            },
+        ))
+    }
+    /// Sends a signed note to every relay and waits up to `timeout` for each of
+    /// them to answer with an OK for its id.
+    pub async fn publish_and_collect_ok(
+        &mut self,
+        note: NostrNote,
+        timeout: Duration,
+    ) -> Result<PublishReport, PoolError> {
+        let note_id = note.id.clone().ok_or(PoolError::MissingNoteId)?;
+        let mut pending = self
+            .relays
+            .iter()
+            .map(|relay| relay.url.clone())
+            .collect::<HashSet<_>>();
+        let mut route = self.routes.claim(RouteKey::Ok(note_id));
+        self.broadcaster
+            .send(note.into())
+            .map_err(|e| PoolError::BroadcastFailed(e.to_string()))?;
+        let mut report = PublishReport::default();
+        let deadline = crate::relays::sleep(timeout);
+        tokio::pin!(deadline);
+        while !pending.is_empty() {
+            select! {
+                event = route.recv() => {
+                    let Some((relay_url, event)) = event else {
+                        break;
+                    };
+                    match event {
+                        RelayEvent::SentOk((_, _, accepted, ref message))
+                            if pending.remove(&relay_url) =>
+                        {
+                            if accepted {
+                                report.confirmed.push(relay_url);
+                            } else {
+                                report.rejected.push((relay_url, message.clone()));
+                            }
+                        }
+                        _ => report.other_events.push((relay_url, event)),
+                    }
+                }
+                _ = &mut deadline => break,
+            }
+        }
+        report.timed_out = pending.into_iter().collect();
+        Ok(report)
+    }
+    /// Publishes `note` and waits up to `DEFAULT_PUBLISH_TIMEOUT` for each relay,
+    /// see `publish_with_timeout`.
+    pub async fn publish(&mut self, note: &NostrNote) -> HashMap<String, Result<bool, String>> {
+        self.publish_with_timeout(note, DEFAULT_PUBLISH_TIMEOUT)
+            .await
+    }
+    /// Whether each relay accepted `note`, by relay url. Relays that did not
+    /// answer in time map to `Err("timeout")`, and every relay maps to the error
+    /// if the note could not be sent. Other events stay in the pool reader.
+    pub async fn publish_with_timeout(
+        &mut self,
+        note: &NostrNote,
+        timeout: Duration,
+    ) -> HashMap<String, Result<bool, String>> {
+        let report = match self.publish_and_collect_ok(note.clone(), timeout).await {
+            Ok(report) => report,
+            Err(e) => {
+                return self
+                    .relays
+                    .iter()
+                    .map(|relay| (relay.url.clone(), Err(e.to_string())))
+                    .collect()
+            }
+        };
+        let confirmed = report.confirmed.into_iter().map(|url| (url, Ok(true)));
+        let rejected = report.rejected.into_iter().map(|(url, _)| (url, Ok(false)));
+        let timed_out = report
+            .timed_out
+            .into_iter()
+            .map(|url| (url, Err("timeout".to_string())));
+        confirmed.chain(rejected).chain(timed_out).collect()
+    }
+    /// Sends a signed note to the relay at `url` alone and returns whether it
+    /// accepted it, waiting up to `RelayConfig::write_timeout` for its OK. The other
+    /// relays never see the note, and other events stay in the pool reader.
+    pub async fn publish_to_relay(
+        &mut self,
+        url: &str,
+        note: &NostrNote,
+    ) -> Result<bool, PoolError> {
+        let relay = self
+            .relays
+            .iter()
+            .find(|relay| relay.url == url)
+            .ok_or_else(|| PoolError::UnknownRelay(url.to_string()))?;
+        let mut state = relay.state_watch();
+        let is_closed = |state: &WebsocketStatus| matches!(state, WebsocketStatus::Closed(_));
+        if relay.is_closed() || is_closed(&state.borrow()) {
+            return Err(PoolError::RelayClosed(url.to_string()));
+        }
+        let note_id = note.id.clone().ok_or(PoolError::MissingNoteId)?;
+        let mut route = self.routes.claim(RouteKey::Ok(note_id));
+        relay
+            .send_to_relay(note.clone().into())
+            .await
+            .map_err(|e| PoolError::BroadcastFailed(e.to_string()))?;
+        if let Some(counters) = self.counters.get(url) {
+            counters.sent();
+        }
+        let deadline = crate::relays::sleep(self.config.relay.write_timeout);
+        tokio::pin!(deadline);
+        loop {
+            select! {
+                event = route.recv() => {
+                    match event {
+                        Some((relay_url, RelayEvent::SentOk((_, _, accepted, _))))
+                            if relay_url == url =>
+                        {
+                            return Ok(accepted);
+                        }
+                        Some(_) => {}
+                        None => return Err(PoolError::RelayClosed(url.to_string())),
+                    }
+                }
+                _ = state.wait_for(is_closed) => {
+                    return Err(PoolError::RelayClosed(url.to_string()));
+                }
+                _ = &mut deadline => break,
+            }
+        }
+        Err(PoolError::PublishTimedOut(url.to_string()))
+    }
+    /// Closes the pool, waiting up to `DEFAULT_CLOSE_TIMEOUT` for relay tasks.
+    pub async fn close(self) -> Result<(), PoolError> {
+        self.close_with_timeout(DEFAULT_CLOSE_TIMEOUT).await
+    }
+    /// Signals every relay task to stop and waits at most `timeout` for them to
+    /// finish, returning `PoolError::TasksStillRunning` with the rest.
+    pub async fn close_with_timeout(mut self, timeout: Duration) -> Result<(), PoolError> {
+        self.shutdown.send_replace(true);
         for relay in &self.relays {
             relay.clone().close().await;
         }
         self.reader.close();
+        let mut pending = std::mem::take(&mut self.tasks)
+            .into_iter()
+            .collect::<futures_util::stream::FuturesUnordered<_>>();
         drop(self);
-        Ok(())
+        let deadline = crate::relays::sleep(timeout);
+        tokio::pin!(deadline);
+        loop {
+            select! {
+                finished = futures_util::StreamExt::next(&mut pending) => {
+                    if finished.is_none() {
+                        return Ok(());
+                    }
+                }
+                _ = &mut deadline => {
+                    tracing::warn!("{} relay tasks still running after close", pending.len());
+                    return Err(PoolError::TasksStillRunning(pending.len()));
+                }
+            }
+        }
+    }
+    /// Stops relay tasks from queueing new events and returns everything already
+    /// queued, waiting at most `timeout`, before closing the relay connections.
+    pub async fn drain_and_close(
+        mut self,
+        timeout: Duration,
+    ) -> Result<Vec<(String, RelayEvent)>, PoolError> {
+        self.reader.close();
+        let mut events = vec![];
+        let deadline = crate::relays::sleep(timeout);
+        tokio::pin!(deadline);
+        loop {
+            select! {
+                event = self.reader.recv() => {
+                    match event {
+                        Some(event) => events.push(event),
+                        None => break,
+                    }
+                }
+                _ = &mut deadline => {
+                    tracing::warn!("Relay pool drain timed out with events still queued");
+                    break;
+                }
+            }
+        }
+        self.close().await?;
+        Ok(events)
     }
 }
 
 impl Drop for NostrRelayPool {
     fn drop(&mut self) {
         // Ensure all resources are cleaned up
+        self.shutdown.send_replace(true);
         self.reader.close();
         for relay in &self.relays {
             let relay = relay.clone();
@@ -159,36 +1218,1345 @@ impl Drop for NostrRelayPool {
 mod tests {
 
     use super::*;
-    use crate::relays::{NostrSubscription, SubscribeEvent};
+    use crate::relays::RelayEventTag;
     wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
 
+    // A pool without relays, with the sender for its reader and a receiver for its broadcasts.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_pool(
+        tasks: Vec<oneshot::Receiver<()>>,
+    ) -> (
+        NostrRelayPool,
+        PoolRelaySender,
+        tokio::sync::broadcast::Receiver<crate::relays::WebSocketMessage>,
+    ) {
+        let (in_tx, in_rx) = tokio::sync::mpsc::channel(16);
+        let (broadcast_tx, relay_rx) = tokio::sync::broadcast::channel(16);
+        let pool = NostrRelayPool {
+            relays: vec![],
+            reader: in_rx,
+            broadcaster: broadcast_tx,
+            lagged: Arc::new(AtomicUsize::new(0)),
+            shutdown: watch::channel(false).0,
+            tasks,
+            library: PoolNoteLibrary::new(),
+            events: in_tx.clone(),
+            on_auth: None,
+            on_notice: Default::default(),
+            subscriptions: Subscriptions::default(),
+            counters: HashMap::new(),
+            config: PoolConfig::default(),
+            pool_events: PoolEvents::default(),
+            routes: EventRoutes::default(),
+        };
+        (pool, in_tx, relay_rx)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_subscribe_returns_id() {
+        let (pool, _, mut relay_rx) = test_pool(vec![]);
+        let filter = NostrSubscription {
+            kinds: Some(vec![1]),
+            ..Default::default()
+        };
+        let subscription = pool.subscribe(&filter).await.expect("Failed to subscribe");
+        let subscription_id = subscription.id();
+        let message = relay_rx.recv().await.expect("Relay did not receive REQ");
+        let request: serde_json::Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+        assert_eq!(request[0], "REQ");
+        assert_eq!(request[1], subscription_id);
+        assert_eq!(request[2], serde_json::json!({"kinds": [1]}));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_subscription_handle() {
+        use futures_util::StreamExt;
+        let (mut pool, in_tx, mut relay_rx) = test_pool(vec![]);
+        let subscription = pool
+            .subscribe(&NostrSubscription::default())
+            .await
+            .expect("Failed to subscribe");
+        let subscription_id = subscription.id().to_string();
+        relay_rx.recv().await.expect("Relay did not receive REQ");
+        assert_eq!(
+            pool.subscriptions.ids().await,
+            vec![subscription_id.clone()]
+        );
+
+        let note = |content: &str| NostrNote {
+            content: content.to_string(),
+            ..Default::default()
+        };
+        for (id, content) in [("other", "skipped"), (subscription_id.as_str(), "kept")] {
+            let event = RelayEvent::NewNote((RelayEventTag::EVENT, id.to_string(), note(content)));
+            in_tx
+                .send(("wss://relay.example.com".to_string(), event))
+                .await
+                .unwrap();
+        }
+        let received = subscription.stream(&mut pool).next().await;
+        assert_eq!(received.map(|note| note.content).as_deref(), Some("kept"));
+
+        drop(subscription);
+        let message = relay_rx.recv().await.expect("Relay did not receive CLOSE");
+        let close: serde_json::Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+        assert_eq!(close, serde_json::json!(["CLOSE", subscription_id]));
+        tokio::task::yield_now().await;
+        assert!(pool.subscriptions.ids().await.is_empty());
+
+        // Handles outliving their pool drop without sending anything.
+        let orphan = pool
+            .subscribe(&NostrSubscription::default())
+            .await
+            .expect("Failed to subscribe");
+        drop(relay_rx);
+        drop(pool);
+        drop(orphan);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_drain_and_close() {
+        let (pool, in_tx, _) = test_pool(vec![]);
+        for i in 0..5 {
+            let event = RelayEvent::EndOfSubscription((RelayEventTag::EOSE, i.to_string()));
+            in_tx
+                .send(("wss://relay.example.com".to_string(), event))
+                .await
+                .expect("Failed to queue event");
+        }
+        let events = pool
+            .drain_and_close(Duration::from_secs(1))
+            .await
+            .expect("Failed to drain pool");
+        assert_eq!(events.len(), 5);
+        for (i, (_, event)) in events.into_iter().enumerate() {
+            assert_eq!(
+                event,
+                RelayEvent::EndOfSubscription((RelayEventTag::EOSE, i.to_string()))
+            );
+        }
+        let late_event = RelayEvent::EndOfSubscription((RelayEventTag::EOSE, "late".to_string()));
+        assert!(in_tx
+            .send(("wss://relay.example.com".to_string(), late_event))
+            .await
+            .is_err());
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn mock_relay() -> (String, tokio::sync::mpsc::UnboundedReceiver<String>) {
+        mock_relay_with(|_| None::<String>).await
+    }
+
+    // Accepts any number of websocket clients on a local port, forwards every
+    // text message they send and answers with every message `reply` returns.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn mock_relay_with<R>(
+        reply: impl Fn(&str) -> R + Send + Sync + 'static,
+    ) -> (String, tokio::sync::mpsc::UnboundedReceiver<String>)
+    where
+        R: IntoIterator<Item = String>,
+        R::IntoIter: Send,
+    {
+        use futures_util::{SinkExt, StreamExt};
+        let reply = Arc::new(reply);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let (message_tx, message_rx) = tokio::sync::mpsc::unbounded_channel();
+        crate::relays::spawn_thread(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let message_tx = message_tx.clone();
+                let reply = reply.clone();
+                crate::relays::spawn_thread(async move {
+                    let Ok(mut socket) = tokio_tungstenite::accept_async(stream).await else {
+                        return;
+                    };
+                    while let Some(Ok(message)) = socket.next().await {
+                        if let crate::relays::WebSocketMessage::Text(text) = message {
+                            for response in reply(text.as_str()) {
+                                let response =
+                                    crate::relays::WebSocketMessage::Text(response.into());
+                                let _ = socket.send(response).await;
+                            }
+                            let _ = message_tx.send(text.to_string());
+                        }
+                    }
+                });
+            }
+        });
+        (url, message_rx)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_publish_and_collect_ok() {
+        let ok_reply = |accepted: bool, message: &'static str| {
+            move |text: &str| {
+                let request: serde_json::Value = serde_json::from_str(text).ok()?;
+                let id = request[1]["id"].as_str()?;
+                Some(serde_json::json!(["OK", id, accepted, message]).to_string())
+            }
+        };
+        let accept = ok_reply(true, "");
+        let (accepting, _) = mock_relay_with(move |text: &str| {
+            let notice = serde_json::json!(["NOTICE", "thanks"]).to_string();
+            accept(text).into_iter().chain([notice])
+        })
+        .await;
+        let (rejecting, _) = mock_relay_with(ok_reply(false, "blocked: spam")).await;
+        let (silent, _) = mock_relay().await;
+        let mut pool =
+            NostrRelayPool::new(vec![accepting.clone(), rejecting.clone(), silent.clone()])
+                .await
+                .expect("Failed to create pool");
+
+        let keys = crate::keypair::NostrKeypair::generate(false);
+        let mut note = NostrNote {
+            pubkey: keys.public_key(),
+            content: "rotating keys".to_string(),
+            ..Default::default()
+        };
+        keys.sign_note(&mut note).expect("Failed to sign note");
+        let report = pool
+            .publish_and_collect_ok(note, Duration::from_millis(500))
+            .await
+            .expect("Failed to publish");
+        assert_eq!(report.confirmed, vec![accepting.clone()]);
+        assert_eq!(
+            report.rejected,
+            vec![(rejecting.clone(), "blocked: spam".to_string())]
+        );
+        assert_eq!(report.timed_out, vec![silent.clone()]);
+        // Waiting for the OKs leaves the relay's other messages in the reader.
+        assert!(report.other_events.is_empty());
+        assert_eq!(
+            next_event(&mut pool).await,
+            RelayEvent::Notice((RelayEventTag::NOTICE, "thanks".to_string()))
+        );
+
+        let mut note = NostrNote {
+            pubkey: keys.public_key(),
+            content: "publishing again".to_string(),
+            ..Default::default()
+        };
+        keys.sign_note(&mut note).expect("Failed to sign note");
+        let results = pool
+            .publish_with_timeout(&note, Duration::from_millis(500))
+            .await;
+        assert_eq!(
+            results,
+            HashMap::from([
+                (accepting, Ok(true)),
+                (rejecting, Ok(false)),
+                (silent, Err("timeout".to_string())),
+            ])
+        );
+
+        let unsigned = NostrNote::default();
+        assert!(pool
+            .publish_and_collect_ok(unsigned.clone(), Duration::from_millis(10))
+            .await
+            .is_err());
+        let results = pool.publish(&unsigned).await;
+        assert_eq!(results.len(), 3);
+        assert!(results.values().all(|result| result.is_err()));
+        pool.close().await.expect("Failed to close pool");
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_publish_to_relay() {
+        let ok_reply = |text: &str| {
+            let request: serde_json::Value = serde_json::from_str(text).ok()?;
+            let id = request[1]["id"].as_str()?;
+            Some(serde_json::json!(["OK", id, true, ""]).to_string())
+        };
+        let (target, mut target_messages) = mock_relay_with(ok_reply).await;
+        let (other, mut other_messages) = mock_relay_with(ok_reply).await;
+        let (silent, _) = mock_relay().await;
+        let config = PoolConfig {
+            relay: RelayConfig {
+                write_timeout: Duration::from_millis(300),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut pool = NostrRelayPool::new_with_config(
+            vec![target.clone(), other.clone(), silent.clone()],
+            config,
+        )
+        .await
+        .expect("Failed to create pool");
+        for relay in &pool.relays {
+            relay.wait_for_open().await.expect("Relay failed to open");
+        }
+
+        let keys = crate::keypair::NostrKeypair::generate(false);
+        let mut note = NostrNote {
+            pubkey: keys.public_key(),
+            content: "only for one relay".to_string(),
+            ..Default::default()
+        };
+        keys.sign_note(&mut note).expect("Failed to sign note");
+        assert!(pool
+            .publish_to_relay(&target, &note)
+            .await
+            .expect("Failed to publish"));
+        let sent: serde_json::Value =
+            serde_json::from_str(&target_messages.recv().await.unwrap()).unwrap();
+        assert_eq!(sent[1]["id"], note.id.clone().unwrap());
+
+        let stats = pool.relay_stats().await;
+        assert_eq!(stats[&target].sent_count, 1);
+        assert_eq!(stats[&other].sent_count, 0);
+        assert_eq!(stats[&silent].sent_count, 0);
+        assert!(other_messages.try_recv().is_err());
+
+        assert!(matches!(
+            pool.publish_to_relay(&silent, &note).await,
+            Err(PoolError::PublishTimedOut(url)) if url == silent
+        ));
+        assert!(matches!(
+            pool.publish_to_relay("wss://unknown.example.com", &note)
+                .await,
+            Err(PoolError::UnknownRelay(_))
+        ));
+        assert!(matches!(
+            pool.publish_to_relay(&target, &NostrNote::default()).await,
+            Err(PoolError::MissingNoteId)
+        ));
+        pool.close().await.expect("Failed to close pool");
+
+        // Nothing listens on the port, so the relay fails to connect and closes.
+        let unreachable = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("ws://{}", listener.local_addr().unwrap())
+        };
+        let mut pool = NostrRelayPool::new(vec![unreachable.clone()])
+            .await
+            .expect("Failed to create pool");
+        assert!(pool.relays[0].wait_for_open().await.is_err());
+        assert!(matches!(
+            pool.publish_to_relay(&unreachable, &note).await,
+            Err(PoolError::RelayClosed(url)) if url == unreachable
+        ));
+        pool.close().await.expect("Failed to close pool");
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_note_streams() {
+        use futures_util::StreamExt;
+        let keys = crate::keypair::NostrKeypair::generate(false);
+        let signed = |kind: u32, content: &str| {
+            let mut note = NostrNote {
+                pubkey: keys.public_key(),
+                kind,
+                content: content.to_string(),
+                ..Default::default()
+            };
+            keys.sign_note(&mut note).expect("Failed to sign note");
+            note
+        };
+        let (text_note, reaction, other) =
+            (signed(1, "hello"), signed(7, "+"), signed(1, "elsewhere"));
+        let replies = (text_note.clone(), reaction.clone(), other.clone());
+        let (url, _) = mock_relay_with(move |text| {
+            let request: serde_json::Value = serde_json::from_str(text).unwrap();
+            if request[0] != "REQ" {
+                return vec![];
+            }
+            let id = request[1].as_str().unwrap();
+            let (text_note, reaction, other) = &replies;
+            vec![
+                serde_json::json!(["EVENT", "other", other]).to_string(),
+                serde_json::json!(["EVENT", id, reaction]).to_string(),
+                serde_json::json!(["EVENT", id, text_note]).to_string(),
+                serde_json::json!(["EOSE", id]).to_string(),
+            ]
+        })
+        .await;
+
+        // Only the kind 1 note sent for this subscription gets through.
+        let mut pool = NostrRelayPool::new(vec![url.clone()])
+            .await
+            .expect("Failed to create pool");
+        let filter = NostrSubscription {
+            kinds: Some(vec![1]),
+            ..Default::default()
+        };
+        let notes = pool
+            .note_stream_filtered(filter)
+            .await
+            .expect("Failed to subscribe");
+        let first = tokio::time::timeout(Duration::from_secs(5), notes.boxed().next())
+            .await
+            .expect("Timed out waiting for a note");
+        assert_eq!(first, Some(text_note.clone()));
+
+        // The unfiltered streams see every note and event, tagged with their relay.
+        let mut pool = NostrRelayPool::new(vec![url.clone()])
+            .await
+            .expect("Failed to create pool");
+        let _subscription = pool
+            .subscribe(&NostrSubscription::default())
+            .await
+            .expect("Failed to subscribe");
+        let notes: Vec<(String, NostrNote)> = pool.note_stream().take(3).collect().await;
+        assert_eq!(
+            notes,
+            vec![
+                (url.clone(), other),
+                (url.clone(), reaction),
+                (url.clone(), text_note)
+            ]
+        );
+        let (relay_url, event) = pool.event_stream().next().await.unwrap();
+        assert_eq!(relay_url, url);
+        assert!(matches!(event, RelayEvent::EndOfSubscription(_)));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_note_stream_multiple_filters() {
+        use futures_util::StreamExt;
+        let (alice, bob) = (
+            crate::keypair::NostrKeypair::generate(false),
+            crate::keypair::NostrKeypair::generate(false),
+        );
+        let signed = |keys: &crate::keypair::NostrKeypair, kind: u32, created_at: i64| {
+            let mut note = NostrNote {
+                pubkey: keys.public_key(),
+                kind,
+                created_at,
+                ..Default::default()
+            };
+            keys.sign_note(&mut note).expect("Failed to sign note");
+            note
+        };
+        let alice_text = signed(&alice, 1, 2_000);
+        let alice_old_text = signed(&alice, 1, 1_000);
+        let alice_reaction = signed(&alice, 7, 2_000);
+        let bob_text = signed(&bob, 1, 2_000);
+        let bob_reaction = signed(&bob, 7, 2_000);
+        let replies = [
+            alice_reaction,
+            bob_text,
+            alice_old_text,
+            bob_reaction.clone(),
+            alice_text.clone(),
+        ];
+        let (url, _) = mock_relay_with(move |text| {
+            let request: serde_json::Value = serde_json::from_str(text).unwrap();
+            if request[0] != "REQ" {
+                return vec![];
+            }
+            let id = request[1].as_str().unwrap();
+            replies
+                .iter()
+                .map(|note| serde_json::json!(["EVENT", id, note]).to_string())
+                .collect()
+        })
+        .await;
+        let mut pool = NostrRelayPool::new(vec![url])
+            .await
+            .expect("Failed to create pool");
+
+        // Alice's recent text notes or Bob's reactions.
+        let filter = NostrSubscription {
+            authors: Some(vec![alice.public_key()]),
+            kinds: Some(vec![1]),
+            since: Some(1_500),
+            ..Default::default()
+        }
+        .with_additional_filter(NostrSubscription {
+            authors: Some(vec![bob.public_key()]),
+            kinds: Some(vec![7]),
+            ..Default::default()
+        });
+        let notes = pool
+            .note_stream_filtered(filter)
+            .await
+            .expect("Failed to subscribe");
+        let notes: Vec<NostrNote> =
+            tokio::time::timeout(Duration::from_secs(5), notes.take(2).collect::<Vec<_>>())
+                .await
+                .expect("Timed out waiting for notes");
+        assert_eq!(notes, vec![bob_reaction, alice_text]);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_overlapping_subscriptions() {
+        use futures_util::StreamExt;
+        let keys = crate::keypair::NostrKeypair::generate(false);
+        let mut note = NostrNote {
+            pubkey: keys.public_key(),
+            kind: 1,
+            ..Default::default()
+        };
+        keys.sign_note(&mut note).expect("Failed to sign note");
+        let reply = note.clone();
+        let (url, _) = mock_relay_with(move |text| {
+            let request: serde_json::Value = serde_json::from_str(text).unwrap();
+            if request[0] != "REQ" {
+                return vec![];
+            }
+            let event = serde_json::json!(["EVENT", request[1], reply]).to_string();
+            vec![event.clone(), event]
+        })
+        .await;
+        let mut pool = NostrRelayPool::new(vec![url])
+            .await
+            .expect("Failed to create pool");
+        let kinds = NostrSubscription {
+            kinds: Some(vec![1]),
+            ..Default::default()
+        };
+        let authors = NostrSubscription {
+            authors: Some(vec![keys.public_key()]),
+            ..Default::default()
+        };
+
+        // Each subscription gets the note once, even after the pool has seen it.
+        for filter in [kinds.clone(), authors.clone()] {
+            let notes = pool
+                .note_stream_filtered(filter)
+                .await
+                .expect("Failed to subscribe");
+            tokio::pin!(notes);
+            let received = tokio::time::timeout(Duration::from_secs(5), notes.next())
+                .await
+                .expect("Timed out waiting for note");
+            assert_eq!(received, Some(note.clone()));
+            assert!(
+                tokio::time::timeout(Duration::from_millis(200), notes.next())
+                    .await
+                    .is_err()
+            );
+        }
+        let handle = pool.subscribe(&authors).await.expect("Failed to subscribe");
+        let mut notes = handle.stream(&mut pool);
+        let received = tokio::time::timeout(Duration::from_secs(5), notes.next())
+            .await
+            .expect("Timed out waiting for note");
+        assert_eq!(received, Some(note));
+        assert!(
+            tokio::time::timeout(Duration::from_millis(200), notes.next())
+                .await
+                .is_err()
+        );
+        drop(notes);
+        drop(handle);
+        pool.close().await.expect("Failed to close pool");
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_subscribe_until_eose() {
+        let keys = crate::keypair::NostrKeypair::generate(false);
+        let signed = |created_at: i64, content: &str| {
+            let mut note = NostrNote {
+                pubkey: keys.public_key(),
+                created_at,
+                content: content.to_string(),
+                ..Default::default()
+            };
+            keys.sign_note(&mut note).expect("Failed to sign note");
+            note
+        };
+        let (older, newer, late) = (
+            signed(1_000, "older"),
+            signed(2_000, "newer"),
+            signed(3_000, "late"),
+        );
+        let replies = (older.clone(), newer.clone(), late.clone());
+        let (url, mut messages) = mock_relay_with(move |text| {
+            let request: serde_json::Value = serde_json::from_str(text).unwrap();
+            if request[0] != "REQ" {
+                return vec![];
+            }
+            let id = request[1].as_str().unwrap();
+            let (older, newer, late) = &replies;
+            vec![
+                serde_json::json!(["EVENT", "other", late]).to_string(),
+                serde_json::json!(["EVENT", id, older]).to_string(),
+                serde_json::json!(["EVENT", id, newer]).to_string(),
+                serde_json::json!(["EVENT", id, older]).to_string(),
+                serde_json::json!(["EOSE", "other"]).to_string(),
+                serde_json::json!(["EOSE", id]).to_string(),
+                serde_json::json!(["EVENT", id, late]).to_string(),
+            ]
+        })
+        .await;
+        let mut pool = NostrRelayPool::new(vec![url])
+            .await
+            .expect("Failed to create pool");
+        let notes = pool
+            .subscribe_until_eose(&NostrSubscription::default(), Duration::from_secs(5))
+            .await
+            .expect("Failed to collect notes");
+        assert_eq!(notes, vec![newer.clone(), older.clone()]);
+
+        let request: serde_json::Value =
+            serde_json::from_str(&messages.recv().await.unwrap()).unwrap();
+        let close: serde_json::Value =
+            serde_json::from_str(&messages.recv().await.unwrap()).unwrap();
+        assert_eq!(request[0], "REQ");
+        assert_eq!(close, serde_json::json!(["CLOSE", request[1]]));
+
+        // Events for other subscriptions are left for the reader.
+        assert_eq!(
+            next_event(&mut pool).await,
+            RelayEvent::NewNote((RelayEventTag::EVENT, "other".to_string(), late))
+        );
+        assert_eq!(
+            next_event(&mut pool).await,
+            RelayEvent::EndOfSubscription((RelayEventTag::EOSE, "other".to_string()))
+        );
+
+        // Notes the pool has already seen come back again for a new call.
+        let notes = pool
+            .subscribe_until_eose(&NostrSubscription::default(), Duration::from_secs(5))
+            .await
+            .expect("Failed to collect notes");
+        assert_eq!(notes, vec![newer, older]);
+        pool.close().await.expect("Failed to close pool");
+
+        let (silent, _) = mock_relay().await;
+        let mut pool = NostrRelayPool::new(vec![silent])
+            .await
+            .expect("Failed to create pool");
+        let notes = pool
+            .subscribe_until_eose(&NostrSubscription::default(), Duration::from_millis(200))
+            .await
+            .expect("Failed to collect notes");
+        assert!(notes.is_empty());
+        pool.close().await.expect("Failed to close pool");
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_relay_stats() {
+        let (url, _) = mock_relay_with(|text| {
+            let request: serde_json::Value = serde_json::from_str(text).unwrap();
+            let id = request[1].as_str().unwrap().to_string();
+            (request[0] == "REQ").then(|| serde_json::json!(["EOSE", id]).to_string())
+        })
+        .await;
+        let (silent, _) = mock_relay().await;
+        let mut pool = NostrRelayPool::new(vec![url.clone(), silent.clone()])
+            .await
+            .expect("Failed to create pool");
+        assert_eq!(pool.relay_urls(), vec![url.clone(), silent.clone()]);
+        assert_eq!(pool.relay_status("wss://unknown.example.com").await, None);
+        for relay in &pool.relays {
+            relay.wait_for_open().await.expect("Relay failed to open");
+        }
+        assert_eq!(
+            pool.relay_status(&url).await,
+            Some(RelayConnectState::Connected)
+        );
+
+        let stats = pool.relay_stats().await;
+        assert_eq!(stats[&url].received_count, 0);
+        assert_eq!(stats[&url].last_event_at, None);
+        let subscription = pool
+            .subscribe(&NostrSubscription::default())
+            .await
+            .expect("Failed to subscribe");
+        let subscription_id = subscription.id().to_string();
+        assert_eq!(
+            next_event(&mut pool).await,
+            RelayEvent::EndOfSubscription((RelayEventTag::EOSE, subscription_id))
+        );
+        let stats = pool.relay_stats().await;
+        assert!(stats[&url].connected);
+        assert_eq!(stats[&url].received_count, 1);
+        assert!(stats[&url].sent_count >= 1);
+        assert!(stats[&url].last_event_at.is_some());
+        assert_eq!(stats[&silent].received_count, 0);
+
+        pool.remove_relay(&silent)
+            .await
+            .expect("Failed to remove relay");
+        assert_eq!(pool.relay_urls(), vec![url.clone()]);
+        assert!(!pool.relay_stats().await.contains_key(&silent));
+        pool.close().await.expect("Failed to close pool");
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    #[ignore = "needs a live relay"]
+    async fn test_subscribe_until_eose_live() {
+        let mut pool = NostrRelayPool::new(["wss://relay.illuminodes.com"])
+            .await
+            .expect("Failed to create pool");
+        let filter = NostrSubscription {
+            kinds: Some(vec![1]),
+            limit: Some(10),
+            ..Default::default()
+        };
+        let notes = pool
+            .subscribe_until_eose(&filter, Duration::from_secs(10))
+            .await
+            .expect("Failed to collect notes");
+        assert!(!notes.is_empty() && notes.len() <= 10);
+        assert!(notes.windows(2).all(|w| w[0].created_at >= w[1].created_at));
+        pool.close().await.expect("Failed to close pool");
+    }
+
+    // Challenges every client with `AUTH` on connect and only serves `REQ`
+    // once the client answered with a valid kind 22242 note.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn mock_auth_relay(challenge: &'static str) -> String {
+        use futures_util::{SinkExt, StreamExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        crate::relays::spawn_thread(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                crate::relays::spawn_thread(async move {
+                    let Ok(mut socket) = tokio_tungstenite::accept_async(stream).await else {
+                        return;
+                    };
+                    let auth = serde_json::json!(["AUTH", challenge]).to_string();
+                    let _ = socket
+                        .send(crate::relays::WebSocketMessage::Text(auth.into()))
+                        .await;
+                    let mut authenticated = false;
+                    while let Some(Ok(message)) = socket.next().await {
+                        let crate::relays::WebSocketMessage::Text(text) = message else {
+                            continue;
+                        };
+                        let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+                        let response = match request[0].as_str() {
+                            Some("AUTH") => {
+                                let note: NostrNote =
+                                    serde_json::from_value(request[1].clone()).unwrap();
+                                authenticated = note.kind == 22242
+                                    && note.verify()
+                                    && note
+                                        .tags
+                                        .find_tags_ref(crate::notes::NostrTag::Custom("challenge"))
+                                        .any(|value| value == challenge);
+                                serde_json::json!(["OK", note.id, authenticated, ""])
+                            }
+                            Some("REQ") if authenticated => serde_json::json!(["EOSE", request[1]]),
+                            Some("REQ") => serde_json::json!([
+                                "CLOSED",
+                                request[1],
+                                "auth-required: sign in first"
+                            ]),
+                            _ => continue,
+                        };
+                        let response =
+                            crate::relays::WebSocketMessage::Text(response.to_string().into());
+                        let _ = socket.send(response).await;
+                    }
+                });
+            }
+        });
+        url
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn next_event(pool: &mut NostrRelayPool) -> RelayEvent {
+        tokio::time::timeout(Duration::from_secs(5), pool.reader.recv())
+            .await
+            .expect("Timed out waiting for relay")
+            .expect("Pool reader closed")
+            .1
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_auth_handler_answers_challenges() {
+        let url = mock_auth_relay("relay-challenge").await;
+        let keys = crate::keypair::NostrKeypair::generate(false);
+        let handler_keys = keys.clone();
+        let mut pool =
+            NostrRelayPool::new_with_auth(vec![url.clone()], move |relay_url, challenge| {
+                crate::nips::Nip42::auth_note(&handler_keys, relay_url, challenge)
+            })
+            .await
+            .expect("Failed to create pool");
+        assert_eq!(
+            next_event(&mut pool).await,
+            RelayEvent::Auth((RelayEventTag::AUTH, "relay-challenge".to_string()))
+        );
+        assert!(matches!(
+            next_event(&mut pool).await,
+            RelayEvent::SentOk((_, _, true, _))
+        ));
+        let subscription = pool
+            .subscribe(&NostrSubscription::default())
+            .await
+            .expect("Failed to subscribe");
+        let subscription_id = subscription.id().to_string();
+        assert_eq!(
+            next_event(&mut pool).await,
+            RelayEvent::EndOfSubscription((RelayEventTag::EOSE, subscription_id))
+        );
+        pool.close().await.expect("Failed to close pool");
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_notice_handler() {
+        let (url, _) = mock_relay_with(|text| {
+            let request: serde_json::Value = serde_json::from_str(text).unwrap();
+            (request[0] == "REQ")
+                .then(|| serde_json::json!(["NOTICE", "rate limited: slow down"]).to_string())
+        })
+        .await;
+        let mut pool = NostrRelayPool::new(vec![url.clone()])
+            .await
+            .expect("Failed to create pool");
+        let (notice_tx, mut notice_rx) = tokio::sync::mpsc::unbounded_channel();
+        pool.on_notice(move |relay_url, notice| {
+            let _ = notice_tx.send((relay_url.to_string(), notice.to_string()));
+        });
+        let _subscription = pool
+            .subscribe(&NostrSubscription::default())
+            .await
+            .expect("Failed to subscribe");
+        assert_eq!(
+            next_event(&mut pool).await,
+            RelayEvent::Notice((RelayEventTag::NOTICE, "rate limited: slow down".to_string()))
+        );
+        let received = tokio::time::timeout(Duration::from_secs(5), notice_rx.recv())
+            .await
+            .expect("Timed out waiting for notice callback");
+        assert_eq!(
+            received,
+            Some((url.clone(), "rate limited: slow down".to_string()))
+        );
+        pool.close().await.expect("Failed to close pool");
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_manual_auth() {
+        let url = mock_auth_relay("relay-challenge").await;
+        let mut pool = NostrRelayPool::new(vec![url.clone()])
+            .await
+            .expect("Failed to create pool");
+        let RelayEvent::Auth((_, challenge)) = next_event(&mut pool).await else {
+            panic!("Relay did not send a challenge");
+        };
+        let subscription = pool
+            .subscribe(&NostrSubscription::default())
+            .await
+            .expect("Failed to subscribe");
+        let subscription_id = subscription.id().to_string();
+        assert_eq!(
+            next_event(&mut pool).await,
+            RelayEvent::ClosedSubscription((RelayEventTag::CLOSED, subscription_id))
+        );
+
+        let keys = crate::keypair::NostrKeypair::generate(false);
+        pool.relays[0]
+            .send_auth(&keys, &challenge)
+            .await
+            .expect("Failed to send auth");
+        assert!(matches!(
+            next_event(&mut pool).await,
+            RelayEvent::SentOk((_, _, true, _))
+        ));
+        let subscription = pool
+            .subscribe(&NostrSubscription::default())
+            .await
+            .expect("Failed to subscribe");
+        let subscription_id = subscription.id().to_string();
+        assert_eq!(
+            next_event(&mut pool).await,
+            RelayEvent::EndOfSubscription((RelayEventTag::EOSE, subscription_id))
+        );
+        pool.close().await.expect("Failed to close pool");
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_pool_from_url_collections() {
+        let (first, _) = mock_relay().await;
+        let (second, _) = mock_relay().await;
+
+        let from_slice = NostrRelayPool::new(&[first.as_str(), second.as_str()])
+            .await
+            .expect("Failed to create pool");
+        assert_eq!(from_slice.relays.len(), 2);
+        from_slice.close().await.expect("Failed to close pool");
+
+        let urls: std::collections::HashSet<String> =
+            [first.clone(), second.clone(), first.clone()].into();
+        let from_set = NostrRelayPool::new(urls)
+            .await
+            .expect("Failed to create pool");
+        assert_eq!(from_set.relays.len(), 2);
+        from_set.close().await.expect("Failed to close pool");
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_add_and_remove_relay() {
+        async fn next_request(
+            messages: &mut tokio::sync::mpsc::UnboundedReceiver<String>,
+        ) -> serde_json::Value {
+            let message = tokio::time::timeout(Duration::from_secs(5), messages.recv())
+                .await
+                .expect("Timed out waiting for relay message")
+                .expect("Relay closed");
+            serde_json::from_str(&message).unwrap()
+        }
+        let (first, mut first_messages) = mock_relay().await;
+        let (second, mut second_messages) = mock_relay().await;
+        let mut pool = NostrRelayPool::new(vec![first.clone()])
+            .await
+            .expect("Failed to create pool");
+        let kept = pool
+            .subscribe(&NostrSubscription::default())
+            .await
+            .expect("Failed to subscribe");
+        let closed = pool
+            .subscribe(&NostrSubscription::default())
+            .await
+            .expect("Failed to subscribe");
+        assert_eq!(next_request(&mut first_messages).await[1], kept.id());
+        assert_eq!(next_request(&mut first_messages).await[1], closed.id());
+        let closed_id = closed.id().to_string();
+        drop(closed);
+        assert_eq!(
+            next_request(&mut first_messages).await,
+            serde_json::json!(["CLOSE", closed_id])
+        );
+
+        pool.add_relay(&second).await.expect("Failed to add relay");
+        let request = next_request(&mut second_messages).await;
+        assert_eq!(request[0], "REQ");
+        assert_eq!(request[1], kept.id());
+        assert!(matches!(
+            pool.add_relay(&second).await,
+            Err(PoolError::DuplicateRelay(_))
+        ));
+        assert!(matches!(
+            pool.add_relay("https://relay.example.com").await,
+            Err(PoolError::InvalidRelay(_))
+        ));
+
+        pool.remove_relay(&first)
+            .await
+            .expect("Failed to remove relay");
+        assert_eq!(
+            next_request(&mut first_messages).await,
+            serde_json::json!(["CLOSE", kept.id()])
+        );
+        assert_eq!(
+            pool.relays
+                .iter()
+                .map(|relay| relay.url.clone())
+                .collect::<Vec<_>>(),
+            vec![second.clone()]
+        );
+        assert!(matches!(
+            pool.remove_relay(&first).await,
+            Err(PoolError::UnknownRelay(_))
+        ));
+
+        // Later subscriptions only reach the relays still in the pool.
+        let later = pool
+            .subscribe(&NostrSubscription::default())
+            .await
+            .expect("Failed to subscribe");
+        assert_eq!(next_request(&mut second_messages).await[1], later.id());
+        pool.close().await.expect("Failed to close pool");
+        assert!(first_messages.try_recv().is_err());
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_pool_relay_config() {
+        // Never completes the websocket handshake.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let config = RelayConfig {
+            connect_timeout: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let mut pool = NostrRelayPool::with_config(vec![url.clone()], config)
+            .await
+            .expect("Failed to create pool");
+        assert_eq!(pool.config.relay, config);
+        let (added, _) = mock_relay().await;
+        pool.add_relay(&added).await.expect("Failed to add relay");
+        let opened = tokio::time::timeout(Duration::from_secs(2), async {
+            (
+                pool.relays[0].wait_for_open().await,
+                pool.relays[1].wait_for_open().await,
+            )
+        })
+        .await
+        .expect("Connect timeout did not fire");
+        assert!(opened.0.is_err());
+        assert!(opened.1.is_ok());
+        pool.close().await.expect("Failed to close pool");
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_idle_relays_are_removed() {
+        use futures_util::StreamExt;
+        // Answers the first REQ with EOSE and then never sends anything again.
+        let (silent, _) = mock_relay_with(|text| {
+            let request: serde_json::Value = serde_json::from_str(text).unwrap();
+            (request[0] == "REQ").then(|| serde_json::json!(["EOSE", request[1]]).to_string())
+        })
+        .await;
+        let config = PoolConfig {
+            idle_timeout: Some(Duration::from_millis(200)),
+            ..Default::default()
+        };
+        let mut pool = NostrRelayPool::build(vec![silent.clone()], config, |_, _, _| {}, None)
+            .expect("Failed to create pool");
+        let subscription = pool
+            .subscribe(&NostrSubscription::default())
+            .await
+            .expect("Failed to subscribe");
+        assert!(matches!(
+            next_event(&mut pool).await,
+            RelayEvent::EndOfSubscription(_)
+        ));
+        assert_eq!(pool.relay_urls(), vec![silent.clone()]);
+
+        let removed = tokio::time::timeout(Duration::from_secs(2), pool.pool_events().next())
+            .await
+            .expect("Idle relay was not removed");
+        assert_eq!(removed, Some(PoolEvent::RelayRemoved(silent.clone())));
+        assert!(pool.relay_urls().is_empty());
+
+        // Pools without an idle timeout keep quiet relays.
+        let (quiet, _) = mock_relay().await;
+        let mut pool_without_timeout = NostrRelayPool::new(vec![quiet.clone()])
+            .await
+            .expect("Failed to create pool");
+        pool_without_timeout.relays[0]
+            .wait_for_open()
+            .await
+            .unwrap();
+        let event = tokio::time::timeout(
+            Duration::from_millis(300),
+            pool_without_timeout.pool_events().next(),
+        )
+        .await;
+        assert!(event.is_err());
+        assert_eq!(pool_without_timeout.relay_urls(), vec![quiet]);
+
+        // An idle relay can be added back.
+        pool.add_relay(&silent)
+            .await
+            .expect("Failed to re-add relay");
+        assert_eq!(pool.relay_urls(), vec![silent]);
+        drop(subscription);
+        pool.close().await.expect("Failed to close pool");
+        pool_without_timeout.close().await.unwrap();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_idle_timeout_restarts_on_events() {
+        use futures_util::StreamExt;
+        let (url, _) = mock_relay_with(|text| {
+            let request: serde_json::Value = serde_json::from_str(text).unwrap();
+            (request[0] == "REQ").then(|| serde_json::json!(["EOSE", request[1]]).to_string())
+        })
+        .await;
+        let idle_timeout = Duration::from_millis(400);
+        let config = PoolConfig {
+            idle_timeout: Some(idle_timeout),
+            ..Default::default()
+        };
+        let started = tokio::time::Instant::now();
+        let mut pool = NostrRelayPool::build(vec![url.clone()], config, |_, _, _| {}, None)
+            .expect("Failed to create pool");
+        pool.relays[0].wait_for_open().await.unwrap();
+
+        // The last event arrives just before half the timeout has passed.
+        tokio::time::sleep_until(started + idle_timeout / 2 - Duration::from_millis(30)).await;
+        let _subscription = pool
+            .subscribe(&NostrSubscription::default())
+            .await
+            .expect("Failed to subscribe");
+        assert!(matches!(
+            next_event(&mut pool).await,
+            RelayEvent::EndOfSubscription(_)
+        ));
+        let last_event_at = tokio::time::Instant::now();
+
+        let early = tokio::time::timeout_at(
+            last_event_at + idle_timeout - Duration::from_millis(100),
+            pool.pool_events().next(),
+        )
+        .await;
+        assert!(early.is_err(), "Relay removed before its idle timeout");
+        assert_eq!(pool.relay_urls(), vec![url.clone()]);
+
+        let removed = tokio::time::timeout(Duration::from_secs(2), pool.pool_events().next())
+            .await
+            .expect("Idle relay was not removed");
+        assert_eq!(removed, Some(PoolEvent::RelayRemoved(url)));
+        assert!(last_event_at.elapsed() >= idle_timeout - Duration::from_millis(50));
+        pool.close().await.expect("Failed to close pool");
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_keepalive_stats() {
+        use futures_util::StreamExt;
+        // The mock keeps reading, so tungstenite answers every ping with a pong.
+        let (url, _) = mock_relay().await;
+        let config = PoolConfig {
+            relay: RelayConfig {
+                keepalive_interval: Some(Duration::from_millis(50)),
+                ..Default::default()
+            },
+            idle_timeout: Some(Duration::from_millis(400)),
+            ..Default::default()
+        };
+        let mut pool = NostrRelayPool::new_with_config(vec![url.clone()], config)
+            .await
+            .expect("Failed to create pool");
+        pool.relays[0].wait_for_open().await.unwrap();
+        assert_eq!(pool.relay_stats().await[&url].ping_latency_ms, None);
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while pool.relay_stats().await[&url].ping_latency_ms.is_none() {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("Keepalive ping was not answered");
+        assert!(pool.relay_stats().await[&url].received_count >= 1);
+
+        // Pongs alone do not keep a relay from being dropped as idle.
+        let removed = tokio::time::timeout(Duration::from_secs(2), pool.pool_events().next())
+            .await
+            .expect("Idle relay was not removed");
+        assert_eq!(removed, Some(PoolEvent::RelayRemoved(url)));
+        pool.close().await.expect("Failed to close pool");
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_connect_progress() {
+        let (open_url, _messages) = mock_relay().await;
+        // Bind and drop a listener to get a local port nothing is listening on.
+        let refused_url = {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            format!("ws://{}", listener.local_addr().unwrap())
+        };
+
+        let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
+        let pool = NostrRelayPool::new_with_progress(
+            vec![open_url.clone(), refused_url, open_url.clone()],
+            move |connected, total, url| {
+                let _ = progress_tx.send((connected, total, url.to_string()));
+            },
+        )
+        .await
+        .expect("Failed to create pool");
+        let mut progress = vec![];
+        for _ in 0..2 {
+            let update = tokio::time::timeout(Duration::from_secs(5), progress_rx.recv())
+                .await
+                .expect("Timed out waiting for relay to open")
+                .expect("Progress channel closed");
+            progress.push(update);
+        }
+        assert_eq!(
+            progress,
+            vec![(1, 3, open_url.clone()), (2, 3, open_url.clone())]
+        );
+        crate::relays::sleep(Duration::from_millis(100)).await;
+        assert!(progress_rx.try_recv().is_err());
+        pool.close().await.expect("Failed to close pool");
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_note_library_capacity() {
+        let note = |id: usize| NostrNote {
+            id: Some(format!("note{}", id)),
+            ..Default::default()
+        };
+        let library = NoteLibrary::with_capacity(3);
+        for id in 0..10 {
+            assert!(library.insert(note(id)).await);
+            assert!(!library.insert(note(id)).await);
+        }
+        assert_eq!(library.0.read().await.len(), 3);
+        // Evicted ids are forgotten and pass through again.
+        assert!(library.insert(note(0)).await);
+        assert!(!library.insert(note(9)).await);
+        assert_eq!(library.0.read().await.len(), 3);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_close_waits_for_relay_tasks() {
+        let (first, _) = mock_relay().await;
+        let (second, _) = mock_relay().await;
+        let pool = NostrRelayPool::new([first, second])
+            .await
+            .expect("Failed to create pool");
+        for relay in &pool.relays {
+            relay.wait_for_open().await.expect("Relay failed to open");
+        }
+        assert_eq!(pool.tasks.len(), 2);
+        pool.close_with_timeout(Duration::from_secs(5))
+            .await
+            .expect("Relay tasks did not finish");
+
+        // A task whose completion signal never fires is reported as still running.
+        let (_stuck_task, stuck_rx) = oneshot::channel();
+        let (pool, _, _) = test_pool(vec![stuck_rx]);
+        assert!(matches!(
+            pool.close_with_timeout(Duration::from_millis(50)).await,
+            Err(PoolError::TasksStillRunning(1))
+        ));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_lagged_broadcasts_are_counted() {
+        let (url, mut messages) = mock_relay().await;
+        let (open_tx, mut open_rx) = tokio::sync::mpsc::unbounded_channel();
+        let config = PoolConfig {
+            broadcast_capacity: 2,
+            ..Default::default()
+        };
+        let mut pool = NostrRelayPool::build(
+            vec![url.clone()],
+            config,
+            move |_, _, _| {
+                let _ = open_tx.send(());
+            },
+            None,
+        )
+        .expect("Failed to create pool");
+        tokio::time::timeout(Duration::from_secs(5), open_rx.recv())
+            .await
+            .expect("Timed out waiting for relay to open");
+
+        // The relay task cannot run between these sends, so all but the last
+        // two overflow the channel.
+        for i in 0..10 {
+            pool.send_to_relay(crate::relays::WebSocketMessage::Text(i.to_string().into()))
+                .await
+                .expect("Failed to broadcast");
+        }
+        let mut received = vec![];
+        for _ in 0..2 {
+            let message = tokio::time::timeout(Duration::from_secs(5), messages.recv())
+                .await
+                .expect("Timed out waiting for relay message")
+                .expect("Mock relay stopped");
+            received.push(message);
+        }
+        assert_eq!(received, vec!["8", "9"]);
+        assert_eq!(pool.lagged_messages(), 8);
+        assert_eq!(pool.reader.try_recv().ok(), Some((url, RelayEvent::Lag(8))));
+        pool.close().await.expect("Failed to close pool");
+    }
+
+    #[cfg(all(feature = "nip40", not(target_arch = "wasm32")))]
+    #[tokio::test(start_paused = true)]
+    async fn test_expiring_note_library() {
+        use crate::notes::{reset_test_clock, set_test_clock, NostrTag};
+        let expiring_at = |expiration: &str, id: &str| {
+            let mut note = NostrNote {
+                id: Some(id.to_string()),
+                ..Default::default()
+            };
+            note.tags
+                .add_custom_tag(NostrTag::Custom("expiration"), expiration);
+            note
+        };
+        set_test_clock(1_000);
+        let library = ExpiringNoteLibrary::new();
+        assert!(!library.insert(expiring_at("999", "expired")).await);
+        assert!(library.0.read().await.is_empty());
+
+        // Queued after a later note, so the sweeper has to wake up sooner.
+        assert!(library.insert(expiring_at("1005", "later")).await);
+        let note = expiring_at("1002", "expiring");
+        assert!(library.insert(note.clone()).await);
+        assert!(!library.insert(note.clone()).await);
+        let permanent = NostrNote {
+            id: Some("permanent".to_string()),
+            ..Default::default()
+        };
+        assert!(library.insert(permanent.clone()).await);
+        assert!(!library.insert(NostrNote::default()).await);
+        assert!(library.0.read().await.peek("").is_none());
+
+        // Notes are forgotten once they expire, without another insert.
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        assert!(!library.0.read().await.contains("expiring"));
+        assert!(library.0.read().await.contains("later"));
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        assert!(!library.0.read().await.contains("later"));
+        assert!(library.0.read().await.contains("permanent"));
+        assert!(library.1.queue.lock().unwrap().is_empty());
+        assert!(!library.1.sweeping.load(Ordering::SeqCst));
+        set_test_clock(1_006);
+        assert!(!library.insert(note).await);
+        assert!(!library.insert(permanent).await);
+
+        // Expired notes are also forgotten by the next insert.
+        assert!(library.insert(expiring_at("1007", "soon")).await);
+        set_test_clock(1_008);
+        assert!(library.insert(expiring_at("2000", "much later")).await);
+        assert!(!library.0.read().await.contains("soon"));
+
+        // The sweeper lets go of a dropped library.
+        let schedule = Arc::downgrade(&library.1);
+        drop(library);
+        tokio::time::sleep(Duration::from_secs(1_000)).await;
+        assert!(schedule.upgrade().is_none());
+
+        // Evicted ids do not pile up in the expiration queue.
+        let library = ExpiringNoteLibrary::with_capacity(2);
+        for id in 0..10 {
+            assert!(library.insert(expiring_at("2000", &id.to_string())).await);
+        }
+        assert!(library.1.queue.lock().unwrap().len() <= 4);
+        reset_test_clock();
+    }
+
     //#[tokio::test]
     //#[tracing_test::traced_test]
     #[wasm_bindgen_test::wasm_bindgen_test]
     async fn _relay_pool_stress() {
         // let time = tokio::time::Instant::now();
-        let mut pool = NostrRelayPool::new(vec![
-            "wss://relay.arrakis.lat".to_string(),
-            "wss://relay.illuminodes.com".to_string(),
-            "wss://frens.nostr1.com".to_string(),
-            "wss://bitcoiner.social".to_string(),
-            "wss://bouncer.minibolt.info".to_string(),
-            "wss://freespeech.casa".to_string(),
-            "wss://junxingwang.org".to_string(),
-            "wss://nostr.0x7e.xyz".to_string(),
+        let mut pool = NostrRelayPool::new([
+            "wss://relay.arrakis.lat",
+            "wss://relay.illuminodes.com",
+            "wss://frens.nostr1.com",
+            "wss://bitcoiner.social",
+            "wss://bouncer.minibolt.info",
+            "wss://freespeech.casa",
+            "wss://junxingwang.org",
+            "wss://nostr.0x7e.xyz",
         ])
         .await
         .expect("Failed to create pool");
         //println!("Time to create pool: {:?}", time.elapsed());
-        let filter: SubscribeEvent = NostrSubscription {
+        let filter = NostrSubscription {
             kinds: Some(vec![1]),
             limit: Some(5000),
             ..Default::default()
-        }
-        .into();
-        pool.send_to_relay(filter.into())
-            .await
-            .expect("Failed to subscribe");
+        };
+        let _subscription = pool.subscribe(&filter).await.expect("Failed to subscribe");
         let mut events = vec![];
         //println!("Time to subscribe: {:?}", time.elapsed());
         while let Some((_, event)) = pool.reader.recv().await {
@@ -220,28 +2588,25 @@ mod tests {
     // #[wasm_bindgen_test::wasm_bindgen_test]
     async fn _test_relay_pool() {
         tracing::info!("Starting test");
-        let mut pool = NostrRelayPool::new(vec![
-            "wss://relay.arrakis.lat".to_string(),
-            "wss://relay.illuminodes.com".to_string(),
-            "wss://frens.nostr1.com".to_string(),
-            "wss://bitcoiner.social".to_string(),
-            "wss://bouncer.minibolt.info".to_string(),
-            "wss://freespeech.casa".to_string(),
-            "wss://junxingwang.org".to_string(),
-            "wss://nostr.0x7e.xyz".to_string(),
+        let mut pool = NostrRelayPool::new([
+            "wss://relay.arrakis.lat",
+            "wss://relay.illuminodes.com",
+            "wss://frens.nostr1.com",
+            "wss://bitcoiner.social",
+            "wss://bouncer.minibolt.info",
+            "wss://freespeech.casa",
+            "wss://junxingwang.org",
+            "wss://nostr.0x7e.xyz",
         ])
         .await
         .expect("Failed to create pool");
         tracing::info!("Pool created");
-        let filter: SubscribeEvent = NostrSubscription {
+        let filter = NostrSubscription {
             kinds: Some(vec![1]),
             limit: Some(10),
             ..Default::default()
-        }
-        .into();
-        pool.send_to_relay(filter.into())
-            .await
-            .expect("Failed to subscribe");
+        };
+        let _subscription = pool.subscribe(&filter).await.expect("Failed to subscribe");
         tracing::info!("Subscribed");
         let mut events = vec![];
         pool.send_to_relay(NostrNote::default().into())
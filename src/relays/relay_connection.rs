@@ -1,11 +1,19 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use futures_util::{SinkExt, StreamExt};
-use tokio::sync::{Notify, RwLock};
+use secp256k1::rand::{thread_rng, Rng};
+use tokio::sync::{watch, RwLock};
 
 use super::{
     tcp::{NostrWebsocketWriter, WebSocketMessage},
-    NostrWebsocketReader, RelayEvent, Url,
+    NostrWebsocketReader, RelayError, RelayEvent, Url,
 };
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -15,42 +23,166 @@ pub enum WebsocketStatus {
     Closed(String),
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RelayConnectState {
+    Connected,
+    /// Attempt 0 is the first connection, later attempts follow a dropped socket.
+    Reconnecting {
+        attempt: u32,
+    },
+    Failed,
+}
+
+/// Backoff for reconnecting after the socket drops; `max_retries: 0` never reconnects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReconnectConfig {
+    pub max_retries: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        ReconnectConfig {
+            max_retries: 5,
+            base_delay_ms: 500,
+            max_delay_ms: 30_000,
+        }
+    }
+}
+impl ReconnectConfig {
+    // Doubles per attempt up to `max_delay_ms`, then jitters into the upper half.
+    fn delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay_ms
+            .saturating_mul(2u64.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay_ms);
+        Duration::from_millis(thread_rng().gen_range(delay / 2..=delay))
+    }
+}
+
+/// Limits on how long a relay may take to connect, to send a message and to
+/// stay silent. After `read_timeout` without a message the relay is pinged, and
+/// after a second one the connection is treated as dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RelayConfig {
+    pub connect_timeout: Duration,
+    pub read_timeout: Duration,
+    pub write_timeout: Duration,
+    /// How often to ping the relay so idle connections are not cut by proxies.
+    /// Without a pong within two intervals the relay reconnects. Native only,
+    /// browsers do not expose ping frames.
+    pub keepalive_interval: Option<Duration>,
+}
+impl Default for RelayConfig {
+    fn default() -> Self {
+        RelayConfig {
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(30),
+            write_timeout: Duration::from_secs(10),
+            keepalive_interval: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+// Keepalive pings in flight and the round trip of the last one answered.
+#[derive(Default)]
+struct Heartbeat {
+    // Set by the first unanswered ping, cleared by its pong.
+    ping_sent_at: std::sync::Mutex<Option<std::time::Instant>>,
+    latency: std::sync::Mutex<Option<Duration>>,
+    // Wakes `next_relay_event` when pongs stop, so it can reconnect.
+    stale: tokio::sync::Notify,
+}
+impl Heartbeat {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn pinged(&self) {
+        if let Ok(mut sent_at) = self.ping_sent_at.lock() {
+            sent_at.get_or_insert_with(std::time::Instant::now);
+        }
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    fn ponged(&self) {
+        let Some(sent_at) = self.ping_sent_at.lock().ok().and_then(|mut at| at.take()) else {
+            return;
+        };
+        if let Ok(mut latency) = self.latency.lock() {
+            *latency = Some(sent_at.elapsed());
+        }
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    fn is_stale(&self, grace: Duration) -> bool {
+        self.ping_sent_at
+            .lock()
+            .ok()
+            .and_then(|sent_at| *sent_at)
+            .is_some_and(|sent_at| sent_at.elapsed() >= grace)
+    }
+    fn reset(&self) {
+        if let Ok(mut sent_at) = self.ping_sent_at.lock() {
+            *sent_at = None;
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct RelayStatus {
-    state: Arc<RwLock<WebsocketStatus>>,
-    notify: Arc<Notify>,
+    state: Arc<watch::Sender<WebsocketStatus>>,
+    attempt: Arc<AtomicU32>,
 }
 impl RelayStatus {
     fn new() -> Self {
+        let (state, _) = watch::channel(WebsocketStatus::Connecting);
         RelayStatus {
-            state: Arc::new(RwLock::new(WebsocketStatus::Connecting)),
-            notify: Arc::new(Notify::new()),
+            state: Arc::new(state),
+            attempt: Arc::new(AtomicU32::new(0)),
         }
     }
     async fn connected(&self) {
-        let mut state = self.state.write().await;
-        *state = WebsocketStatus::Open;
-        self.notify.notify_waiters(); // Notify all waiting tasks
+        self.state.send_replace(WebsocketStatus::Open);
     }
     async fn disconnected(&self, reason: String) {
-        let mut state = self.state.write().await;
-        *state = WebsocketStatus::Closed(reason);
-        self.notify.notify_waiters(); // Notify all waiting tasks
+        self.state.send_replace(WebsocketStatus::Closed(reason));
+    }
+    // Only one caller wins the switch from `Open`, so a drop spawns one reconnect task.
+    fn start_reconnecting(&self) -> bool {
+        self.state.send_if_modified(|state| {
+            if *state != WebsocketStatus::Open {
+                return false;
+            }
+            *state = WebsocketStatus::Connecting;
+            true
+        })
+    }
+    fn reconnecting(&self, attempt: u32) {
+        self.attempt.store(attempt, Ordering::SeqCst);
     }
     pub async fn state(&self) -> WebsocketStatus {
-        self.state.read().await.clone()
+        self.state.borrow().clone()
     }
-    pub async fn wait_for_open(&self) -> anyhow::Result<()> {
-        let mut state = self.state.read().await;
-        if let WebsocketStatus::Closed(reason) = &*state {
-            return Err(anyhow::anyhow!("Disconnected: {}", reason));
+    pub async fn connect_state(&self) -> RelayConnectState {
+        match &*self.state.borrow() {
+            WebsocketStatus::Open => RelayConnectState::Connected,
+            WebsocketStatus::Connecting => RelayConnectState::Reconnecting {
+                attempt: self.attempt.load(Ordering::SeqCst),
+            },
+            WebsocketStatus::Closed(_) => RelayConnectState::Failed,
         }
-        while *state != WebsocketStatus::Open {
-            drop(state); // Drop the read lock before waiting
-            self.notify.notified().await; // Wait for a notification
-            state = self.state.read().await; // Re-acquire the read lock
+    }
+    fn subscribe(&self) -> watch::Receiver<WebsocketStatus> {
+        self.state.subscribe()
+    }
+    pub async fn wait_for_open(&self) -> anyhow::Result<()> {
+        let mut state = self.subscribe();
+        loop {
+            match &*state.borrow_and_update() {
+                WebsocketStatus::Open => return Ok(()),
+                WebsocketStatus::Closed(reason) => {
+                    return Err(anyhow::anyhow!("Disconnected: {}", reason))
+                }
+                WebsocketStatus::Connecting => {}
+            }
+            state.changed().await?;
         }
-        Ok(())
     }
 }
 
@@ -60,10 +192,16 @@ impl NostrWriter {
     pub fn new() -> Self {
         NostrWriter(Arc::new(RwLock::new(None)))
     }
-    async fn send(&self, message: crate::relays::WebSocketMessage) -> anyhow::Result<()> {
+    async fn send(
+        &self,
+        message: crate::relays::WebSocketMessage,
+        timeout: Duration,
+    ) -> anyhow::Result<()> {
         let mut writer = self.0.write().await;
         let writer = writer.as_mut().ok_or(anyhow::anyhow!("No writer"))?;
-        writer.send(message).await?;
+        crate::relays::timeout(timeout, writer.send(message))
+            .await
+            .ok_or(RelayError::Timeout("write"))??;
         Ok(())
     }
     async fn close(&self) {
@@ -75,19 +213,75 @@ impl NostrWriter {
 }
 
 #[derive(Clone)]
-pub struct NostrReader(Arc<RwLock<NostrWebsocketReader>>);
+pub struct NostrReader(Arc<RwLock<NostrWebsocketReader>>, Arc<Heartbeat>);
+impl Default for NostrReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl NostrReader {
     pub fn new() -> Self {
-        NostrReader(Arc::new(RwLock::new(None)))
+        NostrReader(Arc::new(RwLock::new(None)), Arc::default())
     }
     pub async fn read(&self) -> Option<RelayEvent> {
         let mut reader = self.0.write().await;
-        let message = reader.as_mut()?.next().await?.ok()?;
-        match message {
-            WebSocketMessage::Text(text) => RelayEvent::try_from(text.as_str()).ok(),
-            WebSocketMessage::Close(e) => RelayEvent::Close(e.unwrap().to_string()).into(),
-            _ => RelayEvent::Ping.into(),
+        loop {
+            let message = reader.as_mut()?.next().await?.ok()?;
+            return match message {
+                WebSocketMessage::Text(text) => match RelayEvent::parse_strict(text.as_str()) {
+                    Ok(event) => Some(event),
+                    Err(e) => {
+                        tracing::warn!("Skipping relay message: {}", e);
+                        continue;
+                    }
+                },
+                WebSocketMessage::Close(e) => {
+                    RelayEvent::Close(e.map(|frame| frame.to_string()).unwrap_or_default()).into()
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                WebSocketMessage::Pong(_) => {
+                    self.1.ponged();
+                    RelayEvent::Ping.into()
+                }
+                _ => RelayEvent::Ping.into(),
+            };
+        }
+    }
+}
+
+/// Open `REQ` messages by subscription id, kept until their `CLOSE`.
+#[derive(Clone, Default)]
+pub(crate) struct Subscriptions(Arc<RwLock<HashMap<String, WebSocketMessage>>>);
+impl Subscriptions {
+    pub(crate) async fn track(&self, message: &WebSocketMessage) {
+        let WebSocketMessage::Text(text) = message else {
+            return;
+        };
+        if !text.starts_with(r#"["REQ""#) && !text.starts_with(r#"["CLOSE""#) {
+            return;
         }
+        let Ok(request) = serde_json::from_str::<serde_json::Value>(text.as_str()) else {
+            return;
+        };
+        let Some(id) = request[1].as_str() else {
+            return;
+        };
+        let mut subscriptions = self.0.write().await;
+        if request[0] == "REQ" {
+            subscriptions.insert(id.to_string(), message.clone());
+        } else {
+            subscriptions.remove(id);
+        }
+    }
+    pub(crate) async fn remove(&self, id: &str) {
+        self.0.write().await.remove(id);
+    }
+    pub(crate) async fn ids(&self) -> Vec<String> {
+        self.0.read().await.keys().cloned().collect()
+    }
+    pub(crate) async fn requests(&self) -> Vec<WebSocketMessage> {
+        self.0.read().await.values().cloned().collect()
     }
 }
 
@@ -97,18 +291,61 @@ pub struct NostrRelay {
     writer: NostrWriter,
     reader: NostrReader,
     state: RelayStatus,
+    reconnect: ReconnectConfig,
+    config: RelayConfig,
+    closing: Arc<AtomicBool>,
+    // Replayed after a reconnect.
+    subscriptions: Subscriptions,
 }
 impl NostrRelay {
     pub async fn state(&self) -> WebsocketStatus {
         self.state.state().await.clone()
     }
-    pub fn new(relay_string: &str) -> anyhow::Result<Self> {
-        Url::new(&relay_string)?;
+    pub async fn connect_state(&self) -> RelayConnectState {
+        self.state.connect_state().await
+    }
+    pub async fn wait_for_open(&self) -> anyhow::Result<()> {
+        self.state.wait_for_open().await
+    }
+    pub fn state_watch(&self) -> watch::Receiver<WebsocketStatus> {
+        self.state.subscribe()
+    }
+    pub fn new(relay_string: &str) -> Result<Self, RelayError> {
+        Self::new_with_reconnect(relay_string, ReconnectConfig::default())
+    }
+    pub fn new_with_reconnect(
+        relay_string: &str,
+        reconnect: ReconnectConfig,
+    ) -> Result<Self, RelayError> {
+        Self::spawn(relay_string, reconnect, RelayConfig::default())
+    }
+    pub fn with_config(relay_string: &str, config: RelayConfig) -> Result<Self, RelayError> {
+        Self::spawn(relay_string, ReconnectConfig::default(), config)
+    }
+    /// Round trip of the last keepalive ping the relay answered.
+    pub fn ping_latency(&self) -> Option<Duration> {
+        self.reader
+            .1
+            .latency
+            .lock()
+            .ok()
+            .and_then(|latency| *latency)
+    }
+    fn spawn(
+        relay_string: &str,
+        reconnect: ReconnectConfig,
+        config: RelayConfig,
+    ) -> Result<Self, RelayError> {
+        Url::new(relay_string)?;
         let relay = NostrRelay {
             url: relay_string.to_string(),
             reader: NostrReader::new(),
             writer: NostrWriter::new(),
             state: RelayStatus::new(),
+            reconnect,
+            config,
+            closing: Arc::new(AtomicBool::new(false)),
+            subscriptions: Subscriptions::default(),
         };
         let relay_clone = relay.clone();
         crate::relays::spawn_thread(async move {
@@ -116,32 +353,168 @@ impl NostrRelay {
                 relay_clone.state.disconnected(e.to_string()).await;
             }
         });
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(interval) = config.keepalive_interval {
+            crate::relays::spawn_thread(relay.clone().keepalive(interval));
+        }
         Ok(relay)
     }
+    // Holds `closing` weakly, so the task ends once every clone of the relay is gone.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn keepalive(self, interval: Duration) -> impl std::future::Future<Output = ()> {
+        let closing = Arc::downgrade(&self.closing);
+        let (writer, state, heartbeat) = (self.writer, self.state, self.reader.1);
+        let write_timeout = self.config.write_timeout;
+        let url = self.url;
+        async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                match closing.upgrade() {
+                    Some(closing) if !closing.load(Ordering::SeqCst) => {}
+                    _ => break,
+                }
+                if state.state().await != WebsocketStatus::Open {
+                    continue;
+                }
+                if heartbeat.is_stale(interval * 2) {
+                    tracing::warn!(%url, "Relay stopped answering pings");
+                    heartbeat.stale.notify_waiters();
+                    continue;
+                }
+                let ping = WebSocketMessage::Ping(Default::default());
+                if writer.send(ping, write_timeout).await.is_ok() {
+                    heartbeat.pinged();
+                }
+            }
+        }
+    }
     pub async fn connect(&self) -> anyhow::Result<()> {
         let relay_url = Url::new(&self.url)?;
         #[cfg(not(target_arch = "wasm32"))]
-        let (websocket, _response) = tokio_tungstenite::connect_async(relay_url.url).await?;
+        let connecting = tokio_tungstenite::connect_async(relay_url.url);
         #[cfg(target_arch = "wasm32")]
-        let websocket = tokio_tungstenite_wasm::connect(relay_url.url).await?;
+        let connecting = tokio_tungstenite_wasm::connect(relay_url.url);
+        let websocket = crate::relays::timeout(self.config.connect_timeout, connecting)
+            .await
+            .ok_or(RelayError::Timeout("connect"))??;
+        #[cfg(not(target_arch = "wasm32"))]
+        let (websocket, _response) = websocket;
         let (websocket_writer, websocket_reader) = websocket.split();
         let mut writer = self.writer.0.write().await;
         let mut reader = self.reader.0.write().await;
         *writer = Some(websocket_writer);
         *reader = Some(websocket_reader);
+        self.reader.1.reset();
         self.state.connected().await;
         Ok(())
     }
+    async fn reconnect(&self) {
+        for attempt in 1..=self.reconnect.max_retries {
+            self.state.reconnecting(attempt);
+            crate::relays::sleep(self.reconnect.delay(attempt)).await;
+            if self.closing.load(Ordering::SeqCst) {
+                break;
+            }
+            match self.connect().await {
+                Ok(()) => {
+                    tracing::info!(url = %self.url, attempt, "Reconnected to relay");
+                    for subscription in self.subscriptions.requests().await {
+                        let sent = self.writer.send(subscription, self.config.write_timeout);
+                        if let Err(e) = sent.await {
+                            tracing::warn!("Failed to resubscribe to {}: {}", self.url, e);
+                        }
+                    }
+                    return;
+                }
+                Err(e) => tracing::warn!(url = %self.url, attempt, "Reconnect failed: {}", e),
+            }
+        }
+        self.state
+            .disconnected(format!(
+                "Gave up after {} reconnect attempts",
+                self.reconnect.max_retries
+            ))
+            .await;
+    }
+    /// Whether `close` was called on this relay or one of its clones.
+    pub fn is_closed(&self) -> bool {
+        self.closing.load(Ordering::SeqCst)
+    }
+    /// Ids of the subscriptions sent to this relay that are still open.
+    pub async fn subscription_ids(&self) -> Vec<String> {
+        self.subscriptions.ids().await
+    }
     pub async fn send_to_relay(&self, note: crate::relays::WebSocketMessage) -> anyhow::Result<()> {
         self.state.wait_for_open().await?;
-        self.writer.send(note.clone()).await?;
+        self.writer
+            .send(note.clone(), self.config.write_timeout)
+            .await?;
+        self.subscriptions.track(&note).await;
         Ok(())
     }
+    /// Answers a NIP-42 `AUTH` challenge from this relay, signing with `keypair`.
+    pub async fn send_auth(
+        &self,
+        keypair: &crate::keypair::NostrKeypair,
+        challenge: &str,
+    ) -> anyhow::Result<()> {
+        let note = crate::nips::Nip42::auth_note(keypair, &self.url, challenge);
+        self.send_to_relay(super::AuthEvent::from(note).into())
+            .await
+    }
+    /// Waits out reconnects, so a dropped socket only ends the stream once
+    /// `ReconnectConfig::max_retries` attempts have failed or the relay was closed.
     pub async fn next_relay_event(&self) -> Option<RelayEvent> {
-        self.state.wait_for_open().await.ok()?;
-        self.reader.read().await
+        let mut pinged = false;
+        loop {
+            self.state.wait_for_open().await.ok()?;
+            let read = crate::relays::timeout(self.config.read_timeout, self.reader.read());
+            let read = tokio::select! {
+                read = read => read,
+                _ = self.reader.1.stale.notified() => Some(None),
+            };
+            let event = match read {
+                Some(event) => event,
+                None if !pinged => {
+                    pinged = true;
+                    // Browsers answer pings themselves, so WASM only waits out a second timeout.
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        let ping = WebSocketMessage::Ping(Default::default());
+                        let _ = self.writer.send(ping, self.config.write_timeout).await;
+                    }
+                    continue;
+                }
+                None => {
+                    tracing::warn!(url = %self.url, "Relay went silent");
+                    None
+                }
+            };
+            pinged = false;
+            match event {
+                None | Some(RelayEvent::Close(_))
+                    if self.reconnect.max_retries > 0 && !self.closing.load(Ordering::SeqCst) =>
+                {
+                    if self.state.start_reconnecting() {
+                        tracing::warn!(url = %self.url, "Relay dropped, reconnecting");
+                        let relay = self.clone();
+                        crate::relays::spawn_thread(async move { relay.reconnect().await });
+                    }
+                }
+                event => {
+                    if let Some(RelayEvent::ClosedSubscription((_, ref id))) = event {
+                        self.subscriptions.remove(id).await;
+                    }
+                    return event;
+                }
+            }
+        }
     }
     pub async fn close(self) {
+        self.closing.store(true, Ordering::SeqCst);
         self.writer.close().await;
         drop(self);
     }
@@ -178,6 +551,7 @@ mod tests {
     //#[tokio::test]
     //#[tracing_test::traced_test]
     //#[wasm_bindgen_test::wasm_bindgen_test]
+    #[allow(clippy::single_match)]
     async fn _single_stress() -> Result<(), anyhow::Error> {
         use super::*;
         let relay = NostrRelay::new("wss://relay.arrakis.lat")?;
@@ -208,6 +582,7 @@ mod tests {
     #[tokio::test]
     #[tracing_test::traced_test]
     //#[wasm_bindgen_test::wasm_bindgen_test]
+    #[allow(clippy::single_match)]
     async fn _test_single_relay() -> Result<(), anyhow::Error> {
         use super::*;
         let relay = NostrRelay::new("wss://relay.arrakis.lat")?;
@@ -237,6 +612,7 @@ mod tests {
     }
     #[tokio::test]
     // #[wasm_bindgen_test::wasm_bindgen_test]
+    #[allow(clippy::single_match, clippy::useless_conversion)]
     async fn _test_relay_send_note() -> Result<(), anyhow::Error> {
         use super::*;
         let relay = NostrRelay::new("wss://relay.illuminodes.com")?;
@@ -247,7 +623,7 @@ mod tests {
             content: "Hello, world!".to_string(),
             ..Default::default()
         };
-        user_keys.sign_nostr_event(&mut note);
+        user_keys.sign_note(&mut note).expect("Failed to sign note");
         relay.send_to_relay(note.into()).await?;
         let mut sent = false;
         while let Some(event) = relay.reader.read().await {
@@ -267,4 +643,257 @@ mod tests {
         assert!(sent);
         Ok(())
     }
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_state_watch() {
+        use super::*;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        crate::relays::spawn_thread(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let _socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            std::future::pending::<()>().await;
+        });
+        let relay = NostrRelay::new(&url).unwrap();
+        let mut state = relay.state_watch();
+        let open = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            state.wait_for(|status| *status != WebsocketStatus::Connecting),
+        )
+        .await
+        .expect("Timed out waiting for relay state")
+        .unwrap()
+        .clone();
+        assert_eq!(open, WebsocketStatus::Open);
+        relay.wait_for_open().await.unwrap();
+
+        let refused_url = {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            format!("ws://{}", listener.local_addr().unwrap())
+        };
+        let refused = NostrRelay::new(&refused_url).unwrap();
+        let mut refused_state = refused.state_watch();
+        let closed = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            refused_state.wait_for(|status| *status != WebsocketStatus::Connecting),
+        )
+        .await
+        .expect("Timed out waiting for relay state")
+        .unwrap()
+        .clone();
+        assert!(matches!(closed, WebsocketStatus::Closed(_)));
+        assert!(refused.wait_for_open().await.is_err());
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_relay_timeouts() {
+        use super::*;
+        // Accepts TCP connections but never completes the websocket handshake.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let config = RelayConfig {
+            connect_timeout: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let started = std::time::Instant::now();
+        let relay = NostrRelay::with_config(&url, config).unwrap();
+        let error = tokio::time::timeout(Duration::from_secs(2), relay.wait_for_open())
+            .await
+            .expect("Connect timeout did not fire")
+            .unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(error.to_string().contains("Relay connect timed out"));
+        drop(listener);
+
+        // Completes the handshake, then neither sends nor reads anything, so pings go unanswered.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        crate::relays::spawn_thread(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let _socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            std::future::pending::<()>().await;
+        });
+        let config = RelayConfig {
+            read_timeout: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let mut relay = NostrRelay::with_config(&url, config).unwrap();
+        relay.reconnect.max_retries = 0;
+        relay.wait_for_open().await.unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(2), relay.next_relay_event())
+            .await
+            .expect("Read timeout did not fire");
+        assert_eq!(event, None);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_keepalive() {
+        use super::*;
+        // Reads every frame, so tungstenite answers the keepalive pings.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        crate::relays::spawn_thread(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            while let Some(Ok(_)) = socket.next().await {}
+        });
+        let config = RelayConfig {
+            keepalive_interval: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        let relay = NostrRelay::with_config(&url, config).unwrap();
+        relay.wait_for_open().await.unwrap();
+        assert_eq!(relay.ping_latency(), None);
+        let event = tokio::time::timeout(Duration::from_secs(2), relay.next_relay_event())
+            .await
+            .expect("No pong received");
+        assert_eq!(event, Some(RelayEvent::Ping));
+        assert!(relay.ping_latency().is_some());
+
+        // Never reads, so pings go unanswered long before the read timeout.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        crate::relays::spawn_thread(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let _socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            std::future::pending::<()>().await;
+        });
+        let mut relay = NostrRelay::with_config(&url, config).unwrap();
+        relay.reconnect.max_retries = 0;
+        relay.wait_for_open().await.unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(2), relay.next_relay_event())
+            .await
+            .expect("Unanswered pings were not noticed");
+        assert_eq!(event, None);
+        assert_eq!(relay.ping_latency(), None);
+        relay.close().await;
+    }
+
+    #[test]
+    fn test_reconnect_delay() {
+        use super::*;
+        let config = ReconnectConfig {
+            max_retries: 10,
+            base_delay_ms: 100,
+            max_delay_ms: 1_000,
+        };
+        for (attempt, max) in [(1, 100), (2, 200), (4, 800), (5, 1_000), (10, 1_000)] {
+            let delay = config.delay(attempt);
+            assert!(
+                delay >= Duration::from_millis(max / 2),
+                "attempt {}",
+                attempt
+            );
+            assert!(delay <= Duration::from_millis(max), "attempt {}", attempt);
+        }
+        assert!(config.delay(u32::MAX) <= Duration::from_secs(1));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_reconnect_resubscribes() {
+        use super::*;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let (message_tx, mut message_rx) = tokio::sync::mpsc::unbounded_channel();
+        crate::relays::spawn_thread(async move {
+            // The first connection drops after the client closes a subscription,
+            // the second one answers every `REQ` with `EOSE`.
+            for connection in 1..=2 {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+                while let Some(Ok(WebSocketMessage::Text(text))) = socket.next().await {
+                    let request: serde_json::Value = serde_json::from_str(&text).unwrap();
+                    let _ = message_tx.send((connection, request.clone()));
+                    if connection == 1 && request[0] == "CLOSE" {
+                        break;
+                    }
+                    if connection == 2 && request[0] == "REQ" {
+                        let eose = serde_json::json!(["EOSE", request[1]]).to_string();
+                        let _ = socket.send(WebSocketMessage::Text(eose.into())).await;
+                    }
+                }
+            }
+            std::future::pending::<()>().await;
+        });
+
+        let config = ReconnectConfig {
+            max_retries: 3,
+            base_delay_ms: 10,
+            max_delay_ms: 50,
+        };
+        let relay = NostrRelay::new_with_reconnect(&url, config).unwrap();
+        relay.wait_for_open().await.unwrap();
+        assert_eq!(relay.connect_state().await, RelayConnectState::Connected);
+        let kept: SubscribeEvent = NostrSubscription::default().into();
+        let closed: SubscribeEvent = NostrSubscription::default().into();
+        let (kept_id, closed_id) = (kept.1.clone(), closed.1.clone());
+        relay.send_to_relay(kept.into()).await.unwrap();
+        relay.send_to_relay(closed.into()).await.unwrap();
+        let close: WebSocketMessage = crate::relays::CloseEvent::from(closed_id.clone()).into();
+        relay.send_to_relay(close).await.unwrap();
+
+        let event = tokio::time::timeout(Duration::from_secs(5), relay.next_relay_event())
+            .await
+            .expect("Timed out waiting for the relay to reconnect");
+        assert_eq!(
+            event,
+            Some(RelayEvent::EndOfSubscription((
+                crate::relays::RelayEventTag::EOSE,
+                kept_id.clone()
+            )))
+        );
+        assert_eq!(relay.connect_state().await, RelayConnectState::Connected);
+
+        let mut resent = vec![];
+        while let Ok((connection, request)) = message_rx.try_recv() {
+            if connection == 2 {
+                resent.push(request);
+            }
+        }
+        assert_eq!(resent.len(), 1);
+        assert_eq!(resent[0][0], "REQ");
+        assert_eq!(resent[0][1], kept_id.as_str());
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn test_reconnect_gives_up() {
+        use super::*;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        crate::relays::spawn_thread(async move {
+            // Accept once, then drop both the socket and the listener.
+            let (stream, _) = listener.accept().await.unwrap();
+            let socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            drop(socket);
+        });
+        let config = ReconnectConfig {
+            max_retries: 2,
+            base_delay_ms: 10,
+            max_delay_ms: 20,
+        };
+        let relay = NostrRelay::new_with_reconnect(&url, config).unwrap();
+        relay.wait_for_open().await.unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(5), relay.next_relay_event())
+            .await
+            .expect("Timed out waiting for the relay to give up");
+        assert_eq!(event, None);
+        assert_eq!(relay.connect_state().await, RelayConnectState::Failed);
+    }
+
+    #[test]
+    fn test_relay_url_validation() {
+        use super::*;
+        for url in ["ftp://example.com", "not-a-url", "", "wss://"] {
+            assert!(matches!(
+                NostrRelay::new(url),
+                Err(RelayError::InvalidUrl(_))
+            ));
+        }
+        assert!(Url::new("ws://127.0.0.1:8080").is_ok());
+        assert!(Url::new("wss://relay.illuminodes.com").is_ok());
+    }
 }
@@ -12,6 +12,8 @@ pub enum RelayEventTag {
     CLOSE,
     CLOSED,
     REQ,
+    AUTH,
+    COUNT,
 }
 // FROM RELAY TO CLIENT 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -22,78 +24,269 @@ pub enum RelayEvent {
     EndOfSubscription((RelayEventTag, String)),
     ClosedSubscription((RelayEventTag, String)),
     Notice((RelayEventTag, String)),
+    Auth((RelayEventTag, String)),
+    Count((RelayEventTag, String, u64)),
     Ping,
     Close(String),
+    /// Raised by the pool, never sent by a relay: outbound messages a relay task skipped.
+    #[serde(skip)]
+    Lag(u64),
+}
+#[derive(Debug)]
+pub enum RelayParseError {
+    InvalidJson(serde_json::Error),
+    UnknownMessageType(String),
+    MalformedEvent(String),
+}
+impl std::fmt::Display for RelayParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RelayParseError::InvalidJson(e) => write!(f, "Invalid relay message JSON: {}", e),
+            RelayParseError::UnknownMessageType(kind) => {
+                write!(f, "Unknown relay message type: {}", kind)
+            }
+            RelayParseError::MalformedEvent(reason) => {
+                write!(f, "Malformed relay message: {}", reason)
+            }
+        }
+    }
+}
+impl std::error::Error for RelayParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RelayParseError::InvalidJson(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+impl From<serde_json::Error> for RelayParseError {
+    fn from(value: serde_json::Error) -> Self {
+        RelayParseError::InvalidJson(value)
+    }
+}
+
+impl RelayEvent {
+    /// Unlike the untagged TryFrom impls, this checks the message type and
+    /// element count before deserializing anything.
+    pub fn parse_strict(s: &str) -> Result<Self, RelayParseError> {
+        let message: Vec<serde_json::Value> = serde_json::from_str(s)?;
+        let kind = match message.first() {
+            Some(serde_json::Value::String(kind)) => kind.as_str(),
+            _ => {
+                return Err(RelayParseError::MalformedEvent(
+                    "message type must be a string".to_string(),
+                ))
+            }
+        };
+        let expected_len = match kind {
+            "EVENT" | "CLOSED" | "COUNT" => 3,
+            "OK" => 4,
+            "EOSE" | "NOTICE" | "AUTH" => 2,
+            _ => return Err(RelayParseError::UnknownMessageType(kind.to_string())),
+        };
+        if message.len() != expected_len {
+            return Err(RelayParseError::MalformedEvent(format!(
+                "{} must have {} elements, got {}",
+                kind,
+                expected_len,
+                message.len()
+            )));
+        }
+        let string_at = |index: usize| {
+            message[index].as_str().map(str::to_string).ok_or_else(|| {
+                let reason = format!("{} element {} must be a string", kind, index);
+                RelayParseError::MalformedEvent(reason)
+            })
+        };
+        match kind {
+            "EVENT" => {
+                let note = NostrNote::deserialize(&message[2])
+                    .map_err(|e| RelayParseError::MalformedEvent(format!("EVENT note: {}", e)))?;
+                Ok(RelayEvent::NewNote((RelayEventTag::EVENT, string_at(1)?, note)))
+            }
+            "OK" => {
+                let accepted = message[2].as_bool().ok_or_else(|| {
+                    RelayParseError::MalformedEvent("OK element 2 must be a boolean".to_string())
+                })?;
+                Ok(RelayEvent::SentOk((RelayEventTag::OK, string_at(1)?, accepted, string_at(3)?)))
+            }
+            "EOSE" => Ok(RelayEvent::EndOfSubscription((RelayEventTag::EOSE, string_at(1)?))),
+            "NOTICE" => Ok(RelayEvent::Notice((RelayEventTag::NOTICE, string_at(1)?))),
+            "AUTH" => Ok(RelayEvent::Auth((RelayEventTag::AUTH, string_at(1)?))),
+            "COUNT" => {
+                let count = message[2]["count"].as_u64().ok_or_else(|| {
+                    RelayParseError::MalformedEvent("COUNT element 2 must hold a count".to_string())
+                })?;
+                Ok(RelayEvent::Count((RelayEventTag::COUNT, string_at(1)?, count)))
+            }
+            _ => {
+                // ClosedSubscription has no room for the reason, but it must still be a string.
+                string_at(2)?;
+                Ok(RelayEvent::ClosedSubscription((RelayEventTag::CLOSED, string_at(1)?)))
+            }
+        }
+    }
+}
+impl std::str::FromStr for RelayEvent {
+    type Err = RelayParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RelayEvent::parse_strict(s)
+    }
 }
 impl TryFrom<String> for RelayEvent {
-    type Error = serde_json::Error;
+    type Error = RelayParseError;
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        serde_json::from_str(&value)
+        RelayEvent::parse_strict(&value)
     }
 }
 impl TryFrom<&String> for RelayEvent {
-    type Error = serde_json::Error;
+    type Error = RelayParseError;
     fn try_from(value: &String) -> Result<Self, Self::Error> {
-        serde_json::from_str(value)
+        RelayEvent::parse_strict(value)
     }
 }
 #[cfg(not(target_arch = "wasm32"))]
 impl TryFrom<&Utf8Bytes> for RelayEvent {
-    type Error = serde_json::Error;
+    type Error = RelayParseError;
     fn try_from(value: &Utf8Bytes) -> Result<Self, Self::Error> {
-        serde_json::from_str(value)
+        RelayEvent::parse_strict(value)
     }
 }
 #[cfg(not(target_arch = "wasm32"))]
 impl TryFrom<Utf8Bytes> for RelayEvent {
-    type Error = serde_json::Error;
+    type Error = RelayParseError;
     fn try_from(value: Utf8Bytes) -> Result<Self, Self::Error> {
-        serde_json::from_str(value.as_str())
+        RelayEvent::parse_strict(value.as_str())
     }
 }
 impl TryFrom<&str> for RelayEvent {
-    type Error = serde_json::Error;
+    type Error = RelayParseError;
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        serde_json::from_str(value)
+        RelayEvent::parse_strict(value)
+    }
+}
+// Hand the event back on mismatch so callers can keep matching on it.
+impl TryFrom<RelayEvent> for NostrNote {
+    type Error = RelayEvent;
+    fn try_from(value: RelayEvent) -> Result<Self, Self::Error> {
+        match value {
+            RelayEvent::NewNote((_, _, note)) => Ok(note),
+            other => Err(other),
+        }
+    }
+}
+impl TryFrom<RelayEvent> for (RelayEventTag, String) {
+    type Error = RelayEvent;
+    fn try_from(value: RelayEvent) -> Result<Self, Self::Error> {
+        match value {
+            RelayEvent::EndOfSubscription(end_of_subscription) => Ok(end_of_subscription),
+            other => Err(other),
+        }
     }
 }
 
 // FROM CLIENT TO RELAY
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Clone)]
 pub struct SubscribeEvent(pub RelayEventTag, pub String, pub super::NostrSubscription);
-impl Into<String> for SubscribeEvent {
-    fn into(self) -> String {
-        serde_json::to_string(&self).unwrap()
+// Wire format sent to relays: `["REQ","<id>",{<filter>},...]`, one object per filter.
+impl Serialize for SubscribeEvent {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeSeq;
+        let mut seq = serializer.serialize_seq(Some(2 + self.2.filters().count()))?;
+        seq.serialize_element(&self.0)?;
+        seq.serialize_element(&self.1)?;
+        for filter in self.2.filters() {
+            seq.serialize_element(filter)?;
+        }
+        seq.end()
+    }
+}
+impl<'de> Deserialize<'de> for SubscribeEvent {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SubscribeVisitor;
+        impl<'de> serde::de::Visitor<'de> for SubscribeVisitor {
+            type Value = SubscribeEvent;
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a REQ message with at least one filter")
+            }
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let missing = |index| serde::de::Error::invalid_length(index, &self);
+                let tag = seq.next_element()?.ok_or_else(|| missing(0))?;
+                let id = seq.next_element()?.ok_or_else(|| missing(1))?;
+                let mut subscription: super::NostrSubscription =
+                    seq.next_element()?.ok_or_else(|| missing(2))?;
+                while let Some(filter) = seq.next_element()? {
+                    subscription = subscription.with_additional_filter(filter);
+                }
+                Ok(SubscribeEvent(tag, id, subscription))
+            }
+        }
+        deserializer.deserialize_seq(SubscribeVisitor)
+    }
+}
+impl std::fmt::Display for SubscribeEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&serde_json::to_string(self).map_err(|_| std::fmt::Error)?)
+    }
+}
+impl From<SubscribeEvent> for String {
+    fn from(value: SubscribeEvent) -> Self {
+        value.to_string()
     }
 }
 #[cfg(not(target_arch = "wasm32"))]
-impl Into<Utf8Bytes> for SubscribeEvent {
-    fn into(self) -> Utf8Bytes {
-        serde_json::to_string(&self).unwrap().into()
+impl From<SubscribeEvent> for Utf8Bytes {
+    fn from(value: SubscribeEvent) -> Self {
+        value.to_string().into()
     }
 }
-impl Into<crate::relays::WebSocketMessage> for SubscribeEvent {
-    fn into(self) -> crate::relays::WebSocketMessage {
-        crate::relays::WebSocketMessage::Text(self.into())
+impl From<SubscribeEvent> for crate::relays::WebSocketMessage {
+    fn from(value: SubscribeEvent) -> Self {
+        crate::relays::WebSocketMessage::Text(value.into())
     }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SendNoteEvent(pub RelayEventTag, pub NostrNote);
-impl Into<String> for SendNoteEvent {
-    fn into(self) -> String {
-        serde_json::to_string(&self).unwrap()
+impl From<SendNoteEvent> for String {
+    fn from(value: SendNoteEvent) -> Self {
+        serde_json::to_string(&value).unwrap()
     }
 }
 #[cfg(not(target_arch = "wasm32"))]
-impl Into<Utf8Bytes> for SendNoteEvent {
-    fn into(self) -> Utf8Bytes {
-        serde_json::to_string(&self).unwrap().into()
+impl From<SendNoteEvent> for Utf8Bytes {
+    fn from(value: SendNoteEvent) -> Self {
+        serde_json::to_string(&value).unwrap().into()
     }
 }
-impl Into<crate::relays::WebSocketMessage> for SendNoteEvent {
-    fn into(self) -> crate::relays::WebSocketMessage {
-        crate::relays::WebSocketMessage::Text(self.into())
+impl From<SendNoteEvent> for crate::relays::WebSocketMessage {
+    fn from(value: SendNoteEvent) -> Self {
+        crate::relays::WebSocketMessage::Text(value.into())
+    }
+}
+
+// NIP-42 `["AUTH", <signed kind 22242 note>]`, answering a relay's challenge.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuthEvent(pub RelayEventTag, pub NostrNote);
+impl From<NostrNote> for AuthEvent {
+    fn from(value: NostrNote) -> Self {
+        AuthEvent(RelayEventTag::AUTH, value)
+    }
+}
+impl From<AuthEvent> for String {
+    fn from(value: AuthEvent) -> Self {
+        serde_json::to_string(&value).unwrap()
+    }
+}
+#[cfg(not(target_arch = "wasm32"))]
+impl From<AuthEvent> for Utf8Bytes {
+    fn from(value: AuthEvent) -> Self {
+        serde_json::to_string(&value).unwrap().into()
+    }
+}
+impl From<AuthEvent> for crate::relays::WebSocketMessage {
+    fn from(value: AuthEvent) -> Self {
+        crate::relays::WebSocketMessage::Text(value.into())
     }
 }
 
@@ -104,19 +297,184 @@ impl From<String> for CloseEvent {
         CloseEvent(RelayEventTag::CLOSE, value)
     }
 }
-impl Into<String> for CloseEvent {
-    fn into(self) -> String {
-        serde_json::to_string(&self).unwrap()
+// Wire format sent to relays: `["CLOSE","<id>"]`.
+impl std::fmt::Display for CloseEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&serde_json::to_string(self).map_err(|_| std::fmt::Error)?)
+    }
+}
+impl From<CloseEvent> for String {
+    fn from(value: CloseEvent) -> Self {
+        value.to_string()
     }
 }
 #[cfg(not(target_arch = "wasm32"))]
-impl Into<Utf8Bytes> for CloseEvent {
-    fn into(self) -> Utf8Bytes {
-        serde_json::to_string(&self).unwrap().into()
+impl From<CloseEvent> for Utf8Bytes {
+    fn from(value: CloseEvent) -> Self {
+        value.to_string().into()
     }
 }
-impl Into<crate::relays::WebSocketMessage> for CloseEvent {
-    fn into(self) -> crate::relays::WebSocketMessage {
-        crate::relays::WebSocketMessage::Text(self.into())
+impl From<CloseEvent> for crate::relays::WebSocketMessage {
+    fn from(value: CloseEvent) -> Self {
+        crate::relays::WebSocketMessage::Text(value.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_strict() {
+        let note = serde_json::json!({
+            "pubkey": "a".repeat(64),
+            "created_at": 1714628274,
+            "kind": 1,
+            "tags": [],
+            "content": "strict",
+        });
+        let event = serde_json::json!(["EVENT", "sub", note]).to_string();
+        assert!(matches!(
+            RelayEvent::parse_strict(&event),
+            Ok(RelayEvent::NewNote((_, ref id, _))) if id == "sub"
+        ));
+        assert!(matches!(
+            RelayEvent::parse_strict(r#"["OK","id",true,""]"#),
+            Ok(RelayEvent::SentOk((_, _, true, _)))
+        ));
+        assert!(matches!(
+            RelayEvent::parse_strict(r#"["EOSE","sub"]"#),
+            Ok(RelayEvent::EndOfSubscription(_))
+        ));
+        assert!(matches!(
+            RelayEvent::parse_strict(r#"["NOTICE","slow down"]"#),
+            Ok(RelayEvent::Notice(_))
+        ));
+        assert!(matches!(
+            RelayEvent::parse_strict(r#"["CLOSED","sub","error: shutting down"]"#),
+            Ok(RelayEvent::ClosedSubscription((_, ref id))) if id == "sub"
+        ));
+
+        assert!(matches!(
+            RelayEvent::parse_strict(r#"["EOSE","sub""#),
+            Err(RelayParseError::InvalidJson(_))
+        ));
+        assert!(matches!(
+            r#"["AUTH","challenge"]"#.parse::<RelayEvent>(),
+            Ok(RelayEvent::Auth((_, ref challenge))) if challenge == "challenge"
+        ));
+        assert!(matches!(
+            RelayEvent::try_from(r#"["COUNT","sub",{"count":42}]"#),
+            Ok(RelayEvent::Count((_, _, 42)))
+        ));
+        assert!(matches!(
+            RelayEvent::parse_strict(r#"["HELLO","relay"]"#),
+            Err(RelayParseError::UnknownMessageType(ref kind)) if kind == "HELLO"
+        ));
+        for malformed in [
+            r#"["EVENT","sub"]"#,
+            r#"["EOSE","sub","extra"]"#,
+            r#"["OK","id","yes",""]"#,
+            r#"["NOTICE",42]"#,
+            r#"["EVENT","sub",{"content":"no fields"}]"#,
+            r#"[1,"sub"]"#,
+            r#"["COUNT","sub",42]"#,
+            r#"["AUTH"]"#,
+        ] {
+            assert!(
+                matches!(
+                    RelayEvent::parse_strict(malformed),
+                    Err(RelayParseError::MalformedEvent(_))
+                ),
+                "{} should be malformed",
+                malformed
+            );
+        }
+    }
+
+    #[test]
+    fn test_extract_from_relay_event() {
+        let note = NostrNote {
+            content: "extracted".to_string(),
+            ..Default::default()
+        };
+        let new_note = RelayEvent::NewNote((RelayEventTag::EVENT, "sub".to_string(), note.clone()));
+        let end_of_subscription = (RelayEventTag::EOSE, "sub".to_string());
+        let events = vec![
+            new_note.clone(),
+            RelayEvent::SentOk((RelayEventTag::OK, "id".to_string(), true, String::new())),
+            RelayEvent::EndOfSubscription(end_of_subscription.clone()),
+            RelayEvent::ClosedSubscription((RelayEventTag::CLOSED, "sub".to_string())),
+            RelayEvent::Notice((RelayEventTag::NOTICE, "slow down".to_string())),
+            RelayEvent::Auth((RelayEventTag::AUTH, "challenge".to_string())),
+            RelayEvent::Count((RelayEventTag::COUNT, "sub".to_string(), 42)),
+            RelayEvent::Ping,
+            RelayEvent::Close("sub".to_string()),
+            RelayEvent::Lag(3),
+        ];
+        for event in events.clone() {
+            match NostrNote::try_from(event.clone()) {
+                Ok(extracted) => {
+                    assert_eq!(event, new_note);
+                    assert_eq!(extracted, note);
+                }
+                Err(returned) => assert_eq!(returned, event),
+            }
+            match <(RelayEventTag, String)>::try_from(event.clone()) {
+                Ok(extracted) => {
+                    assert_eq!(extracted, end_of_subscription);
+                    assert_eq!(RelayEvent::EndOfSubscription(extracted), event);
+                }
+                Err(returned) => assert_eq!(returned, event),
+            }
+        }
+        let notes: Vec<NostrNote> =
+            events.into_iter().filter_map(|event| NostrNote::try_from(event).ok()).collect();
+        assert_eq!(notes, vec![note]);
+    }
+
+    #[test]
+    fn test_client_messages_display_wire_format() {
+        let mut filter = crate::relays::NostrSubscription {
+            kinds: Some(vec![1]),
+            limit: Some(10),
+            ..Default::default()
+        };
+        filter.add_tag("#p", "pubkey");
+        let subscribe = SubscribeEvent(RelayEventTag::REQ, "sub".to_string(), filter);
+        let request: serde_json::Value = serde_json::from_str(&subscribe.to_string()).unwrap();
+        let request = request.as_array().expect("REQ should be an array");
+        assert_eq!(request.len(), 3);
+        assert_eq!(request[0], "REQ");
+        assert_eq!(request[1], "sub");
+        assert_eq!(request[2], serde_json::json!({"kinds": [1], "limit": 10, "#p": ["pubkey"]}));
+        assert_eq!(String::from(subscribe.clone()), subscribe.to_string());
+
+        let subscribe = SubscribeEvent(
+            RelayEventTag::REQ,
+            "multi".to_string(),
+            crate::relays::NostrSubscription::single(crate::relays::NostrSubscription {
+                kinds: Some(vec![0]),
+                ..Default::default()
+            })
+            .with_additional_filter(crate::relays::NostrSubscription {
+                kinds: Some(vec![1]),
+                limit: Some(5),
+                ..Default::default()
+            }),
+        );
+        let request: serde_json::Value = serde_json::from_str(&subscribe.to_string()).unwrap();
+        assert_eq!(
+            request,
+            serde_json::json!(["REQ", "multi", {"kinds": [0]}, {"kinds": [1], "limit": 5}])
+        );
+        let parsed: SubscribeEvent = serde_json::from_value(request).unwrap();
+        assert_eq!(parsed.to_string(), subscribe.to_string());
+        assert!(serde_json::from_str::<SubscribeEvent>(r#"["REQ","empty"]"#).is_err());
+
+        let close = CloseEvent::from("sub".to_string());
+        let request: serde_json::Value = serde_json::from_str(&close.to_string()).unwrap();
+        assert_eq!(request, serde_json::json!(["CLOSE", "sub"]));
+        assert_eq!(String::from(close.clone()), close.to_string());
     }
 }
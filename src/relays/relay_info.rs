@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+
+use super::NostrRelay;
+
+/// How long `relay_info` waits for the relay to answer, on native targets.
+pub const RELAY_INFO_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RelayInfoError {
+    InvalidUrl(String),
+    Network(String),
+    Parse(String),
+}
+impl std::fmt::Display for RelayInfoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RelayInfoError::InvalidUrl(e) => write!(f, "Invalid relay url: {}", e),
+            RelayInfoError::Network(e) => write!(f, "Failed to fetch relay info: {}", e),
+            RelayInfoError::Parse(e) => write!(f, "Failed to parse relay info: {}", e),
+        }
+    }
+}
+impl std::error::Error for RelayInfoError {}
+
+/// The NIP-11 information document a relay serves over HTTP.
+/// Every field is optional on the wire, missing ones are left empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RelayInfo {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub pubkey: Option<String>,
+    pub contact: Option<String>,
+    pub supported_nips: Vec<u32>,
+    pub software: Option<String>,
+    pub version: Option<String>,
+    pub limitation: Option<RelayLimitation>,
+}
+
+impl RelayInfo {
+    pub fn supports_nip(&self, nip: u32) -> bool {
+        self.supported_nips.contains(&nip)
+    }
+}
+
+impl super::NostrSubscription {
+    /// Whether a relay described by `info` can serve this filter: filters with
+    /// a `search` query need a relay that lists NIP-50.
+    pub fn supports_search(&self, info: &RelayInfo) -> bool {
+        self.filters().all(|filter| filter.search.is_none()) || info.supports_nip(50)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RelayLimitation {
+    pub max_message_length: Option<u64>,
+    pub max_subscriptions: Option<u64>,
+    pub max_filters: Option<u64>,
+    pub min_pow_difficulty: Option<u8>,
+    pub auth_required: Option<bool>,
+    pub payment_required: Option<bool>,
+}
+
+/// Fetches the NIP-11 document of the relay at `url`, a `ws://` or `wss://` address.
+pub async fn relay_info(url: &str) -> Result<RelayInfo, RelayInfoError> {
+    let mut http_url =
+        url::Url::parse(url).map_err(|e| RelayInfoError::InvalidUrl(e.to_string()))?;
+    let scheme = match http_url.scheme() {
+        "ws" | "http" => "http",
+        "wss" | "https" => "https",
+        scheme => {
+            return Err(RelayInfoError::InvalidUrl(format!(
+                "Unsupported scheme {}",
+                scheme
+            )))
+        }
+    };
+    http_url
+        .set_scheme(scheme)
+        .map_err(|_| RelayInfoError::InvalidUrl(url.to_string()))?;
+    #[cfg(not(target_arch = "wasm32"))]
+    let client = reqwest::Client::builder()
+        .timeout(RELAY_INFO_TIMEOUT)
+        .build()
+        .map_err(|e| RelayInfoError::Network(e.to_string()))?;
+    #[cfg(target_arch = "wasm32")]
+    let client = reqwest::Client::new();
+    let body = client
+        .get(http_url)
+        .header(reqwest::header::ACCEPT, "application/nostr+json")
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| RelayInfoError::Network(e.to_string()))?
+        .text()
+        .await
+        .map_err(|e| RelayInfoError::Network(e.to_string()))?;
+    serde_json::from_str(&body).map_err(|e| RelayInfoError::Parse(e.to_string()))
+}
+
+impl NostrRelay {
+    /// This relay's NIP-11 document, see `relay_info`.
+    pub async fn relay_info(&self) -> Result<RelayInfo, RelayInfoError> {
+        relay_info(&self.url).await
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::relays::NostrSubscription;
+    use crate::test_util::mock_http_server;
+
+    async fn mock_server(
+        status: &'static str,
+        body: &'static str,
+    ) -> (String, tokio::sync::mpsc::UnboundedReceiver<String>) {
+        let (address, requests) = mock_http_server(status, "application/nostr+json", body).await;
+        (format!("ws://{}", address), requests)
+    }
+
+    #[tokio::test]
+    async fn test_relay_info() {
+        let (url, mut requests) = mock_server(
+            "200 OK",
+            r#"{"name":"JellyFish","description":"Stay Immortal!","pubkey":"bf2bee5281149c7c350f5d12ae32f514c7864ff10805182f4178538c2c421007","contact":"hi@dezh.tech","supported_nips":[1,9,11,40],"software":"https://github.com/dezh-tech/immortal","version":"immortal - 0.0.9","limitation":{"max_message_length":70000,"max_subscriptions":350,"max_filters":10,"min_pow_difficulty":0,"auth_required":false},"icon":"https://example.com/icon.png"}"#,
+        )
+        .await;
+        let info = relay_info(&url).await.expect("Failed to fetch relay info");
+        assert_eq!(info.name.as_deref(), Some("JellyFish"));
+        assert_eq!(info.contact.as_deref(), Some("hi@dezh.tech"));
+        assert_eq!(info.supported_nips, vec![1, 9, 11, 40]);
+        let limitation = info.limitation.expect("Missing limitation");
+        assert_eq!(limitation.max_message_length, Some(70000));
+        assert_eq!(limitation.max_subscriptions, Some(350));
+        assert_eq!(limitation.max_filters, Some(10));
+        assert_eq!(limitation.min_pow_difficulty, Some(0));
+        assert_eq!(limitation.payment_required, None);
+
+        let request = requests.recv().await.unwrap().to_lowercase();
+        assert!(request.starts_with("get / http/1.1"));
+        assert!(request.contains("accept: application/nostr+json"));
+
+        let (url, _) = mock_server("200 OK", "{}").await;
+        assert_eq!(relay_info(&url).await, Ok(RelayInfo::default()));
+    }
+
+    #[test]
+    fn test_supports_search() {
+        let search = NostrSubscription::default().with_search("nostr");
+        let plain = NostrSubscription::default();
+        let searchable = RelayInfo {
+            supported_nips: vec![1, 11, 50],
+            ..Default::default()
+        };
+        assert!(searchable.supports_nip(50));
+        assert!(search.supports_search(&searchable));
+        assert!(!search.supports_search(&RelayInfo::default()));
+        assert!(plain.supports_search(&RelayInfo::default()));
+    }
+
+    #[tokio::test]
+    async fn test_relay_info_errors() {
+        assert!(matches!(
+            relay_info("not a url").await,
+            Err(RelayInfoError::InvalidUrl(_))
+        ));
+        assert!(matches!(
+            relay_info("ftp://relay.example.com").await,
+            Err(RelayInfoError::InvalidUrl(_))
+        ));
+        let (url, _) = mock_server("200 OK", "<html></html>").await;
+        assert!(matches!(
+            relay_info(&url).await,
+            Err(RelayInfoError::Parse(_))
+        ));
+        let (url, _) = mock_server("404 Not Found", "{}").await;
+        assert!(matches!(
+            relay_info(&url).await,
+            Err(RelayInfoError::Network(_))
+        ));
+    }
+}
@@ -5,6 +5,31 @@ pub use tokio::task::spawn as spawn_thread;
 #[cfg(target_arch = "wasm32")]
 pub use wasm_bindgen_futures::spawn_local as spawn_thread;
 
+#[cfg(target_arch = "wasm32")]
+pub use gloo_timers::future::sleep;
+#[cfg(not(target_arch = "wasm32"))]
+pub use tokio::time::sleep;
+
+/// Resolves to `None` if `future` does not finish within `duration`.
+pub(crate) async fn timeout<F: std::future::Future>(
+    duration: std::time::Duration,
+    future: F,
+) -> Option<F::Output> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        tokio::time::timeout(duration, future).await.ok()
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        use futures_util::future::{select, Either};
+        let future = std::pin::pin!(future);
+        match select(future, std::pin::pin!(sleep(duration))).await {
+            Either::Left((output, _)) => Some(output),
+            Either::Right(_) => None,
+        }
+    }
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub use tokio_tungstenite::tungstenite::Message as WebSocketMessage;
 #[cfg(target_arch = "wasm32")]
@@ -19,17 +44,40 @@ pub type NostrRelayStream = tokio_tungstenite_wasm::WebSocketStream;
 pub type NostrWebsocketReader = Option<SplitStream<NostrRelayStream>>;
 pub type NostrWebsocketWriter = Option<SplitSink<NostrRelayStream, WebSocketMessage>>;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RelayError {
+    InvalidUrl(String),
+    /// The operation, such as `"connect"` or `"write"`, ran past its `RelayConfig` timeout.
+    Timeout(&'static str),
+}
+impl std::fmt::Display for RelayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RelayError::InvalidUrl(reason) => write!(f, "Invalid relay url: {}", reason),
+            RelayError::Timeout(operation) => write!(f, "Relay {} timed out", operation),
+        }
+    }
+}
+impl std::error::Error for RelayError {}
+
 pub struct Url {
     pub url: String,
 }
 impl Url {
-    pub fn new(url: &str) -> anyhow::Result<Self> {
-        if url.starts_with("wss://") {
-            Ok(Url {
-                url: url.to_string(),
-            })
-        } else {
-            Err(anyhow::anyhow!("Invalid url, must start with wss://"))
+    pub fn new(url: &str) -> Result<Self, RelayError> {
+        let parsed =
+            url::Url::parse(url).map_err(|e| RelayError::InvalidUrl(format!("{}: {}", url, e)))?;
+        if !matches!(parsed.scheme(), "ws" | "wss") {
+            return Err(RelayError::InvalidUrl(format!(
+                "{}: scheme must be ws:// or wss://",
+                url
+            )));
+        }
+        if parsed.host_str().is_none_or(str::is_empty) {
+            return Err(RelayError::InvalidUrl(format!("{}: missing host", url)));
         }
+        Ok(Url {
+            url: url.to_string(),
+        })
     }
 }
@@ -0,0 +1,29 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Serves `body` with `status` and `content_type` to every request on a local
+/// port, sending each raw request back. Returns the `host:port` it listens on.
+pub(crate) async fn mock_http_server(
+    status: &'static str,
+    content_type: &'static str,
+    body: &'static str,
+) -> (String, tokio::sync::mpsc::UnboundedReceiver<String>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut request = vec![0u8; 4096];
+            let read = stream.read(&mut request).await.unwrap_or(0);
+            let _ = tx.send(String::from_utf8_lossy(&request[..read]).to_string());
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                content_type,
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        }
+    });
+    (address, rx)
+}
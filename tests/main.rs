@@ -30,6 +30,7 @@ mod tests {
     // Created and verified the signature of a note.
     #[cfg(not(target_arch = "wasm32"))]
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_create_note() {
         let content_of_note = "- .... .. ... / .. ... / .- / -- . ... ... .- --. .";
         let user_key_pair = NostrKeypair::new(PRIV).unwrap();
@@ -39,7 +40,9 @@ mod tests {
             content: content_of_note.to_string(),
             ..Default::default()
         };
-        user_key_pair.sign_nostr_event(&mut unsigned_note);
+        user_key_pair
+            .sign_note(&mut unsigned_note)
+            .expect("Failed to sign note");
         assert_eq!(unsigned_note.verify(), true);
     }
 
@@ -58,8 +61,10 @@ mod tests {
             .add_custom_tag(NostrTag::Custom("t"), "test");
         signed_note.tags.add_event_tag("adsfasdfadsfadsfasdfadfs");
         signed_note.tags.add_pubkey_tag("adsfasdfadsfadsfasdfadfs");
-        user_key_pair.sign_nostr_event(&mut signed_note);
-        let t_tags = signed_note.tags.find_tags(NostrTag::Custom("t"));
+        user_key_pair
+            .sign_note(&mut signed_note)
+            .expect("Failed to sign note");
+        let t_tags = signed_note.tags.find_tags_owned(NostrTag::Custom("t"));
         let t_tag = t_tags.first().expect("Failed to get tag!");
         assert_eq!(t_tag, "test");
         let p_tag = signed_note
@@ -84,7 +89,9 @@ mod tests {
             content: content_of_note.to_string(),
             ..Default::default()
         };
-        user_key_pair.sign_nostr_event(&mut signed_note);
+        user_key_pair
+            .sign_note(&mut signed_note)
+            .expect("Failed to sign note");
         signed_note.tags.add_pubkey_tag(&user_key_pair.public_key());
         signed_note
             .tags